@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 use chrono::{Duration as ChronoDuration, Utc};
 use serde::{Deserialize, Serialize};
@@ -7,13 +8,18 @@ use tracing::{info, warn};
 
 use crate::agents::{
     auth::{
-        auth_path_for_agent_type, build_google_auth_url, exchange_google_code, parse_google_id_token,
-        parse_rfc3339_to_epoch, refresh_google_token, save_auth_file, should_refresh_google,
-        AgentAuthContext, AgentAuthError, AuthFlowStart,
+        auth_path_for_agent_type, build_google_auth_url, exchange_google_code, load_auth_file,
+        parse_google_id_token, parse_rfc3339_to_epoch, refresh_google_token, save_auth_file,
+        send_honoring_retry_after, AgentAuthContext, AgentAuthError, AuthFlowStart,
+        StoredAuthSummary, TokenPayload,
+        validate_stored_token,
     },
     AgentMetadata, CodingAgentDefinition,
 };
-use crate::models::{AgentProviderType, AgentQuota, AgentQuotaEntry, AgentType};
+use crate::models::{AgentProviderType, AgentQuota, AgentQuotaEntry, AgentType, TokenValidity};
+
+/// User-Agent the Antigravity app sends; bump it when upstream starts rejecting old versions
+const ANTIGRAVITY_USER_AGENT: &str = "antigravity/1.11.3 Darwin/arm64";
 
 pub struct AntigravityAgent;
 
@@ -66,6 +72,14 @@ struct AntigravityTokenStorage {
     pub project_id: String,
 }
 
+impl TokenPayload for AntigravityTokenStorage {
+    const REFRESH_WINDOW: ChronoDuration = ChronoDuration::minutes(50);
+
+    fn expire(&self) -> &str {
+        &self.expire
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct FetchAvailableModelsResponse {
     models: HashMap<String, FetchAvailableModelInfo>,
@@ -119,6 +133,18 @@ struct OnboardResponseData {
     cloudaicompanion_project: Option<ProjectRef>,
 }
 
+/// Secret-free summary of the login stored at `path`
+pub(crate) async fn inspect_auth(path: &PathBuf) -> Result<StoredAuthSummary, AgentAuthError> {
+    let auth: AntigravityTokenStorage = load_auth_file(path).await?;
+    Ok(StoredAuthSummary::new(
+        &auth.email,
+        &auth.expire,
+        &auth.access_token,
+        &auth.refresh_token,
+        auth.needs_refresh(),
+    ))
+}
+
 pub(crate) fn start_auth_flow(state: &str) -> Result<AuthFlowStart, AgentAuthError> {
     let auth_url = build_google_auth_url(
         ANTIGRAVITY_CLIENT_ID,
@@ -192,7 +218,7 @@ pub(crate) async fn get_quota(
         .load_and_normalize_auth(agent_type)
         .await?;
 
-    if should_refresh_google(&auth.timestamp, auth.expires_in) {
+    if auth.needs_refresh() {
         auth = refresh_antigravity_token(ctx, &auth).await?;
         save_auth_file(&auth_path, &auth).await?;
     }
@@ -208,6 +234,15 @@ pub(crate) async fn get_quota(
     }
 }
 
+/// Refresh the stored token when due, then probe it (see `validate_agent_token`)
+pub(crate) async fn validate_token(
+    ctx: &AgentAuthContext,
+    agent_type: &AgentProviderType,
+) -> Result<TokenValidity, AgentAuthError> {
+    validate_stored_token(ctx, agent_type, refresh_antigravity_token, fetch_antigravity_quota)
+        .await
+}
+
 async fn refresh_antigravity_token(
     ctx: &AgentAuthContext,
     auth: &AntigravityTokenStorage,
@@ -244,15 +279,15 @@ async fn fetch_antigravity_quota(
         body["project"] = json!(auth.project_id.clone());
     }
 
-    let response = ctx
-        .http_client()
-        .await?
-        .post(ANTIGRAVITY_FETCH_MODELS_URL)
-        .bearer_auth(&auth.access_token)
-        .header("User-Agent", "antigravity/1.11.3 Darwin/arm64")
-        .json(&body)
-        .send()
-        .await?;
+    let client = ctx.http_client().await?;
+    let response = send_honoring_retry_after(|| {
+        client
+            .post(ANTIGRAVITY_FETCH_MODELS_URL)
+            .bearer_auth(&auth.access_token)
+            .header("User-Agent", ANTIGRAVITY_USER_AGENT)
+            .json(&body)
+    })
+    .await?;
 
     match response.status() {
         reqwest::StatusCode::UNAUTHORIZED => return Err(AgentAuthError::Unauthorized),
@@ -1,20 +1,25 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
 use rand::{distributions::Alphanumeric, Rng};
 use reqwest::{NoProxy, Proxy};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use tracing::{debug, warn};
 
-use crate::models::AgentProviderType;
-use crate::storage::ConfigStore;
+use super::{probe_validity, refresh_or_expired};
+use crate::models::{
+    redact_proxy_url, validate_proxy_url, AgentProviderType, ProxyMode, TokenValidity,
+};
+use crate::storage::{config_dir, ConfigStore};
 
 const GOOGLE_AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
 const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
 const GOOGLE_USERINFO_URL: &str = "https://www.googleapis.com/oauth2/v1/userinfo?alt=json";
+/// Longest `Retry-After` we are willing to sleep through before retrying a quota request
+const MAX_RETRY_AFTER_WAIT_SECS: u64 = 10;
 
 #[derive(Debug, thiserror::Error)]
 pub enum AgentAuthError {
@@ -28,6 +33,8 @@ pub enum AgentAuthError {
     InvalidCallback(String),
     #[error("Unauthorized - token expired or invalid")]
     Unauthorized,
+    #[error("Rate limited, retry after {0} seconds")]
+    RateLimited(u64),
     #[error("HTTP error: {0}")]
     Http(#[from] reqwest::Error),
     #[error("Storage error: {0}")]
@@ -51,7 +58,7 @@ pub struct AgentAuthContext {
     store: Arc<ConfigStore>,
 }
 
-fn auth_filename(agent_type: &AgentProviderType) -> &'static str {
+pub(crate) fn auth_filename(agent_type: &AgentProviderType) -> &'static str {
     match agent_type {
         AgentProviderType::Codex => "codex.json",
         AgentProviderType::ClaudeCode => "claude_code.json",
@@ -60,11 +67,28 @@ fn auth_filename(agent_type: &AgentProviderType) -> &'static str {
     }
 }
 
+/// Whether `name` follows the `<agent>*.json` naming of agent auth files
+pub(crate) fn is_agent_auth_filename(name: &str) -> bool {
+    let agents = [
+        AgentProviderType::Codex,
+        AgentProviderType::ClaudeCode,
+        AgentProviderType::GeminiCli,
+        AgentProviderType::Antigravity,
+    ];
+    name.ends_with(".json")
+        && agents
+            .iter()
+            .any(|agent| name.starts_with(auth_filename(agent).trim_end_matches(".json")))
+}
+
 /// Auth path for an agent type: ~/.vibemate/auth/<agent_type>.json
 pub fn auth_path_for_agent_type(agent_type: &AgentProviderType) -> Result<PathBuf, AgentAuthError> {
-    let home = dirs::home_dir()
-        .ok_or_else(|| AgentAuthError::Parse("Could not determine home directory".to_string()))?;
-    Ok(home.join(".vibemate").join("auth").join(auth_filename(agent_type)))
+    Ok(auth_path_in(config_dir(), agent_type))
+}
+
+/// Auth path for an agent type under a given config directory
+pub(crate) fn auth_path_in(config_dir: &Path, agent_type: &AgentProviderType) -> PathBuf {
+    config_dir.join("auth").join(auth_filename(agent_type))
 }
 
 impl AgentAuthContext {
@@ -77,7 +101,7 @@ impl AgentAuthContext {
         agent_type: &AgentProviderType,
     ) -> Result<(PathBuf, T), AgentAuthError>
     where
-        T: DeserializeOwned,
+        T: Serialize + DeserializeOwned,
     {
         let auth_path = auth_path_for_agent_type(agent_type)?;
         if !auth_path.exists() {
@@ -94,16 +118,26 @@ impl AgentAuthContext {
         let config = self.store.get_config().await;
         let mut builder = reqwest::Client::builder();
 
-        if config.app.enable_proxy {
+        if config.app.proxy_mode == ProxyMode::None {
+            builder = builder.no_proxy();
+        } else if config.app.proxy_mode == ProxyMode::Custom {
             if let Some(proxy_url) = &config.app.proxy_url {
+                validate_proxy_url(proxy_url).map_err(AgentAuthError::Parse)?;
                 let mut proxy = Proxy::all(proxy_url)
                     .map_err(|err| AgentAuthError::Parse(err.to_string()))?;
-                if !config.app.no_proxy.is_empty() {
-                    let no_proxy = NoProxy::from_string(&config.app.no_proxy.join(","));
+                if let Some((username, password)) = config.app.proxy_credentials() {
+                    proxy = proxy.basic_auth(username, password);
+                }
+                let no_proxy_list = config.app.effective_no_proxy();
+                if !no_proxy_list.is_empty() {
+                    let no_proxy = NoProxy::from_string(&no_proxy_list.join(","));
                     proxy = proxy.no_proxy(no_proxy);
                 }
                 builder = builder.proxy(proxy);
-                debug!("Using proxy {} for agent auth requests", proxy_url);
+                debug!(
+                    "Using proxy {} for agent auth requests",
+                    redact_proxy_url(proxy_url)
+                );
             } else {
                 warn!("Proxy enabled but proxy URL not configured");
             }
@@ -127,6 +161,9 @@ impl AgentAuthContext {
                 AgentAuthError::Http(err)
             })?;
 
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(AgentAuthError::Unauthorized);
+        }
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await.unwrap_or_default();
@@ -150,7 +187,11 @@ pub async fn read_email_from_auth(agent_type: &AgentProviderType) -> Option<Stri
     }
     let content = tokio::fs::read_to_string(&path).await.ok()?;
     let value: serde_json::Value = serde_json::from_str(&content).ok()?;
-    value.get("email").and_then(|v| v.as_str()).map(String::from)
+    value
+        .pointer("/token/email")
+        .or_else(|| value.get("email"))
+        .and_then(|v| v.as_str())
+        .map(String::from)
 }
 
 #[derive(Debug, Deserialize)]
@@ -301,11 +342,61 @@ pub fn parse_google_id_token(id_token: &str) -> Result<String, AgentAuthError> {
     Ok(email)
 }
 
-pub fn should_refresh_google(timestamp: &i64, expires_in: i64) -> bool {
-    let now_ms = Utc::now().timestamp_millis();
-    let expiry = *timestamp + (expires_in * 1000);
-    let refresh_skew = 3000 * 1000;
-    now_ms >= (expiry - refresh_skew)
+/// Whether a token expiring at `expire` (RFC 3339) is within `window` of expiring.
+/// An unparsable expiry counts as expired.
+pub fn expires_within(expire: &str, window: ChronoDuration) -> bool {
+    let expire = DateTime::parse_from_rfc3339(expire)
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|_| Utc::now());
+    expire - Utc::now() < window
+}
+
+/// Parse a `Retry-After` header value (delta-seconds or HTTP-date) into seconds from now
+pub fn parse_retry_after(value: &str) -> Option<u64> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(secs);
+    }
+    DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|dt| (dt.with_timezone(&Utc) - Utc::now()).num_seconds().max(0) as u64)
+}
+
+/// Send a quota request, honoring `Retry-After` on 429.
+/// Short waits are slept through and the request is retried once; longer waits (or a
+/// second 429) return `RateLimited` so the caller can schedule the next poll instead.
+pub async fn send_honoring_retry_after<F>(build: F) -> Result<reqwest::Response, AgentAuthError>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    let response = build().send().await?;
+    let Some(retry_after) = rate_limit_retry_after(&response) else {
+        return Ok(response);
+    };
+
+    if retry_after > MAX_RETRY_AFTER_WAIT_SECS {
+        warn!("Quota request rate limited, retry after {}s", retry_after);
+        return Err(AgentAuthError::RateLimited(retry_after));
+    }
+
+    debug!("Quota request rate limited, retrying in {}s", retry_after);
+    tokio::time::sleep(std::time::Duration::from_secs(retry_after)).await;
+    let response = build().send().await?;
+    match rate_limit_retry_after(&response) {
+        Some(retry_after) => Err(AgentAuthError::RateLimited(retry_after)),
+        None => Ok(response),
+    }
+}
+
+fn rate_limit_retry_after(response: &reqwest::Response) -> Option<u64> {
+    if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+    response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_retry_after)
 }
 
 pub fn parse_rfc3339_to_epoch(value: &str) -> Option<i64> {
@@ -314,6 +405,67 @@ pub fn parse_rfc3339_to_epoch(value: &str) -> Option<i64> {
         .ok()
 }
 
+/// Format version written into new auth files; bump it when a payload changes shape
+pub const AUTH_FILE_FORMAT_VERSION: u32 = 1;
+
+/// On-disk envelope of a stored login: `{"formatVersion": 1, "token": {...}}`.
+/// Payload fields a build doesn't know are ignored, so newer files still load.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredToken<T> {
+    pub format_version: u32,
+    pub token: T,
+}
+
+/// Agent-specific payload of a `StoredToken`
+pub trait TokenPayload {
+    /// How long before expiry the token gets refreshed
+    const REFRESH_WINDOW: ChronoDuration;
+
+    /// Expiry as RFC 3339
+    fn expire(&self) -> &str;
+
+    fn needs_refresh(&self) -> bool {
+        expires_within(self.expire(), Self::REFRESH_WINDOW)
+    }
+}
+
+/// Check a stored login: refresh it when due, probe the agent's API with it, and on a 401
+/// refresh once more and re-probe. Refreshed tokens are written back to the auth file.
+pub(crate) async fn validate_stored_token<T, P>(
+    ctx: &AgentAuthContext,
+    agent_type: &AgentProviderType,
+    refresh: impl AsyncFn(&AgentAuthContext, &T) -> Result<T, AgentAuthError>,
+    probe: impl AsyncFn(&AgentAuthContext, &T) -> Result<P, AgentAuthError>,
+) -> Result<TokenValidity, AgentAuthError>
+where
+    T: TokenPayload + Serialize + DeserializeOwned,
+{
+    let (auth_path, mut auth): (PathBuf, T) = ctx.load_and_normalize_auth(agent_type).await?;
+
+    let mut validity = TokenValidity::Valid;
+    if auth.needs_refresh() {
+        let Some(refreshed) = refresh_or_expired(refresh(ctx, &auth).await)? else {
+            return Ok(TokenValidity::Expired);
+        };
+        auth = refreshed;
+        save_auth_file(&auth_path, &auth).await?;
+        validity = TokenValidity::Refreshed;
+    }
+
+    match probe(ctx, &auth).await {
+        Err(AgentAuthError::Unauthorized) if validity == TokenValidity::Valid => {
+            let Some(refreshed) = refresh_or_expired(refresh(ctx, &auth).await)? else {
+                return Ok(TokenValidity::Expired);
+            };
+            auth = refreshed;
+            save_auth_file(&auth_path, &auth).await?;
+            probe_validity(probe(ctx, &auth).await, TokenValidity::Refreshed)
+        }
+        result => probe_validity(result, validity),
+    }
+}
+
 pub async fn save_auth_file<T: Serialize>(
     path: &PathBuf,
     auth: &T,
@@ -321,13 +473,182 @@ pub async fn save_auth_file<T: Serialize>(
     if let Some(parent) = path.parent() {
         tokio::fs::create_dir_all(parent).await?;
     }
-    let content = serde_json::to_string_pretty(auth)
+    let stored = StoredToken {
+        format_version: AUTH_FILE_FORMAT_VERSION,
+        token: auth,
+    };
+    let content = serde_json::to_string_pretty(&stored)
         .map_err(|err| AgentAuthError::Parse(err.to_string()))?;
     tokio::fs::write(path, content).await?;
     Ok(())
 }
 
-pub async fn load_auth_file<T: DeserializeOwned>(path: &PathBuf) -> Result<T, AgentAuthError> {
+/// What a stored login holds, minus the secrets; see `inspect_agent_auth_file`
+pub(crate) struct StoredAuthSummary {
+    pub email: Option<String>,
+    pub expires_at: Option<String>,
+    pub needs_refresh: bool,
+    pub has_access_token: bool,
+    pub has_refresh_token: bool,
+}
+
+impl StoredAuthSummary {
+    pub(crate) fn new(
+        email: &str,
+        expire: &str,
+        access_token: &str,
+        refresh_token: &str,
+        needs_refresh: bool,
+    ) -> Self {
+        let non_empty = |value: &str| Some(value.to_string()).filter(|v| !v.is_empty());
+        Self {
+            email: non_empty(email),
+            expires_at: non_empty(expire),
+            needs_refresh,
+            has_access_token: !access_token.is_empty(),
+            has_refresh_token: !refresh_token.is_empty(),
+        }
+    }
+}
+
+/// Load a stored login. Files from before `StoredToken` hold the bare payload; those are
+/// rewritten in the current format once they parse.
+pub async fn load_auth_file<T>(path: &PathBuf) -> Result<T, AgentAuthError>
+where
+    T: Serialize + DeserializeOwned,
+{
     let content = tokio::fs::read_to_string(path).await?;
-    serde_json::from_str(&content).map_err(|err| AgentAuthError::Parse(err.to_string()))
+    let value: serde_json::Value =
+        serde_json::from_str(&content).map_err(|err| AgentAuthError::Parse(err.to_string()))?;
+    if value.get("formatVersion").is_some() {
+        let stored: StoredToken<T> =
+            serde_json::from_value(value).map_err(|err| AgentAuthError::Parse(err.to_string()))?;
+        if stored.format_version > AUTH_FILE_FORMAT_VERSION {
+            debug!(
+                "{} has format version {}, newer than {}",
+                path.display(),
+                stored.format_version,
+                AUTH_FILE_FORMAT_VERSION
+            );
+        }
+        return Ok(stored.token);
+    }
+    let auth: T =
+        serde_json::from_value(value).map_err(|err| AgentAuthError::Parse(err.to_string()))?;
+    debug!("Migrating {} to auth file format {}", path.display(), AUTH_FILE_FORMAT_VERSION);
+    if let Err(err) = save_auth_file(path, &auth).await {
+        warn!("Failed to migrate {}: {}", path.display(), err);
+    }
+    Ok(auth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::{http::StatusCode, response::IntoResponse, routing::get, Router};
+
+    /// Serve a quota endpoint that answers 429 with the given `Retry-After` until `fail_times` is used up
+    async fn spawn_quota_server(retry_after: &'static str, fail_times: usize) -> String {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let app = Router::new().route(
+            "/usage",
+            get(move || {
+                let hits = hits.clone();
+                async move {
+                    if hits.fetch_add(1, Ordering::SeqCst) < fail_times {
+                        (StatusCode::TOO_MANY_REQUESTS, [("retry-after", retry_after)], "slow down")
+                            .into_response()
+                    } else {
+                        (StatusCode::OK, r#"{"ok":true}"#).into_response()
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        format!("http://{}/usage", addr)
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(120));
+        assert_eq!(parse_retry_after(" 5 "), Some(5));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(0));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_short_wait_retries_once() {
+        let url = spawn_quota_server("1", 1).await;
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+
+        let started = std::time::Instant::now();
+        let response = send_honoring_retry_after(|| client.get(&url)).await.unwrap();
+        assert!(response.status().is_success());
+        assert!(started.elapsed() >= std::time::Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_long_wait_returns_rate_limited() {
+        let url = spawn_quota_server("3600", usize::MAX).await;
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+
+        let result = send_honoring_retry_after(|| client.get(&url)).await;
+        assert!(matches!(result, Err(AgentAuthError::RateLimited(3600))));
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct TestToken {
+        access_token: String,
+        expire: String,
+    }
+
+    impl TokenPayload for TestToken {
+        const REFRESH_WINDOW: ChronoDuration = ChronoDuration::minutes(5);
+
+        fn expire(&self) -> &str {
+            &self.expire
+        }
+    }
+
+    #[tokio::test]
+    async fn test_legacy_auth_file_is_migrated_on_load() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("auth").join("claude_code.json");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let expire = (Utc::now() + ChronoDuration::hours(1)).to_rfc3339();
+        std::fs::write(
+            &path,
+            serde_json::json!({"access_token": "at", "expire": expire}).to_string(),
+        )
+        .unwrap();
+
+        let auth: TestToken = load_auth_file(&path).await.unwrap();
+        assert_eq!(auth.access_token, "at");
+        assert!(!auth.needs_refresh());
+
+        let migrated: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(migrated["formatVersion"], AUTH_FILE_FORMAT_VERSION);
+        assert_eq!(migrated["token"]["access_token"], "at");
+
+        // Fields from a newer build are ignored
+        std::fs::write(
+            &path,
+            serde_json::json!({
+                "formatVersion": AUTH_FILE_FORMAT_VERSION + 1,
+                "token": {"access_token": "at2", "expire": "not a date", "scopes": ["a"]}
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let auth: TestToken = load_auth_file(&path).await.unwrap();
+        assert_eq!(auth.access_token, "at2");
+        assert!(auth.needs_refresh());
+    }
 }
@@ -1,13 +1,17 @@
+use std::path::PathBuf;
+
 use crate::agents::{
     auth::{
-        auth_path_for_agent_type, generate_pkce_codes, parse_rfc3339_to_epoch, save_auth_file,
+        auth_path_for_agent_type, generate_pkce_codes, load_auth_file, parse_rfc3339_to_epoch,
+        save_auth_file, send_honoring_retry_after, StoredAuthSummary, TokenPayload,
+        validate_stored_token,
     },
     auth::{AgentAuthContext, AgentAuthError, AuthFlowStart},
     AgentMetadata, CodingAgentDefinition,
 };
-use crate::models::{AgentProviderType, AgentQuota, AgentQuotaEntry, AgentType};
+use crate::models::{AgentProviderType, AgentQuota, AgentQuotaEntry, AgentType, TokenValidity};
 
-use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use chrono::{Duration as ChronoDuration, Utc};
 use reqwest::StatusCode as ReqwestStatusCode;
 use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
@@ -19,6 +23,8 @@ const CLAUDE_REDIRECT_URI: &str = "http://localhost:54545/callback";
 const CLAUDE_CALLBACK_PATH: &str = "/callback";
 const CLAUDE_CALLBACK_PORT: u16 = 54545;
 const CLAUDE_USAGE_URL: &str = "https://api.anthropic.com/api/oauth/usage";
+/// `anthropic-beta` flag required when calling Anthropic APIs with an OAuth access token
+pub(crate) const CLAUDE_OAUTH_BETA: &str = "oauth-2025-04-20";
 
 const CLAUDE_SCOPES: &[&str] = &["org:create_api_key", "user:profile", "user:inference"];
 
@@ -83,6 +89,18 @@ struct ClaudeUsageWindow {
     resets_at: Option<String>,
 }
 
+/// Secret-free summary of the login stored at `path`
+pub(crate) async fn inspect_auth(path: &PathBuf) -> Result<StoredAuthSummary, AgentAuthError> {
+    let auth: ClaudeTokenStorage = load_auth_file(path).await?;
+    Ok(StoredAuthSummary::new(
+        &auth.email,
+        &auth.expire,
+        &auth.access_token,
+        &auth.refresh_token,
+        auth.needs_refresh(),
+    ))
+}
+
 pub(crate) fn start_auth_flow(state: &str) -> Result<AuthFlowStart, AgentAuthError> {
     let (code_verifier, code_challenge) = generate_pkce_codes();
     let auth_url = build_claude_auth_url(state, &code_challenge)?;
@@ -129,7 +147,7 @@ pub(crate) async fn get_quota(
         .load_and_normalize_auth(agent_type)
         .await?;
 
-    if should_refresh_claude(&auth) {
+    if auth.needs_refresh() {
         auth = refresh_claude_token(ctx, &auth).await?;
         save_auth_file(&auth_path, &auth).await?;
     }
@@ -145,19 +163,44 @@ pub(crate) async fn get_quota(
     }
 }
 
+/// Refresh the stored token when due, then probe it (see `validate_agent_token`)
+pub(crate) async fn validate_token(
+    ctx: &AgentAuthContext,
+    agent_type: &AgentProviderType,
+) -> Result<TokenValidity, AgentAuthError> {
+    validate_stored_token(ctx, agent_type, refresh_claude_token, fetch_claude_quota).await
+}
+
+/// Load the stored OAuth access token, refreshing and persisting it when close to expiry
+pub(crate) async fn access_token(
+    ctx: &AgentAuthContext,
+    agent_type: &AgentProviderType,
+) -> Result<String, AgentAuthError> {
+    let (auth_path, mut auth): (std::path::PathBuf, ClaudeTokenStorage) = ctx
+        .load_and_normalize_auth(agent_type)
+        .await?;
+
+    if auth.needs_refresh() {
+        auth = refresh_claude_token(ctx, &auth).await?;
+        save_auth_file(&auth_path, &auth).await?;
+    }
+
+    Ok(auth.access_token)
+}
+
 async fn fetch_claude_quota(
     ctx: &AgentAuthContext,
     auth: &ClaudeTokenStorage,
 ) -> Result<AgentQuota, AgentAuthError> {
-    let response = ctx
-        .http_client()
-        .await?
-        .get(CLAUDE_USAGE_URL)
-        .bearer_auth(&auth.access_token)
-        .header("anthropic-beta", "oauth-2025-04-20")
-        .header("Accept", "application/json")
-        .send()
-        .await?;
+    let client = ctx.http_client().await?;
+    let response = send_honoring_retry_after(|| {
+        client
+            .get(CLAUDE_USAGE_URL)
+            .bearer_auth(&auth.access_token)
+            .header("anthropic-beta", CLAUDE_OAUTH_BETA)
+            .header("Accept", "application/json")
+    })
+    .await?;
 
     let status = response.status();
     let body = response.text().await.unwrap_or_default();
@@ -335,9 +378,10 @@ fn build_claude_auth_url(state: &str, code_challenge: &str) -> Result<String, Ag
     Ok(url.to_string())
 }
 
-fn should_refresh_claude(auth: &ClaudeTokenStorage) -> bool {
-    let expire = DateTime::parse_from_rfc3339(&auth.expire)
-        .map(|dt| dt.with_timezone(&Utc))
-        .unwrap_or_else(|_| Utc::now());
-    expire - Utc::now() < ChronoDuration::minutes(5)
+impl TokenPayload for ClaudeTokenStorage {
+    const REFRESH_WINDOW: ChronoDuration = ChronoDuration::minutes(5);
+
+    fn expire(&self) -> &str {
+        &self.expire
+    }
 }
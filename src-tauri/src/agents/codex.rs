@@ -1,12 +1,20 @@
+use std::path::PathBuf;
+
 use crate::agents::{
-    auth::{auth_path_for_agent_type, generate_pkce_codes, save_auth_file, AuthFlowStart},
+    auth::{
+        auth_path_for_agent_type, generate_pkce_codes, load_auth_file, save_auth_file,
+        send_honoring_retry_after, AuthFlowStart, StoredAuthSummary, TokenPayload,
+        validate_stored_token,
+    },
     auth::{AgentAuthContext, AgentAuthError},
     AgentMetadata, CodingAgentDefinition,
 };
-use crate::models::{AgentProviderType, AgentQuota, AgentType};
+use crate::models::{
+    AgentOrganization, AgentProviderType, AgentQuota, AgentType, TokenValidity,
+};
 
 use base64::Engine as _;
-use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use chrono::{Duration as ChronoDuration, Utc};
 use reqwest::StatusCode as ReqwestStatusCode;
 use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
@@ -94,6 +102,19 @@ struct OpenAIAuth {
 struct OpenAIOrganization {
     id: Option<String>,
     uuid: Option<String>,
+    title: Option<String>,
+}
+
+/// Secret-free summary of the login stored at `path`
+pub(crate) async fn inspect_auth(path: &PathBuf) -> Result<StoredAuthSummary, AgentAuthError> {
+    let auth: CodexTokenStorage = load_auth_file(path).await?;
+    Ok(StoredAuthSummary::new(
+        &auth.email,
+        &auth.expire,
+        &auth.access_token,
+        &auth.refresh_token,
+        auth.needs_refresh(),
+    ))
 }
 
 pub(crate) fn start_auth_flow(state: &str) -> Result<AuthFlowStart, AgentAuthError> {
@@ -147,42 +168,72 @@ pub(crate) async fn complete_auth(
     Ok(())
 }
 
+/// Quota for the stored account, or for `account_id` (another organization of the same
+/// login) when given
 pub(crate) async fn get_quota(
     ctx: &AgentAuthContext,
     agent_type: &AgentProviderType,
+    account_id: Option<&str>,
 ) -> Result<AgentQuota, AgentAuthError> {
     let (auth_path, mut auth): (std::path::PathBuf, CodexTokenStorage) = ctx
         .load_and_normalize_auth(agent_type)
         .await?;
 
-    if should_refresh_codex(&auth) {
+    if auth.needs_refresh() {
         auth = refresh_codex_token(ctx, &auth).await?;
         save_auth_file(&auth_path, &auth).await?;
     }
 
-    match fetch_codex_quota(ctx, &auth).await {
+    let account_id = account_id.unwrap_or(&auth.account_id).to_string();
+    match fetch_codex_quota(ctx, &auth, &account_id).await {
         Ok(quota) => Ok(quota),
         Err(AgentAuthError::Unauthorized) => {
             auth = refresh_codex_token(ctx, &auth).await?;
             save_auth_file(&auth_path, &auth).await?;
-            fetch_codex_quota(ctx, &auth).await
+            fetch_codex_quota(ctx, &auth, &account_id).await
         }
         Err(err) => Err(err),
     }
 }
 
+/// Refresh the stored token when due, then probe it (see `validate_agent_token`)
+pub(crate) async fn validate_token(
+    ctx: &AgentAuthContext,
+    agent_type: &AgentProviderType,
+) -> Result<TokenValidity, AgentAuthError> {
+    let probe = async |ctx: &AgentAuthContext, auth: &CodexTokenStorage| {
+        fetch_codex_quota(ctx, auth, &auth.account_id).await
+    };
+    validate_stored_token(ctx, agent_type, refresh_codex_token, probe).await
+}
+
+/// Organizations listed in the stored login's id_token
+pub(crate) async fn list_organizations(
+    ctx: &AgentAuthContext,
+    agent_type: &AgentProviderType,
+) -> Result<Vec<AgentOrganization>, AgentAuthError> {
+    let (_, auth): (std::path::PathBuf, CodexTokenStorage) =
+        ctx.load_and_normalize_auth(agent_type).await?;
+    let mut organizations = parse_codex_organizations(&auth.id_token)?;
+    for org in &mut organizations {
+        org.is_default = org.id == auth.account_id;
+    }
+    Ok(organizations)
+}
+
 async fn fetch_codex_quota(
     ctx: &AgentAuthContext,
     auth: &CodexTokenStorage,
+    account_id: &str,
 ) -> Result<AgentQuota, AgentAuthError> {
-    let response = ctx
-        .http_client()
-        .await?
-        .get(CODEX_USAGE_URL)
-        .bearer_auth(&auth.access_token)
-        .header("ChatGPT-Account-Id", &auth.account_id)
-        .send()
-        .await?;
+    let client = ctx.http_client().await?;
+    let response = send_honoring_retry_after(|| {
+        client
+            .get(CODEX_USAGE_URL)
+            .bearer_auth(&auth.access_token)
+            .header("ChatGPT-Account-Id", account_id)
+    })
+    .await?;
 
     match response.status() {
         ReqwestStatusCode::UNAUTHORIZED => return Err(AgentAuthError::Unauthorized),
@@ -309,7 +360,7 @@ fn build_codex_auth_url(state: &str, code_challenge: &str) -> Result<String, Age
     Ok(url.to_string())
 }
 
-fn parse_codex_id_token(id_token: &str) -> Result<(String, String), AgentAuthError> {
+fn decode_id_token_claims(id_token: &str) -> Result<IdTokenClaims, AgentAuthError> {
     let parts: Vec<&str> = id_token.split('.').collect();
     if parts.len() != 3 {
         return Err(AgentAuthError::Parse("Invalid JWT format".to_string()));
@@ -318,8 +369,29 @@ fn parse_codex_id_token(id_token: &str) -> Result<(String, String), AgentAuthErr
     let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
         .decode(parts[1])
         .map_err(|err| AgentAuthError::Parse(err.to_string()))?;
-    let claims: IdTokenClaims =
-        serde_json::from_slice(&payload).map_err(|err| AgentAuthError::Parse(err.to_string()))?;
+    serde_json::from_slice(&payload).map_err(|err| AgentAuthError::Parse(err.to_string()))
+}
+
+/// All organizations in the id_token; `is_default` is left false for the caller to set
+fn parse_codex_organizations(id_token: &str) -> Result<Vec<AgentOrganization>, AgentAuthError> {
+    let claims = decode_id_token_claims(id_token)?;
+    Ok(claims
+        .openai_auth
+        .and_then(|auth| auth.organizations)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|org| {
+            Some(AgentOrganization {
+                id: org.id.or(org.uuid)?,
+                title: org.title,
+                is_default: false,
+            })
+        })
+        .collect())
+}
+
+fn parse_codex_id_token(id_token: &str) -> Result<(String, String), AgentAuthError> {
+    let claims = decode_id_token_claims(id_token)?;
 
     let email = claims
         .email
@@ -335,9 +407,38 @@ fn parse_codex_id_token(id_token: &str) -> Result<(String, String), AgentAuthErr
     Ok((account_id, email))
 }
 
-fn should_refresh_codex(auth: &CodexTokenStorage) -> bool {
-    let expire = DateTime::parse_from_rfc3339(&auth.expire)
-        .map(|dt| dt.with_timezone(&Utc))
-        .unwrap_or_else(|_| Utc::now());
-    expire - Utc::now() < ChronoDuration::days(5)
+impl TokenPayload for CodexTokenStorage {
+    const REFRESH_WINDOW: ChronoDuration = ChronoDuration::days(5);
+
+    fn expire(&self) -> &str {
+        &self.expire
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_codex_organizations() {
+        let claims = serde_json::json!({
+            "email": "dev@example.com",
+            "https://api.openai.com/auth": {
+                "organizations": [
+                    {"id": "org-personal", "title": "Personal"},
+                    {"uuid": "org-team"},
+                    {"title": "No id"}
+                ]
+            }
+        });
+        let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .encode(serde_json::to_vec(&claims).unwrap());
+        let token = format!("header.{}.sig", payload);
+
+        let orgs = parse_codex_organizations(&token).unwrap();
+        assert_eq!(orgs.len(), 2);
+        assert_eq!(orgs[0].id, "org-personal");
+        assert_eq!(orgs[0].title.as_deref(), Some("Personal"));
+        assert_eq!(orgs[1].id, "org-team");
+    }
 }
@@ -1,12 +1,15 @@
+use std::path::PathBuf;
+
 use crate::agents::{
     auth::{
-        auth_path_for_agent_type, build_google_auth_url, exchange_google_code,
-        parse_google_id_token, refresh_google_token, save_auth_file, should_refresh_google,
-        AgentAuthContext, AgentAuthError, AuthFlowStart,
+        auth_path_for_agent_type, build_google_auth_url, exchange_google_code, load_auth_file,
+        parse_google_id_token, refresh_google_token, save_auth_file, AgentAuthContext,
+        AgentAuthError, AuthFlowStart, StoredAuthSummary, TokenPayload,
+        validate_stored_token,
     },
     AgentMetadata, CodingAgentDefinition,
 };
-use crate::models::{AgentProviderType, AgentQuota, AgentType};
+use crate::models::{AgentProviderType, AgentQuota, AgentType, TokenValidity};
 
 use chrono::{Duration as ChronoDuration, Utc};
 use serde::{Deserialize, Serialize};
@@ -55,6 +58,26 @@ struct GeminiTokenStorage {
     pub project_id: Option<String>,
 }
 
+impl TokenPayload for GeminiTokenStorage {
+    const REFRESH_WINDOW: ChronoDuration = ChronoDuration::minutes(50);
+
+    fn expire(&self) -> &str {
+        &self.expire
+    }
+}
+
+/// Secret-free summary of the login stored at `path`
+pub(crate) async fn inspect_auth(path: &PathBuf) -> Result<StoredAuthSummary, AgentAuthError> {
+    let auth: GeminiTokenStorage = load_auth_file(path).await?;
+    Ok(StoredAuthSummary::new(
+        &auth.email,
+        &auth.expire,
+        &auth.access_token,
+        &auth.refresh_token,
+        auth.needs_refresh(),
+    ))
+}
+
 pub(crate) fn start_auth_flow(state: &str) -> Result<AuthFlowStart, AgentAuthError> {
     let auth_url = build_google_auth_url(
         GEMINI_CLIENT_ID,
@@ -127,7 +150,7 @@ pub(crate) async fn get_quota(
         .load_and_normalize_auth(agent_type)
         .await?;
 
-    if should_refresh_google(&auth.timestamp, auth.expires_in) {
+    if auth.needs_refresh() {
         auth = refresh_gemini_token(ctx, &auth).await?;
         save_auth_file(&auth_path, &auth).await?;
     }
@@ -135,6 +158,17 @@ pub(crate) async fn get_quota(
     fetch_gemini_quota(&auth).await
 }
 
+/// Refresh the stored token when due, then probe it (see `validate_agent_token`)
+pub(crate) async fn validate_token(
+    ctx: &AgentAuthContext,
+    agent_type: &AgentProviderType,
+) -> Result<TokenValidity, AgentAuthError> {
+    let probe = async |ctx: &AgentAuthContext, auth: &GeminiTokenStorage| {
+        ctx.fetch_google_email(&auth.access_token).await
+    };
+    validate_stored_token(ctx, agent_type, refresh_gemini_token, probe).await
+}
+
 async fn refresh_gemini_token(
     ctx: &AgentAuthContext,
     auth: &GeminiTokenStorage,
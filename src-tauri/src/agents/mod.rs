@@ -3,17 +3,25 @@ mod codex;
 mod gemini_cli;
 mod antigravity;
 pub(crate) mod auth;
+mod quota_history;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use crate::models::{AgentProviderType, AgentQuota, AgentType};
+use chrono::Utc;
+
+use crate::models::{
+    AgentOrganization, AgentProviderType, AgentQuota, AgentType, AuthFileReport, AuthFileStatus,
+    TokenValidity,
+};
 
 pub use antigravity::AntigravityAgent;
 pub use claude_code::ClaudeCodeAgent;
+pub(crate) use claude_code::CLAUDE_OAUTH_BETA;
 pub use codex::CodexAgent;
 pub use gemini_cli::GeminiCliAgent;
-pub use auth::{AgentAuthContext, AgentAuthError, AuthFlowStart};
+pub use auth::{parse_retry_after, AgentAuthContext, AgentAuthError, AuthFlowStart};
+pub use quota_history::{append_quota_history, quota_history_path, read_quota_history};
 
 #[derive(Debug, Clone)]
 pub struct AgentMetadata {
@@ -26,6 +34,16 @@ pub struct AgentMetadata {
 
 pub trait CodingAgentDefinition {
     fn metadata(&self) -> &'static AgentMetadata;
+
+    /// Whether the agent's binary can be found on PATH or in a well-known install location
+    fn is_installed(&self) -> bool {
+        binary_is_installed(self.metadata().binary)
+    }
+
+    /// Version reported by the agent's binary, if it is installed
+    fn get_version(&self) -> Option<String> {
+        resolve_binary_version(self.metadata().binary)
+    }
 }
 
 /// Build a list of candidate directories where CLI tools are commonly installed.
@@ -164,7 +182,7 @@ fn common_binary_search_dirs() -> Vec<PathBuf> {
 
 /// Resolve the full path of a binary by first checking PATH, then searching
 /// common installation directories. Returns `None` if not found anywhere.
-fn resolve_binary_path(binary: &str) -> Option<PathBuf> {
+pub(crate) fn resolve_binary_path(binary: &str) -> Option<PathBuf> {
     // Check if binary already contains a path separator — treat as absolute/relative
     let binary_path = PathBuf::from(binary);
     if binary_path.components().count() > 1 && binary_path.exists() {
@@ -225,10 +243,37 @@ fn resolve_binary_path(binary: &str) -> Option<PathBuf> {
 /// When the app runs as a packaged bundle (e.g. macOS .app), the process PATH is
 /// minimal. We therefore resolve the binary via [`resolve_binary_path`] which also
 /// searches well-known installation directories.
-pub(crate) fn is_binary_installed(binary: &str) -> bool {
+pub(crate) fn binary_is_installed(binary: &str) -> bool {
     resolve_binary_path(binary).is_some()
 }
 
+/// Resolve a binary and read its version from `<binary> --version`
+pub(crate) fn resolve_binary_version(binary: &str) -> Option<String> {
+    read_binary_version(&resolve_binary_path(binary)?)
+}
+
+/// Run `<binary> --version` and extract the version
+fn read_binary_version(path: &Path) -> Option<String> {
+    let output = Command::new(path).arg("--version").output().ok()?;
+    if !output.status.success() {
+        tracing::debug!("{} --version exited with {}", path.display(), output.status);
+        return None;
+    }
+    parse_version_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Pick the version from `--version` output such as `codex-cli 0.46.0` or
+/// `1.0.120 (Claude Code)`, falling back to the first non-empty line
+fn parse_version_output(output: &str) -> Option<String> {
+    let line = output.lines().map(str::trim).find(|l| !l.is_empty())?;
+    let version = line
+        .split_whitespace()
+        .map(|token| token.trim_start_matches('v'))
+        .find(|token| token.starts_with(|c: char| c.is_ascii_digit()) && token.contains('.'))
+        .unwrap_or(line);
+    Some(version.to_string())
+}
+
 static ANTIGRAVITY_AGENT: AntigravityAgent = AntigravityAgent;
 static CLAUDE_CODE_AGENT: ClaudeCodeAgent = ClaudeCodeAgent;
 static CODEX_AGENT: CodexAgent = CodexAgent;
@@ -291,14 +336,198 @@ pub async fn complete_agent_auth(
     }
 }
 
+/// OAuth access token for proxying API requests with an agent account.
+/// Only Claude Code tokens are usable against a public API today.
+pub async fn get_agent_access_token(
+    ctx: &AgentAuthContext,
+    agent_type: &AgentProviderType,
+) -> Result<String, AgentAuthError> {
+    match agent_type {
+        AgentProviderType::ClaudeCode => claude_code::access_token(ctx, agent_type).await,
+        _ => Err(AgentAuthError::Parse(format!(
+            "{:?} accounts cannot be used as proxy credentials",
+            agent_type
+        ))),
+    }
+}
+
+/// Quota for an agent account. `account_id` selects another organization of the same
+/// login and is only supported by Codex; other agents ignore it.
 pub async fn get_agent_quota(
     ctx: &AgentAuthContext,
     agent_type: &AgentProviderType,
+    account_id: Option<&str>,
 ) -> Result<AgentQuota, AgentAuthError> {
     match agent_type {
-        AgentProviderType::Codex => codex::get_quota(ctx, agent_type).await,
+        AgentProviderType::Codex => codex::get_quota(ctx, agent_type, account_id).await,
         AgentProviderType::ClaudeCode => claude_code::get_quota(ctx, agent_type).await,
         AgentProviderType::GeminiCli => gemini_cli::get_quota(ctx, agent_type).await,
         AgentProviderType::Antigravity => antigravity::get_quota(ctx, agent_type).await,
     }
 }
+
+/// Organizations of an agent login; empty for agents without organizations
+pub async fn list_agent_organizations(
+    ctx: &AgentAuthContext,
+    agent_type: &AgentProviderType,
+) -> Result<Vec<AgentOrganization>, AgentAuthError> {
+    match agent_type {
+        AgentProviderType::Codex => codex::list_organizations(ctx, agent_type).await,
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Check that an agent login still works: refresh it when due, then make a lightweight
+/// authenticated call. The auth file is only rewritten by a successful refresh.
+pub async fn validate_agent_token(
+    ctx: &AgentAuthContext,
+    agent_type: &AgentProviderType,
+) -> Result<TokenValidity, AgentAuthError> {
+    match agent_type {
+        AgentProviderType::Codex => codex::validate_token(ctx, agent_type).await,
+        AgentProviderType::ClaudeCode => claude_code::validate_token(ctx, agent_type).await,
+        AgentProviderType::GeminiCli => gemini_cli::validate_token(ctx, agent_type).await,
+        AgentProviderType::Antigravity => antigravity::validate_token(ctx, agent_type).await,
+    }
+}
+
+/// Sanitized report of the login stored at `path`, for diagnosing re-login prompts.
+/// Token values never leave the agent modules; the report only says whether they're present.
+pub async fn inspect_agent_auth_file(
+    agent_type: &AgentProviderType,
+    path: &Path,
+) -> AuthFileReport {
+    let mut report = AuthFileReport {
+        agent_type: agent_type.clone(),
+        auth_path: path.display().to_string(),
+        status: AuthFileStatus::Missing,
+        error: None,
+        email: None,
+        expires_at: None,
+        expired: false,
+        needs_refresh: false,
+        has_access_token: false,
+        has_refresh_token: false,
+    };
+    if !path.exists() {
+        return report;
+    }
+
+    let path = path.to_path_buf();
+    let summary = match agent_type {
+        AgentProviderType::Codex => codex::inspect_auth(&path).await,
+        AgentProviderType::ClaudeCode => claude_code::inspect_auth(&path).await,
+        AgentProviderType::GeminiCli => gemini_cli::inspect_auth(&path).await,
+        AgentProviderType::Antigravity => antigravity::inspect_auth(&path).await,
+    };
+    match summary {
+        Ok(summary) => {
+            report.status = AuthFileStatus::Present;
+            report.expired = summary
+                .expires_at
+                .as_deref()
+                .and_then(auth::parse_rfc3339_to_epoch)
+                .is_some_and(|expires| expires <= Utc::now().timestamp());
+            report.email = summary.email;
+            report.expires_at = summary.expires_at;
+            report.needs_refresh = summary.needs_refresh;
+            report.has_access_token = summary.has_access_token;
+            report.has_refresh_token = summary.has_refresh_token;
+        }
+        Err(e) => {
+            report.status = AuthFileStatus::Unreadable;
+            report.error = Some(e.to_string());
+        }
+    }
+    report
+}
+
+/// A refresh during validation: `None` means the refresh token was rejected. Transport
+/// and storage errors are passed on since they say nothing about the token.
+fn refresh_or_expired<T>(result: Result<T, AgentAuthError>) -> Result<Option<T>, AgentAuthError> {
+    match result {
+        Ok(auth) => Ok(Some(auth)),
+        Err(err) if is_transient(&err) => Err(err),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Classify the validity probe made with a token in state `validity`
+fn probe_validity<T>(
+    result: Result<T, AgentAuthError>,
+    validity: TokenValidity,
+) -> Result<TokenValidity, AgentAuthError> {
+    match result {
+        Ok(_) => Ok(validity),
+        Err(AgentAuthError::Unauthorized) => Ok(TokenValidity::Expired),
+        Err(err) => Err(err),
+    }
+}
+
+fn is_transient(err: &AgentAuthError) -> bool {
+    matches!(
+        err,
+        AgentAuthError::Http(_)
+            | AgentAuthError::RateLimited(_)
+            | AgentAuthError::Io(_)
+            | AgentAuthError::Storage(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version_output() {
+        assert_eq!(parse_version_output("codex-cli 0.46.0\n").as_deref(), Some("0.46.0"));
+        assert_eq!(parse_version_output("1.0.120 (Claude Code)").as_deref(), Some("1.0.120"));
+        assert_eq!(parse_version_output("\nv2.1.0").as_deref(), Some("2.1.0"));
+        assert_eq!(parse_version_output("dev build").as_deref(), Some("dev build"));
+        assert_eq!(parse_version_output("  \n"), None);
+    }
+
+    #[test]
+    fn test_token_validation_outcomes() {
+        let rejected: Result<(), _> = Err(AgentAuthError::Parse("invalid_grant".to_string()));
+        assert_eq!(refresh_or_expired(rejected).unwrap(), None);
+        assert!(refresh_or_expired::<()>(Err(AgentAuthError::RateLimited(5))).is_err());
+
+        let ok: Result<(), AgentAuthError> = Ok(());
+        assert_eq!(probe_validity(ok, TokenValidity::Refreshed).unwrap(), TokenValidity::Refreshed);
+        assert_eq!(
+            probe_validity::<()>(Err(AgentAuthError::Unauthorized), TokenValidity::Valid).unwrap(),
+            TokenValidity::Expired
+        );
+    }
+
+    #[tokio::test]
+    async fn test_inspect_agent_auth_file_hides_tokens() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("claude_code.json");
+        let agent_type = AgentProviderType::ClaudeCode;
+
+        let report = inspect_agent_auth_file(&agent_type, &path).await;
+        assert_eq!(report.status, AuthFileStatus::Missing);
+
+        std::fs::write(
+            &path,
+            r#"{"access_token":"sk-ant-oat-secret","refresh_token":"sk-ant-ort-secret",
+                "email":"dev@example.com","last_refresh":"2026-01-01T00:00:00Z",
+                "expire":"2026-01-01T08:00:00Z"}"#,
+        )
+        .unwrap();
+        let report = inspect_agent_auth_file(&agent_type, &path).await;
+        assert_eq!(report.status, AuthFileStatus::Present);
+        assert_eq!(report.email.as_deref(), Some("dev@example.com"));
+        assert_eq!(report.expires_at.as_deref(), Some("2026-01-01T08:00:00Z"));
+        assert!(report.expired && report.needs_refresh);
+        assert!(report.has_access_token && report.has_refresh_token);
+        assert!(!serde_json::to_string(&report).unwrap().contains("secret"));
+
+        std::fs::write(&path, "{not json").unwrap();
+        let report = inspect_agent_auth_file(&agent_type, &path).await;
+        assert_eq!(report.status, AuthFileStatus::Unreadable);
+        assert!(report.error.is_some());
+    }
+}
@@ -0,0 +1,116 @@
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use tokio::fs;
+
+use super::auth::AgentAuthError;
+use crate::models::{AgentProviderType, AgentQuota, QuotaHistoryPoint};
+use crate::storage::config_dir;
+
+/// Number of quota samples kept per agent; older samples are dropped
+const MAX_QUOTA_HISTORY_ENTRIES: usize = 1000;
+
+fn history_filename(agent_type: &AgentProviderType) -> &'static str {
+    match agent_type {
+        AgentProviderType::Codex => "codex.jsonl",
+        AgentProviderType::ClaudeCode => "claude_code.jsonl",
+        AgentProviderType::GeminiCli => "gemini_cli.jsonl",
+        AgentProviderType::Antigravity => "antigravity.jsonl",
+    }
+}
+
+/// Quota history path for an agent type: ~/.vibemate/quota-history/<agent_type>.jsonl
+pub fn quota_history_path(agent_type: &AgentProviderType) -> Result<PathBuf, AgentAuthError> {
+    Ok(config_dir().join("quota-history").join(history_filename(agent_type)))
+}
+
+/// Append a quota sample to the history file, keeping only the most recent entries
+pub async fn append_quota_history(path: &Path, quota: &AgentQuota) -> Result<(), AgentAuthError> {
+    let point = QuotaHistoryPoint {
+        timestamp: Utc::now().timestamp_millis(),
+        session_used_percent: quota.session_used_percent,
+        week_used_percent: quota.week_used_percent,
+    };
+    let line = serde_json::to_string(&point).map_err(|e| AgentAuthError::Parse(e.to_string()))?;
+
+    let existing = match fs::read_to_string(path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let mut lines: Vec<&str> = existing.lines().filter(|l| !l.trim().is_empty()).collect();
+    lines.push(&line);
+    let start = lines.len().saturating_sub(MAX_QUOTA_HISTORY_ENTRIES);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    // Write to a temp file and rename so a crash never leaves a truncated history
+    let tmp_path = path.with_extension("jsonl.tmp");
+    fs::write(&tmp_path, lines[start..].join("\n") + "\n").await?;
+    fs::rename(&tmp_path, path).await?;
+    Ok(())
+}
+
+/// Read the quota history, oldest first. Malformed lines are skipped.
+pub async fn read_quota_history(path: &Path) -> Result<Vec<QuotaHistoryPoint>, AgentAuthError> {
+    let content = match fs::read_to_string(path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn quota(session: f64, week: f64) -> AgentQuota {
+        AgentQuota {
+            plan_type: None,
+            limit_reached: None,
+            session_used_percent: session,
+            session_reset_at: None,
+            week_used_percent: week,
+            week_reset_at: None,
+            entries: None,
+            note: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quota_history_append_and_read() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("quota-history").join("codex.jsonl");
+
+        assert!(read_quota_history(&path).await.unwrap().is_empty());
+
+        append_quota_history(&path, &quota(10.0, 2.0)).await.unwrap();
+        append_quota_history(&path, &quota(25.0, 4.5)).await.unwrap();
+
+        let history = read_quota_history(&path).await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].session_used_percent, 10.0);
+        assert_eq!(history[1].week_used_percent, 4.5);
+        assert!(history[0].timestamp <= history[1].timestamp);
+    }
+
+    #[tokio::test]
+    async fn test_quota_history_is_capped() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("codex.jsonl");
+        let old_line = r#"{"timestamp":0,"sessionUsedPercent":1.0,"weekUsedPercent":1.0}"#;
+        std::fs::write(&path, vec![old_line; MAX_QUOTA_HISTORY_ENTRIES].join("\n")).unwrap();
+
+        append_quota_history(&path, &quota(50.0, 5.0)).await.unwrap();
+
+        let history = read_quota_history(&path).await.unwrap();
+        assert_eq!(history.len(), MAX_QUOTA_HISTORY_ENTRIES);
+        assert_eq!(history.last().unwrap().session_used_percent, 50.0);
+    }
+}
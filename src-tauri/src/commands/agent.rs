@@ -1,6 +1,7 @@
 use std::sync::Arc;
 use tauri::State;
 
+use super::CommandError;
 use crate::models::{AgentType, CodingAgent};
 use crate::services::AgentService;
 
@@ -8,10 +9,10 @@ use crate::services::AgentService;
 pub async fn check_status(
     service: State<'_, Arc<AgentService>>,
     agent_type: AgentType,
-) -> Result<CodingAgent, String> {
+) -> Result<CodingAgent, CommandError> {
     service
         .check_status(&agent_type)
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
@@ -19,11 +20,11 @@ pub async fn read_agent_config(
     service: State<'_, Arc<AgentService>>,
     agent_type: AgentType,
     config_path: Option<String>,
-) -> Result<String, String> {
+) -> Result<String, CommandError> {
     service
         .read_config(&agent_type, config_path)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
@@ -32,9 +33,9 @@ pub async fn save_agent_config(
     agent_type: AgentType,
     content: String,
     config_path: Option<String>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     service
         .save_config(&agent_type, content, config_path)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
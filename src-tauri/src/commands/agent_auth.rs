@@ -1,56 +1,114 @@
 use std::sync::Arc;
 use tauri::State;
 
-use crate::models::{AgentAccountInfo, AgentAuthStart, AgentQuota, AgentProviderType};
+use super::CommandError;
+use crate::models::{
+    AgentAccountInfo, AgentAuthStart, AgentOrganization, AgentQuota, AgentProviderType,
+    AgentTokenStatus, AuthFileReport, QuotaHistoryPoint,
+};
 use crate::services::AgentAuthService;
 
 #[tauri::command]
 pub async fn start_agent_auth(
     service: State<'_, Arc<AgentAuthService>>,
     agent_type: AgentProviderType,
-) -> Result<AgentAuthStart, String> {
+) -> Result<AgentAuthStart, CommandError> {
     service
         .start_auth(agent_type)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
 pub async fn complete_agent_auth(
     service: State<'_, Arc<AgentAuthService>>,
     flow_id: String,
-) -> Result<AgentAccountInfo, String> {
+) -> Result<AgentAccountInfo, CommandError> {
     service
         .complete_auth(&flow_id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn complete_agent_auth_manual(
+    service: State<'_, Arc<AgentAuthService>>,
+    flow_id: String,
+    code: String,
+) -> Result<AgentAccountInfo, CommandError> {
+    service
+        .complete_auth_manual(&flow_id, &code)
+        .await
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
 pub async fn get_agent_quota(
     service: State<'_, Arc<AgentAuthService>>,
     agent_type: AgentProviderType,
-) -> Result<AgentQuota, String> {
+    account_id: Option<String>,
+) -> Result<AgentQuota, CommandError> {
+    service
+        .get_quota(agent_type, account_id)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn list_agent_organizations(
+    service: State<'_, Arc<AgentAuthService>>,
+    agent_type: AgentProviderType,
+) -> Result<Vec<AgentOrganization>, CommandError> {
+    service
+        .list_organizations(&agent_type)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn get_quota_history(
+    service: State<'_, Arc<AgentAuthService>>,
+    agent_type: AgentProviderType,
+) -> Result<Vec<QuotaHistoryPoint>, CommandError> {
     service
-        .get_quota(agent_type)
+        .get_quota_history(&agent_type)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
 pub async fn list_agent_accounts(
     service: State<'_, Arc<AgentAuthService>>,
-) -> Result<Vec<AgentAccountInfo>, String> {
+) -> Result<Vec<AgentAccountInfo>, CommandError> {
     Ok(service.list_accounts().await)
 }
 
+#[tauri::command]
+pub async fn validate_agent_tokens(
+    service: State<'_, Arc<AgentAuthService>>,
+) -> Result<Vec<AgentTokenStatus>, CommandError> {
+    Ok(service.validate_tokens().await)
+}
+
+/// Sanitized contents of an agent's auth file: email, expiry, token presence
+#[tauri::command]
+pub async fn inspect_auth_file(
+    service: State<'_, Arc<AgentAuthService>>,
+    agent_type: AgentProviderType,
+) -> Result<AuthFileReport, CommandError> {
+    service
+        .inspect_auth_file(&agent_type)
+        .await
+        .map_err(CommandError::from)
+}
+
 #[tauri::command]
 pub async fn remove_agent_auth(
     service: State<'_, Arc<AgentAuthService>>,
     agent_type: AgentProviderType,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     service
         .remove_auth(&agent_type)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
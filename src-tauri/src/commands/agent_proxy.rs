@@ -2,18 +2,31 @@ use std::sync::Arc;
 
 use tauri::State;
 
-use crate::models::AgentType;
+use super::CommandError;
+use crate::models::{AgentProxyChangePreview, AgentProxyConfigStatus, AgentType, ProxyEndpoints};
 use crate::services::AgentProxyService;
 
 #[tauri::command]
 pub async fn is_agent_proxy_enabled(
     service: State<'_, Arc<AgentProxyService>>,
     agent_type: AgentType,
-) -> Result<bool, String> {
+) -> Result<bool, CommandError> {
     service
         .is_proxy_enabled(&agent_type)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
+}
+
+/// Whether the agent's config file really points at the proxy on the current port
+#[tauri::command]
+pub async fn verify_agent_proxy_config(
+    service: State<'_, Arc<AgentProxyService>>,
+    agent_type: AgentType,
+) -> Result<AgentProxyConfigStatus, CommandError> {
+    service
+        .verify_proxy_config(&agent_type)
+        .await
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
@@ -21,9 +34,52 @@ pub async fn set_agent_proxy_enabled(
     service: State<'_, Arc<AgentProxyService>>,
     agent_type: AgentType,
     enabled: bool,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     service
         .set_proxy_enabled(&agent_type, enabled)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
+}
+
+/// Diff of what `set_agent_proxy_enabled` would write, without touching the file
+#[tauri::command]
+pub async fn preview_agent_proxy_change(
+    service: State<'_, Arc<AgentProxyService>>,
+    agent_type: AgentType,
+    enabled: bool,
+) -> Result<AgentProxyChangePreview, CommandError> {
+    service
+        .preview_proxy_change(&agent_type, enabled)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn launch_agent_with_proxy(
+    service: State<'_, Arc<AgentProxyService>>,
+    agent_type: AgentType,
+) -> Result<(), CommandError> {
+    service
+        .launch_with_proxy(&agent_type)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn restore_agent_config(
+    service: State<'_, Arc<AgentProxyService>>,
+    agent_type: AgentType,
+) -> Result<(), CommandError> {
+    service
+        .restore_agent_config(&agent_type)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Proxy URLs to configure in each agent
+#[tauri::command]
+pub async fn get_proxy_endpoints(
+    service: State<'_, Arc<AgentProxyService>>,
+) -> Result<ProxyEndpoints, CommandError> {
+    Ok(service.proxy_endpoints().await)
 }
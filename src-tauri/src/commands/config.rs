@@ -1,7 +1,9 @@
+use std::collections::HashMap;
 use std::sync::Arc;
-use tauri::State;
+use tauri::{AppHandle, Emitter, State};
 
-use crate::models::{AppConfig, CodingAgent, LatencyResult, UpdateAppConfigInput};
+use super::CommandError;
+use crate::models::{ApiGroup, AppConfig, CodingAgent, LatencyResult, UpdateAppConfigInput};
 use crate::services::{AgentService, ConfigService};
 use crate::storage::{merge_coding_agents, ConfigStore};
 use crate::models::AgentType;
@@ -9,35 +11,94 @@ use crate::models::AgentType;
 #[tauri::command]
 pub async fn get_config(
     service: State<'_, Arc<ConfigService>>,
-) -> Result<AppConfig, String> {
+) -> Result<AppConfig, CommandError> {
     service
         .get_config()
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
 pub async fn update_config(
     service: State<'_, Arc<ConfigService>>,
     input: UpdateAppConfigInput,
-) -> Result<AppConfig, String> {
+) -> Result<AppConfig, CommandError> {
     service
         .update_config(input)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
+}
+
+/// Emitted after `reload_config` so every view refetches what it shows
+pub const CONFIG_RELOADED_EVENT: &str = "config-reloaded";
+
+#[tauri::command]
+pub async fn reload_config(
+    app: AppHandle,
+    service: State<'_, Arc<ConfigService>>,
+) -> Result<AppConfig, CommandError> {
+    let config = service.reload_config().await?;
+    if let Err(e) = app.emit(CONFIG_RELOADED_EVENT, &config) {
+        tracing::warn!("Failed to emit {}: {}", CONFIG_RELOADED_EVENT, e);
+    }
+    Ok(config)
+}
+
+#[tauri::command]
+pub async fn get_model_aliases(
+    service: State<'_, Arc<ConfigService>>,
+) -> Result<HashMap<String, String>, CommandError> {
+    service
+        .get_model_aliases()
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn set_model_aliases(
+    service: State<'_, Arc<ConfigService>>,
+    aliases: HashMap<String, String>,
+) -> Result<HashMap<String, String>, CommandError> {
+    service
+        .set_model_aliases(aliases)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn set_group_default_provider(
+    service: State<'_, Arc<ConfigService>>,
+    api_group: ApiGroup,
+    provider_id: String,
+) -> Result<AppConfig, CommandError> {
+    service
+        .set_group_default_provider(api_group, provider_id)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn clear_group_default_provider(
+    service: State<'_, Arc<ConfigService>>,
+    api_group: ApiGroup,
+) -> Result<AppConfig, CommandError> {
+    service
+        .clear_group_default_provider(api_group)
+        .await
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
 pub async fn test_latency(
     service: State<'_, Arc<ConfigService>>,
-) -> Result<LatencyResult, String> {
+) -> Result<LatencyResult, CommandError> {
     Ok(service.test_latency().await)
 }
 
 #[tauri::command]
 pub async fn get_coding_agents(
     store: State<'_, Arc<ConfigStore>>,
-) -> Result<Vec<CodingAgent>, String> {
+) -> Result<Vec<CodingAgent>, CommandError> {
     let config = store.get_config().await;
     Ok(config.coding_agents)
 }
@@ -46,11 +107,12 @@ pub async fn get_coding_agents(
 pub async fn refresh_coding_agents(
     store: State<'_, Arc<ConfigStore>>,
     agent_service: State<'_, Arc<AgentService>>,
-) -> Result<Vec<CodingAgent>, String> {
-    let discovered = agent_service
-        .discover_agents()
-        .map_err(|e| e.to_string())?;
+    force_refresh: Option<bool>,
+) -> Result<Vec<CodingAgent>, CommandError> {
     let config = store.get_config().await;
+    let discovered = agent_service
+        .discover_agents(&config.coding_agents, force_refresh.unwrap_or(false))
+        .map_err(CommandError::from)?;
     let merged = merge_coding_agents(
         &config.coding_agents,
         discovered,
@@ -58,7 +120,7 @@ pub async fn refresh_coding_agents(
     store
         .update(|c| c.coding_agents = merged.clone())
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(CommandError::from)?;
     Ok(merged)
 }
 
@@ -67,7 +129,7 @@ pub async fn set_coding_agent_featured(
     store: State<'_, Arc<ConfigStore>>,
     agent_type: AgentType,
     featured: bool,
-) -> Result<Vec<CodingAgent>, String> {
+) -> Result<Vec<CodingAgent>, CommandError> {
     store
         .update(|config| {
             if let Some(entry) = config
@@ -79,7 +141,7 @@ pub async fn set_coding_agent_featured(
             }
         })
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(CommandError::from)?;
     let config = store.get_config().await;
     Ok(config.coding_agents)
 }
@@ -0,0 +1,161 @@
+use serde::Serialize;
+
+use crate::agents::AgentAuthError;
+use crate::services::{
+    AgentError, AgentProxyError, ConfigError, ProviderError, ProxyError, RouterError,
+};
+use crate::storage::StorageError;
+
+/// Error returned by every Tauri command. `code` is stable so the UI can branch on it
+/// (e.g. "auth_expired", "network", "not_found"); `message` is for display.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandError {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl CommandError {
+    fn new(code: &'static str, error: impl std::fmt::Display) -> Self {
+        Self {
+            code,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<std::io::Error> for CommandError {
+    fn from(e: std::io::Error) -> Self {
+        Self::new("io", e)
+    }
+}
+
+impl From<StorageError> for CommandError {
+    fn from(e: StorageError) -> Self {
+        Self::new("storage", e)
+    }
+}
+
+impl From<AgentAuthError> for CommandError {
+    fn from(e: AgentAuthError) -> Self {
+        let code = match &e {
+            AgentAuthError::FlowInProgress => "auth_flow_in_progress",
+            AgentAuthError::FlowNotFound(_) => "not_found",
+            AgentAuthError::Timeout => "timeout",
+            AgentAuthError::InvalidCallback(_) => "invalid_input",
+            AgentAuthError::Unauthorized => "auth_expired",
+            AgentAuthError::RateLimited(_) => "rate_limited",
+            AgentAuthError::Http(_) => "network",
+            AgentAuthError::Storage(_) => "storage",
+            AgentAuthError::Io(_) => "io",
+            AgentAuthError::Parse(_) => "parse_error",
+        };
+        Self::new(code, e)
+    }
+}
+
+impl From<RouterError> for CommandError {
+    fn from(e: RouterError) -> Self {
+        let code = match &e {
+            RouterError::RuleNotFound(_) => "not_found",
+            RouterError::Storage(_) => "storage",
+            RouterError::InvalidPattern(_) | RouterError::InvalidHeader(_) => "invalid_input",
+        };
+        Self::new(code, e)
+    }
+}
+
+impl From<ProviderError> for CommandError {
+    fn from(e: ProviderError) -> Self {
+        let code = match &e {
+            ProviderError::NotFound(_) => "not_found",
+            ProviderError::InvalidBaseUrl(_) | ProviderError::InvalidAuthHeader(_) => {
+                "invalid_input"
+            }
+            ProviderError::NoPricing(_) => "not_configured",
+            ProviderError::Storage(_) => "storage",
+            ProviderError::Io(_) => "io",
+        };
+        Self::new(code, e)
+    }
+}
+
+impl From<AgentError> for CommandError {
+    fn from(e: AgentError) -> Self {
+        let code = match &e {
+            AgentError::CommandError(_) => "command_failed",
+            AgentError::InvalidConfig(_) => "invalid_input",
+        };
+        Self::new(code, e)
+    }
+}
+
+impl From<AgentProxyError> for CommandError {
+    fn from(e: AgentProxyError) -> Self {
+        let code = match &e {
+            AgentProxyError::UnsupportedAgent(_) | AgentProxyError::TerminalUnavailable => {
+                "unsupported"
+            }
+            AgentProxyError::HomeDirectoryUnavailable => "home_dir_unavailable",
+            AgentProxyError::InvalidConfigFormat(_) => "invalid_input",
+            AgentProxyError::BackupNotFound(_) => "not_found",
+            AgentProxyError::NotInstalled(_) => "not_installed",
+            AgentProxyError::Io(_) => "io",
+            AgentProxyError::Json(_) | AgentProxyError::Toml(_) => "parse_error",
+            AgentProxyError::Storage(_) => "storage",
+        };
+        Self::new(code, e)
+    }
+}
+
+impl From<ConfigError> for CommandError {
+    fn from(e: ConfigError) -> Self {
+        let code = match &e {
+            ConfigError::Storage(_) => "storage",
+            ConfigError::InvalidProxyUrl(_)
+            | ConfigError::InvalidModelAlias(_)
+            | ConfigError::InvalidCorsOrigin(_)
+            | ConfigError::InvalidRoutePrefix(_) => "invalid_input",
+            ConfigError::ProviderNotFound(_) => "not_found",
+        };
+        Self::new(code, e)
+    }
+}
+
+impl From<ProxyError> for CommandError {
+    fn from(e: ProxyError) -> Self {
+        let code = match &e {
+            ProxyError::AlreadyRunning => "already_running",
+            ProxyError::NotRunning => "not_running",
+            ProxyError::BindFailed(_) => "port_unavailable",
+            ProxyError::Io(_) => "io",
+            ProxyError::Replay(_) => "replay_failed",
+        };
+        Self::new(code, e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_error_codes() {
+        let error = CommandError::from(AgentAuthError::Unauthorized);
+        assert_eq!(error.code, "auth_expired");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "code": "auth_expired",
+                "message": "Unauthorized - token expired or invalid"
+            })
+        );
+        assert_eq!(CommandError::from(ProviderError::NotFound("p1".into())).code, "not_found");
+    }
+}
@@ -5,6 +5,7 @@ mod router;
 mod agent;
 mod config;
 mod system;
+mod error;
 
 pub use agent_auth::*;
 pub use agent_proxy::*;
@@ -13,3 +14,4 @@ pub use router::*;
 pub use agent::*;
 pub use config::*;
 pub use system::*;
+pub use error::CommandError;
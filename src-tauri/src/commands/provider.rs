@@ -1,30 +1,43 @@
 use std::sync::Arc;
 use tauri::State;
 
+use super::CommandError;
 use crate::models::{
-    ConnectionStatus, CreateProviderInput, Provider, UpdateProviderInput,
+    ConnectionStatus, CreateProviderInput, OrphanAuthFiles, Provider, ProviderFilter,
+    ProviderImportResult, ProviderPing, ProviderSpend, UpdateProviderInput,
 };
-use crate::services::ProviderService;
+use crate::services::{ProviderService, ProxyServer};
 
 #[tauri::command]
 pub async fn list_providers(
     service: State<'_, Arc<ProviderService>>,
-) -> Result<Vec<Provider>, String> {
+    filter: Option<ProviderFilter>,
+) -> Result<Vec<Provider>, CommandError> {
     service
-        .list_providers()
+        .list_providers(&filter.unwrap_or_default())
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn list_provider_groups(
+    service: State<'_, Arc<ProviderService>>,
+) -> Result<Vec<String>, CommandError> {
+    service
+        .list_provider_groups()
+        .await
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
 pub async fn create_provider(
     service: State<'_, Arc<ProviderService>>,
     input: CreateProviderInput,
-) -> Result<Provider, String> {
+) -> Result<Provider, CommandError> {
     service
         .create_provider(input)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
@@ -32,31 +45,147 @@ pub async fn update_provider(
     service: State<'_, Arc<ProviderService>>,
     id: String,
     input: UpdateProviderInput,
-) -> Result<Provider, String> {
+) -> Result<Provider, CommandError> {
     service
         .update_provider(&id, input)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn import_providers(
+    service: State<'_, Arc<ProviderService>>,
+    inputs: Vec<CreateProviderInput>,
+) -> Result<ProviderImportResult, CommandError> {
+    service
+        .import_providers(inputs)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn duplicate_provider(
+    service: State<'_, Arc<ProviderService>>,
+    id: String,
+) -> Result<Provider, CommandError> {
+    service
+        .duplicate_provider(&id)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn set_provider_enabled(
+    service: State<'_, Arc<ProviderService>>,
+    id: String,
+    enabled: bool,
+) -> Result<Provider, CommandError> {
+    service
+        .set_provider_enabled(&id, enabled)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn set_provider_secret(
+    service: State<'_, Arc<ProviderService>>,
+    id: String,
+    api_key: String,
+) -> Result<Provider, CommandError> {
+    service
+        .set_provider_secret(&id, api_key)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn reorder_providers(
+    service: State<'_, Arc<ProviderService>>,
+    provider_ids: Vec<String>,
+) -> Result<(), CommandError> {
+    service
+        .reorder_providers(provider_ids)
+        .await
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
 pub async fn delete_provider(
     service: State<'_, Arc<ProviderService>>,
     id: String,
-) -> Result<(), String> {
+    remove_auth_file: Option<bool>,
+) -> Result<(), CommandError> {
+    service
+        .delete_provider(&id, remove_auth_file.unwrap_or(false))
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Report auth files no provider uses; `delete` removes them
+#[tauri::command]
+pub async fn cleanup_orphan_auth_files(
+    service: State<'_, Arc<ProviderService>>,
+    delete: Option<bool>,
+) -> Result<OrphanAuthFiles, CommandError> {
+    service
+        .cleanup_orphan_auth_files(delete.unwrap_or(false))
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn provider_curl_example(
+    service: State<'_, Arc<ProviderService>>,
+    provider_id: String,
+    sample_model: String,
+) -> Result<String, CommandError> {
+    service
+        .curl_example(&provider_id, &sample_model)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn ping_provider(
+    service: State<'_, Arc<ProviderService>>,
+    id: String,
+) -> Result<ProviderPing, CommandError> {
+    service
+        .ping_provider(&id)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Current-period spend of every provider, from proxied responses' token usage
+#[tauri::command]
+pub async fn get_provider_spend(
+    proxy: State<'_, Arc<ProxyServer>>,
+) -> Result<Vec<ProviderSpend>, CommandError> {
+    Ok(proxy.provider_spend().await)
+}
+
+/// Dollar estimate for a request, from the provider's price for `model`
+#[tauri::command]
+pub async fn estimate_request_cost(
+    service: State<'_, Arc<ProviderService>>,
+    provider_id: String,
+    model: String,
+    prompt_tokens: u64,
+    max_tokens: u64,
+) -> Result<f64, CommandError> {
     service
-        .delete_provider(&id)
+        .estimate_request_cost(&provider_id, &model, prompt_tokens, max_tokens)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
 pub async fn test_connection(
     service: State<'_, Arc<ProviderService>>,
     id: String,
-) -> Result<ConnectionStatus, String> {
+) -> Result<ConnectionStatus, CommandError> {
     service
         .test_connection(&id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
@@ -1,28 +1,43 @@
 use std::sync::Arc;
 use tauri::State;
 
-use crate::models::{CreateRuleInput, RoutingRule, UpdateRuleInput};
+use super::CommandError;
+use crate::models::{
+    CreateRuleInput, PatternTestResult, RoutingRule, RuleImportResult, RuleIssue, RulePreview,
+    RulePreviewSample, RuleType, RoutingTableEntry, UpdateRuleInput,
+};
 use crate::services::RouterService;
 
 #[tauri::command]
 pub async fn list_rules(
     service: State<'_, Arc<RouterService>>,
-) -> Result<Vec<RoutingRule>, String> {
+) -> Result<Vec<RoutingRule>, CommandError> {
     service
         .list_rules()
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
 pub async fn create_rule(
     service: State<'_, Arc<RouterService>>,
     input: CreateRuleInput,
-) -> Result<RoutingRule, String> {
+) -> Result<RoutingRule, CommandError> {
     service
         .create_rule(input)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn import_rules(
+    service: State<'_, Arc<RouterService>>,
+    inputs: Vec<CreateRuleInput>,
+) -> Result<RuleImportResult, CommandError> {
+    service
+        .import_rules(inputs)
+        .await
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
@@ -30,32 +45,96 @@ pub async fn update_rule(
     service: State<'_, Arc<RouterService>>,
     id: String,
     input: UpdateRuleInput,
-) -> Result<RoutingRule, String> {
+) -> Result<RoutingRule, CommandError> {
     service
         .update_rule(&id, input)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
 pub async fn delete_rule(
     service: State<'_, Arc<RouterService>>,
     id: String,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     service
         .delete_rule(&id)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn set_rules_enabled(
+    service: State<'_, Arc<RouterService>>,
+    ids: Vec<String>,
+    enabled: bool,
+) -> Result<Vec<RoutingRule>, CommandError> {
+    service
+        .set_rules_enabled(ids, enabled)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn list_rule_issues(
+    service: State<'_, Arc<RouterService>>,
+) -> Result<Vec<RuleIssue>, CommandError> {
+    service
+        .list_rule_issues()
+        .await
+        .map_err(CommandError::from)
 }
 
 #[tauri::command]
 pub async fn reorder_rules(
     service: State<'_, Arc<RouterService>>,
     rule_ids: Vec<String>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     service
         .reorder_rules(rule_ids)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn export_routes_dot(
+    service: State<'_, Arc<RouterService>>,
+) -> Result<String, CommandError> {
+    service
+        .export_routes_dot()
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Rules in the order the proxy tries them, per api group
+#[tauri::command]
+pub async fn get_routing_table(
+    service: State<'_, Arc<RouterService>>,
+) -> Result<Vec<RoutingTableEntry>, CommandError> {
+    service
+        .routing_table()
+        .await
+        .map_err(CommandError::from)
 }
 
+#[tauri::command]
+pub async fn preview_rule(
+    service: State<'_, Arc<RouterService>>,
+    input: CreateRuleInput,
+    sample: RulePreviewSample,
+) -> Result<RulePreview, CommandError> {
+    service
+        .preview_rule(input, sample)
+        .await
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+pub async fn test_pattern(
+    service: State<'_, Arc<RouterService>>,
+    pattern: String,
+    rule_type: RuleType,
+    samples: Vec<String>,
+) -> Result<PatternTestResult, CommandError> {
+    Ok(service.test_pattern(&pattern, &rule_type, samples))
+}
@@ -2,14 +2,18 @@ use std::sync::Arc;
 use std::time::Duration;
 use tauri::State;
 
-use crate::models::ProxyStatus;
-use crate::services::ProxyServer;
+use super::CommandError;
+use crate::models::{
+    AccessLogEntry, DiagnosticsReport, PortAvailability, ProxyStatus, ReplayResult, RequestRecord,
+    RequestTimings,
+};
+use crate::services::{self, ProxyServer};
 use crate::storage::ConfigStore;
 
 #[tauri::command]
 pub async fn proxy_status(
     state: State<'_, Arc<ProxyServer>>,
-) -> Result<ProxyStatus, String> {
+) -> Result<ProxyStatus, CommandError> {
     let port = state.port();
     let request_count = state.request_count();
     
@@ -24,6 +28,7 @@ pub async fn proxy_status(
         is_running,
         port,
         request_count,
+        paused: state.is_paused(),
     })
 }
 
@@ -46,25 +51,112 @@ async fn check_health(port: u16) -> bool {
     }
 }
 
+#[tauri::command]
+pub async fn check_port_available(port: u16) -> Result<PortAvailability, CommandError> {
+    Ok(services::check_port_available(port).await)
+}
+
 #[tauri::command]
 pub async fn start_proxy(
     state: State<'_, Arc<ProxyServer>>,
     store: State<'_, Arc<ConfigStore>>,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let config = store.get_config().await;
     let port = config.app.port;
-    state.start(port).await.map_err(|e| e.to_string())
+    state.start(port).await.map_err(CommandError::from)
 }
 
 #[tauri::command]
 pub async fn stop_proxy(
     state: State<'_, Arc<ProxyServer>>,
-) -> Result<(), String> {
-    state.stop().await.map_err(|e| e.to_string())
+) -> Result<(), CommandError> {
+    state.stop().await.map_err(CommandError::from)
+}
+
+/// Answer proxied requests with 503 without stopping the server
+#[tauri::command]
+pub async fn pause_proxy(
+    state: State<'_, Arc<ProxyServer>>,
+) -> Result<(), CommandError> {
+    state.pause();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn resume_proxy(
+    state: State<'_, Arc<ProxyServer>>,
+) -> Result<(), CommandError> {
+    state.resume();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn reset_proxy_stats(
+    state: State<'_, Arc<ProxyServer>>,
+) -> Result<(), CommandError> {
+    state.reset_stats();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn last_request_timings(
+    state: State<'_, Arc<ProxyServer>>,
+) -> Result<Option<RequestTimings>, CommandError> {
+    Ok(state.last_request_timings())
+}
+
+/// Recently proxied requests, newest first
+#[tauri::command]
+pub async fn recent_requests(
+    state: State<'_, Arc<ProxyServer>>,
+) -> Result<Vec<RequestRecord>, CommandError> {
+    Ok(state.recent_requests())
+}
+
+/// Capture request and response bodies of the next proxied request
+#[tauri::command]
+pub async fn capture_next_request(
+    state: State<'_, Arc<ProxyServer>>,
+) -> Result<(), CommandError> {
+    state.capture_next_request();
+    Ok(())
+}
+
+/// Re-send a captured request, optionally to a different provider
+#[tauri::command]
+pub async fn replay_request(
+    state: State<'_, Arc<ProxyServer>>,
+    record_id: String,
+    override_provider_id: Option<String>,
+) -> Result<ReplayResult, CommandError> {
+    state
+        .replay_request(&record_id, override_provider_id)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Most recent `n` access log entries, oldest first
+#[tauri::command]
+pub async fn tail_access_log(
+    store: State<'_, Arc<ConfigStore>>,
+    n: usize,
+) -> Result<Vec<AccessLogEntry>, CommandError> {
+    let path = services::access_log_path(store.config_dir());
+    services::tail_access_log(&path, n)
+        .await
+        .map_err(CommandError::from)
+}
+
+/// Environment readiness report for first-run troubleshooting
+#[tauri::command]
+pub async fn diagnostics(
+    state: State<'_, Arc<ProxyServer>>,
+) -> Result<DiagnosticsReport, CommandError> {
+    Ok(services::run_diagnostics(&state).await)
 }
 
 #[tauri::command]
-pub async fn get_version() -> Result<String, String> {
+pub async fn get_version() -> Result<String, CommandError> {
     Ok(env!("CARGO_PKG_VERSION").to_string())
 }
 
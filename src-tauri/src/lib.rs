@@ -12,10 +12,9 @@ use services::{
 };
 use tauri::Manager;
 
-/// Get config directory path (~/.vibemate/)
+/// Get config directory path (~/.vibemate/ unless overridden, see `storage::config_dir`)
 fn get_config_dir() -> std::path::PathBuf {
-    let home = dirs::home_dir().expect("Failed to get home directory");
-    home.join(".vibemate")
+    storage::config_dir().to_path_buf()
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -31,7 +30,7 @@ pub fn run() {
         )
         .plugin(tauri_plugin_opener::init())
         .setup(|app| {
-            // Use ~/.vibemate/ as config directory
+            // Use ~/.vibemate/ (or $VIBEMATE_CONFIG_DIR) as config directory
             let config_dir = get_config_dir();
 
             // Initialize unified config storage
@@ -44,9 +43,12 @@ pub fn run() {
             let provider_service = Arc::new(ProviderService::new(store.clone()));
             let router_service = Arc::new(RouterService::new(store.clone()));
             let agent_service = Arc::new(AgentService::new());
-            let config_service = Arc::new(ConfigService::new(store.clone()));
             let agent_auth_service = Arc::new(AgentAuthService::new(store.clone()));
             let agent_proxy_service = Arc::new(AgentProxyService::new(store.clone()));
+            let config_service = Arc::new(ConfigService::new(
+                store.clone(),
+                agent_proxy_service.clone(),
+            ));
             
             // Create the proxy server with access to the config store
             let proxy_server = Arc::new(ProxyServer::new(store.clone()));
@@ -55,9 +57,9 @@ pub fn run() {
             let store_clone = store.clone();
             let agent_service_clone = agent_service.clone();
             tauri::async_runtime::block_on(async move {
-                match agent_service_clone.discover_agents() {
+                let config = store_clone.get_config().await;
+                match agent_service_clone.discover_agents(&config.coding_agents, false) {
                     Ok(discovered) => {
-                        let config = store_clone.get_config().await;
                         let merged = merge_coding_agents(
                             &config.coding_agents,
                             discovered,
@@ -72,6 +74,20 @@ pub fn run() {
                 }
             });
 
+            // Report (never delete) auth files left behind by removed providers
+            let provider_service_clone = provider_service.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = provider_service_clone.cleanup_orphan_auth_files(false).await {
+                    tracing::warn!("Failed to check for orphan auth files: {}", e);
+                }
+            });
+
+            // Stop callback servers of auth flows the user abandoned
+            let agent_auth_clone = agent_auth_service.clone();
+            tauri::async_runtime::spawn(async move {
+                agent_auth_clone.run_flow_sweeper().await;
+            });
+
             // Register services to Tauri state management
             let store_for_proxy = store.clone();
             app.manage(store);
@@ -92,7 +108,12 @@ pub fn run() {
                 if let Err(e) = proxy_server_clone.start(port).await {
                     tracing::error!("Failed to start proxy server on port {}: {}", port, e);
                 } else {
-                    tracing::info!("Vibe Mate server started on port {} - OpenAI: /api/openai, Anthropic: /api/anthropic", port);
+                    tracing::info!(
+                        "Vibe Mate server started on port {} - OpenAI: {}, Anthropic: {}",
+                        port,
+                        services::OPENAI_API_PREFIX,
+                        services::ANTHROPIC_API_PREFIX
+                    );
                 }
             });
 
@@ -101,31 +122,64 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             // Provider commands
             commands::list_providers,
+            commands::list_provider_groups,
             commands::create_provider,
             commands::update_provider,
+            commands::import_providers,
+            commands::duplicate_provider,
+            commands::set_provider_enabled,
+            commands::set_provider_secret,
+            commands::reorder_providers,
             commands::delete_provider,
             commands::test_connection,
+            commands::provider_curl_example,
+            commands::ping_provider,
+            commands::get_provider_spend,
+            commands::estimate_request_cost,
+            commands::cleanup_orphan_auth_files,
             // Agent auth commands
             commands::start_agent_auth,
             commands::complete_agent_auth,
+            commands::complete_agent_auth_manual,
             commands::get_agent_quota,
+            commands::list_agent_organizations,
+            commands::validate_agent_tokens,
+            commands::inspect_auth_file,
+            commands::get_quota_history,
             commands::list_agent_accounts,
             commands::remove_agent_auth,
             // Router commands
             commands::list_rules,
             commands::create_rule,
+            commands::import_rules,
             commands::update_rule,
             commands::delete_rule,
+            commands::set_rules_enabled,
             commands::reorder_rules,
+            commands::list_rule_issues,
+            commands::export_routes_dot,
+            commands::get_routing_table,
+            commands::preview_rule,
+            commands::test_pattern,
             // Agent commands
             commands::check_status,
             commands::read_agent_config,
             commands::save_agent_config,
             commands::is_agent_proxy_enabled,
             commands::set_agent_proxy_enabled,
+            commands::preview_agent_proxy_change,
+            commands::verify_agent_proxy_config,
+            commands::restore_agent_config,
+            commands::launch_agent_with_proxy,
+            commands::get_proxy_endpoints,
             // Config commands
             commands::get_config,
             commands::update_config,
+            commands::reload_config,
+            commands::get_model_aliases,
+            commands::set_model_aliases,
+            commands::set_group_default_provider,
+            commands::clear_group_default_provider,
             commands::test_latency,
             commands::get_coding_agents,
             commands::refresh_coding_agents,
@@ -134,6 +188,16 @@ pub fn run() {
             commands::proxy_status,
             commands::start_proxy,
             commands::stop_proxy,
+            commands::pause_proxy,
+            commands::resume_proxy,
+            commands::check_port_available,
+            commands::reset_proxy_stats,
+            commands::last_request_timings,
+            commands::recent_requests,
+            commands::capture_next_request,
+            commands::replay_request,
+            commands::tail_access_log,
+            commands::diagnostics,
             commands::get_version,
         ])
         .run(tauri::generate_context!())
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use crate::agents::agent_metadata;
@@ -32,6 +33,10 @@ pub struct CodingAgent {
     pub agent_type: AgentType,
     pub name: String,
     pub version: Option<String>,
+    /// When `version` was last read from the binary
+    pub version_checked_at: Option<DateTime<Utc>>,
+    /// Binary modification time (unix seconds) when `version` was read
+    pub binary_mtime: Option<i64>,
     pub status: AgentStatus,
     pub executable_path: Option<String>,
     pub config_path: Option<String>,
@@ -48,6 +53,8 @@ impl Default for CodingAgent {
             agent_type: AgentType::ClaudeCode,
             name: String::new(),
             version: None,
+            version_checked_at: None,
+            binary_mtime: None,
             status: AgentStatus::default(),
             executable_path: None,
             config_path: None,
@@ -65,6 +72,8 @@ impl CodingAgent {
             name: metadata.name.to_string(),
             agent_type,
             version: None,
+            version_checked_at: None,
+            binary_mtime: None,
             status: AgentStatus::NotInstalled,
             executable_path: Some(metadata.binary.to_string()),
             config_path: Some(metadata.default_config_file.to_string()),
@@ -13,6 +13,9 @@ pub enum AgentProviderType {
 pub struct AgentAuthStart {
     pub flow_id: String,
     pub auth_url: String,
+    /// The callback server couldn't start: the user copies the code (or the whole
+    /// redirect URL) from the browser and submits it with `complete_agent_auth_manual`
+    pub manual: bool,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -36,10 +39,82 @@ pub struct AgentQuota {
     pub note: Option<String>,
 }
 
+/// One quota sample in an agent's usage history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaHistoryPoint {
+    /// Unix timestamp in milliseconds
+    pub timestamp: i64,
+    pub session_used_percent: f64,
+    pub week_used_percent: f64,
+}
+
+/// An organization the signed-in agent account belongs to (Codex)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentOrganization {
+    /// Sent as `ChatGPT-Account-Id` to query this organization's usage
+    pub id: String,
+    pub title: Option<String>,
+    /// The organization whose id is stored with the login and used by default
+    pub is_default: bool,
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AgentAccountInfo {
     pub agent_type: AgentProviderType,
     pub is_authenticated: bool,
     pub email: Option<String>,
+    /// The account requests and quota lookups use for this agent. With one login per
+    /// agent this is the stored login whenever there is one.
+    pub is_active: bool,
+    /// Where the login is (or would be) stored; empty if the config dir is unavailable
+    pub auth_path: String,
+}
+
+/// Result of probing a stored agent login
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub enum TokenValidity {
+    Valid,
+    /// The token had expired (or was rejected) and a refresh fixed it
+    Refreshed,
+    /// The refresh token was rejected; the user needs to log in again
+    Expired,
+}
+
+/// Whether an agent's auth file could be read
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub enum AuthFileStatus {
+    Present,
+    Missing,
+    /// The file exists but can't be read or parsed; see `error`
+    Unreadable,
+}
+
+/// Diagnostic view of a stored agent login. Never carries token values.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthFileReport {
+    pub agent_type: AgentProviderType,
+    pub auth_path: String,
+    pub status: AuthFileStatus,
+    pub error: Option<String>,
+    pub email: Option<String>,
+    /// Access token expiry as stored (RFC 3339)
+    pub expires_at: Option<String>,
+    pub expired: bool,
+    /// Inside the agent's refresh window, so the next use refreshes the token
+    pub needs_refresh: bool,
+    pub has_access_token: bool,
+    pub has_refresh_token: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentTokenStatus {
+    pub agent_type: AgentProviderType,
+    /// None when the check itself failed (e.g. offline), see `error`
+    pub validity: Option<TokenValidity>,
+    pub error: Option<String>,
 }
@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+use super::{AgentProviderType, AgentType, PortAvailability};
+
+/// Environment readiness report, rendered by the UI as a checklist
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsReport {
+    pub config_dir: String,
+    pub config_dir_writable: bool,
+    pub proxy_running: bool,
+    /// Availability of the configured proxy port; None while the proxy itself holds it
+    pub port: Option<PortAvailability>,
+    pub agents: Vec<AgentDiagnostic>,
+    pub auth_files: Vec<AuthFileDiagnostic>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentDiagnostic {
+    pub agent_type: AgentType,
+    /// Resolved binary, None when the agent is not installed
+    pub executable_path: Option<String>,
+}
+
+/// Auth file of a provider backed by an agent account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthFileDiagnostic {
+    pub provider_id: String,
+    pub provider_name: String,
+    pub agent_type: AgentProviderType,
+    pub path: Option<String>,
+    pub exists: bool,
+    /// The file exists and contains valid JSON
+    pub valid: bool,
+    pub error: Option<String>,
+}
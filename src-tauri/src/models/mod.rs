@@ -3,9 +3,13 @@ mod routing_rule;
 mod agent;
 mod config;
 mod agent_auth;
+mod proxy;
+mod diagnostics;
 
 pub use provider::*;
 pub use routing_rule::*;
 pub use agent::*;
 pub use config::*;
 pub use agent_auth::*;
+pub use proxy::*;
+pub use diagnostics::*;
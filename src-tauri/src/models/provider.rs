@@ -1,12 +1,23 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::AgentProviderType;
+
+/// `api_key` value meaning the real key is stored in ~/.vibemate/secrets.json
+pub const SECRET_KEY_PLACEHOLDER: &str = "@secrets.json";
+
+/// Placeholder replaced by the API key in `Provider::auth_header_template`
+pub const AUTH_KEY_PLACEHOLDER: &str = "{key}";
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ProviderType {
     OpenAI,
     Anthropic,
     Google,
     OpenRouter,
+    Azure,
     Custom,
 }
 
@@ -38,12 +49,165 @@ pub struct Provider {
     pub provider_type: ProviderType,
     pub api_base_url: Option<String>,
     pub api_key: Option<String>,
+    /// Authenticate with this agent account's OAuth login instead of `api_key`
+    #[serde(default)]
+    pub agent_account: Option<AgentProviderType>,
+    /// Maximum in-flight proxied requests to this provider (None = unlimited)
+    #[serde(default)]
+    pub max_concurrent_requests: Option<u32>,
+    /// Disabled providers are kept (with their rules) but never routed to
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// OpenRouter attribution: `HTTP-Referer` header (defaults to the Vibe Mate site)
+    #[serde(default)]
+    pub http_referer: Option<String>,
+    /// OpenRouter attribution: `X-Title` header (defaults to "Vibe Mate")
+    #[serde(default)]
+    pub app_title: Option<String>,
+    /// Custom providers: header carrying the API key (defaults to `Authorization`)
+    #[serde(default)]
+    pub auth_header_name: Option<String>,
+    /// Custom providers: header value with a `{key}` placeholder (defaults to `Bearer {key}`)
+    #[serde(default)]
+    pub auth_header_template: Option<String>,
+    /// Without a stored API key, forward the client's own `Authorization` header upstream
+    #[serde(default)]
+    pub passthrough_auth: bool,
+    /// Rewrite JSON keys in non-streaming responses, for clients that reject extra fields
+    #[serde(default)]
+    pub response_transform: Option<ResponseTransform>,
+    /// User-Agent sent upstream for this provider, overriding the global `user_agent`
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    /// Azure OpenAI: deployment name used in the URL (defaults to the routed model)
+    #[serde(default)]
+    pub deployment: Option<String>,
+    /// Azure OpenAI: `api-version` query parameter
+    #[serde(default)]
+    pub api_version: Option<String>,
+    /// Model sent for every request routed here, replacing the client's and any rule rewrite
+    #[serde(default)]
+    pub force_model: Option<String>,
+    /// Send an `Idempotency-Key` header, shared by every retry of the same client request
+    #[serde(default)]
+    pub idempotency_keys: bool,
+    /// Dollars per 1k prompt tokens, for spend tracking
+    #[serde(default)]
+    pub input_price_per_1k: Option<f64>,
+    /// Dollars per 1k completion tokens, for spend tracking
+    #[serde(default)]
+    pub output_price_per_1k: Option<f64>,
+    /// Model -> price, overriding the provider-wide prices for that model
+    #[serde(default)]
+    pub model_prices: HashMap<String, ModelPrice>,
+    /// Once spend reaches this cap, routing skips the provider like a disabled one
+    #[serde(default)]
+    pub spend_limit: Option<SpendLimit>,
+    /// Free-form labels for organizing the provider list
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Group the provider is listed under, e.g. "work" or "personal"
+    #[serde(default)]
+    pub group: Option<String>,
     pub status: ProviderStatus,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+fn default_enabled() -> bool {
+    true
+}
+
+impl Provider {
+    /// Price of `model`: its `model_prices` entry, else the provider-wide prices.
+    /// None when neither is configured.
+    pub fn price_for(&self, model: &str) -> Option<ModelPrice> {
+        if let Some(price) = self.model_prices.get(model) {
+            return Some(*price);
+        }
+        if self.input_price_per_1k.is_none() && self.output_price_per_1k.is_none() {
+            return None;
+        }
+        Some(ModelPrice {
+            input_per_1k: self.input_price_per_1k.unwrap_or(0.0),
+            output_per_1k: self.output_price_per_1k.unwrap_or(0.0),
+        })
+    }
+}
+
+/// Dollars per 1k tokens
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct ModelPrice {
+    pub input_per_1k: f64,
+    pub output_per_1k: f64,
+}
+
+impl ModelPrice {
+    pub fn cost(&self, input_tokens: u64, output_tokens: u64) -> f64 {
+        (self.input_per_1k * input_tokens as f64 + self.output_per_1k * output_tokens as f64)
+            / 1000.0
+    }
+}
+
+/// Keys removed or renamed in a provider's JSON responses, at any nesting depth
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
+pub struct ResponseTransform {
+    pub drop_keys: Vec<String>,
+    /// Old key -> new key
+    pub rename_keys: HashMap<String, String>,
+}
+
+impl ResponseTransform {
+    pub fn is_empty(&self) -> bool {
+        self.drop_keys.is_empty() && self.rename_keys.is_empty()
+    }
+
+    /// Apply the transform to every object in `value`
+    pub fn apply(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for key in &self.drop_keys {
+                    map.remove(key);
+                }
+                for (from, to) in &self.rename_keys {
+                    if let Some(v) = map.remove(from) {
+                        map.insert(to.clone(), v);
+                    }
+                }
+                map.values_mut().for_each(|v| self.apply(v));
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(|v| self.apply(v)),
+            _ => {}
+        }
+    }
+}
+
 impl Provider {
+    /// API key to send upstream. A `${ENV_VAR}` value is read from the environment at
+    /// request time, falling back to the literal text when the variable is unset.
+    /// An empty key counts as no key.
+    pub fn resolved_api_key(&self) -> Option<String> {
+        self.resolved_api_key_from(|name| std::env::var(name).ok())
+    }
+
+    /// `resolved_api_key` reading variables through `lookup`
+    pub(crate) fn resolved_api_key_from(
+        &self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Option<String> {
+        let key = self.api_key.as_deref()?;
+        let resolved = key
+            .strip_prefix("${")
+            .and_then(|rest| rest.strip_suffix('}'))
+            .and_then(lookup)
+            .unwrap_or_else(|| key.to_string());
+        Some(resolved).filter(|k| !k.is_empty())
+    }
+
     pub fn new_model(
         name: String,
         provider_type: ProviderType,
@@ -57,6 +221,26 @@ impl Provider {
             provider_type,
             api_base_url: Some(api_base_url),
             api_key: Some(api_key),
+            agent_account: None,
+            max_concurrent_requests: None,
+            enabled: true,
+            http_referer: None,
+            app_title: None,
+            auth_header_name: None,
+            auth_header_template: None,
+            passthrough_auth: false,
+            response_transform: None,
+            user_agent: None,
+            deployment: None,
+            api_version: None,
+            force_model: None,
+            idempotency_keys: false,
+            input_price_per_1k: None,
+            output_price_per_1k: None,
+            model_prices: HashMap::new(),
+            spend_limit: None,
+            tags: Vec::new(),
+            group: None,
             status: ProviderStatus::Disconnected,
             created_at: now,
             updated_at: now,
@@ -72,6 +256,44 @@ pub struct CreateProviderInput {
     pub provider_type: ProviderType,
     pub api_base_url: Option<String>,
     pub api_key: Option<String>,
+    #[serde(default)]
+    pub agent_account: Option<AgentProviderType>,
+    #[serde(default)]
+    pub max_concurrent_requests: Option<u32>,
+    #[serde(default)]
+    pub http_referer: Option<String>,
+    #[serde(default)]
+    pub app_title: Option<String>,
+    #[serde(default)]
+    pub auth_header_name: Option<String>,
+    #[serde(default)]
+    pub auth_header_template: Option<String>,
+    #[serde(default)]
+    pub passthrough_auth: bool,
+    #[serde(default)]
+    pub response_transform: Option<ResponseTransform>,
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub deployment: Option<String>,
+    #[serde(default)]
+    pub api_version: Option<String>,
+    #[serde(default)]
+    pub force_model: Option<String>,
+    #[serde(default)]
+    pub idempotency_keys: bool,
+    #[serde(default)]
+    pub input_price_per_1k: Option<f64>,
+    #[serde(default)]
+    pub output_price_per_1k: Option<f64>,
+    #[serde(default)]
+    pub model_prices: HashMap<String, ModelPrice>,
+    #[serde(default)]
+    pub spend_limit: Option<SpendLimit>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub group: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -80,6 +302,118 @@ pub struct UpdateProviderInput {
     pub name: Option<String>,
     pub api_base_url: Option<String>,
     pub api_key: Option<String>,
+    pub agent_account: Option<AgentProviderType>,
+    pub max_concurrent_requests: Option<u32>,
+    pub http_referer: Option<String>,
+    pub app_title: Option<String>,
+    pub auth_header_name: Option<String>,
+    pub auth_header_template: Option<String>,
+    pub passthrough_auth: Option<bool>,
+    /// An empty transform removes it
+    pub response_transform: Option<ResponseTransform>,
+    /// An empty string falls back to the global `user_agent`
+    pub user_agent: Option<String>,
+    pub deployment: Option<String>,
+    pub api_version: Option<String>,
+    /// An empty string stops forcing a model
+    pub force_model: Option<String>,
+    pub idempotency_keys: Option<bool>,
+    /// 0 clears the price
+    pub input_price_per_1k: Option<f64>,
+    pub output_price_per_1k: Option<f64>,
+    /// Replaces the whole table
+    pub model_prices: Option<HashMap<String, ModelPrice>>,
+    /// A limit of 0 removes it
+    pub spend_limit: Option<SpendLimit>,
+    /// Replaces all tags
+    pub tags: Option<Vec<String>>,
+    /// An empty string removes the provider from its group
+    pub group: Option<String>,
+}
+
+/// Narrows `list_providers`; every set field must match
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderFilter {
+    pub tag: Option<String>,
+    pub group: Option<String>,
+}
+
+impl ProviderFilter {
+    pub fn matches(&self, provider: &Provider) -> bool {
+        self.tag
+            .as_ref()
+            .is_none_or(|tag| provider.tags.iter().any(|t| t == tag.trim()))
+            && self
+                .group
+                .as_ref()
+                .is_none_or(|group| provider.group.as_deref() == Some(group.trim()))
+    }
+}
+
+/// Outcome of a bulk provider import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderImportResult {
+    pub created: Vec<Provider>,
+    pub errors: Vec<ProviderImportError>,
+}
+
+/// An import item that was skipped, identified by its position in the input list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderImportError {
+    pub index: usize,
+    pub name: String,
+    pub error: String,
+}
+
+/// Calendar period (UTC) a spend limit applies to
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum SpendPeriod {
+    Daily,
+    #[default]
+    Monthly,
+}
+
+/// Cap on what a provider may spend in each period
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SpendLimit {
+    pub limit_usd: f64,
+    pub period: SpendPeriod,
+}
+
+/// A provider's spend in the current period, computed from response token usage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderSpend {
+    pub provider_id: String,
+    pub period: SpendPeriod,
+    /// Current period, e.g. "2026-10" or "2026-10-16"
+    pub period_start: String,
+    pub spent_usd: f64,
+    pub limit_usd: Option<f64>,
+}
+
+/// Files in ~/.vibemate/auth/ not used by any provider's agent account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanAuthFiles {
+    pub files: Vec<String>,
+    /// Whether the files were deleted (only when requested)
+    pub removed: bool,
+}
+
+/// Time to first byte of an authenticated request to a provider's model list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderPing {
+    /// A 2xx response was received
+    pub success: bool,
+    pub status: Option<u16>,
+    pub latency_ms: Option<u64>,
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
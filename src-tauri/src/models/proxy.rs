@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::ApiGroup;
+
+/// Time spent in each stage of a proxied request, in milliseconds.
+/// For streaming responses `response_ms` covers setting up the stream, not its full duration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestTimings {
+    pub body_read_ms: f64,
+    pub routing_ms: f64,
+    pub body_transform_ms: f64,
+    pub upstream_ms: f64,
+    pub response_ms: f64,
+    pub total_ms: f64,
+}
+
+/// One line of the access log (~/.vibemate/access.jsonl)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessLogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub method: String,
+    /// Request path with credential-like query parameters redacted
+    pub path: String,
+    pub api_group: ApiGroup,
+    pub provider: Option<String>,
+    /// Model sent upstream after aliases and rewrites
+    pub model: Option<String>,
+    pub status: u16,
+    pub duration_ms: f64,
+    pub request_bytes: u64,
+    /// Response body size, None for streaming responses
+    pub response_bytes: Option<u64>,
+    pub timings: Option<RequestTimings>,
+}
+
+/// Summary of a recent proxied request, kept in memory for the request inspector
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestRecord {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub method: String,
+    pub path: String,
+    pub api_group: ApiGroup,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    /// Id of the routing rule that matched, "default" when the default provider was used;
+    /// None when the request was rejected before routing
+    pub rule: Option<String>,
+    /// A rule matched but its provider is gone, so the default provider took the request
+    pub fallback: bool,
+    pub status: u16,
+    pub duration_ms: f64,
+    /// Only set for a request captured via `capture_next_request`
+    pub request_body: Option<String>,
+    /// Only set for a captured request with a non-streaming response
+    pub response_body: Option<String>,
+    /// Unredacted path and query of a captured request; memory only, for replays
+    #[serde(skip)]
+    pub replay_uri: Option<String>,
+    /// Content-Type of a captured request; memory only, for replays
+    #[serde(skip)]
+    pub request_content_type: Option<String>,
+}
+
+/// Upstream answer to a replayed request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayResult {
+    pub status: u16,
+    pub body: String,
+    /// Provider the request was sent to, None when it was rejected before routing
+    pub provider: Option<String>,
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -44,6 +46,12 @@ pub struct RoutingRule {
     pub model_rewrite: Option<String>,
     pub priority: i32,
     pub enabled: bool,
+    /// Translate request/response bodies when the provider speaks a different API format
+    #[serde(default)]
+    pub translate: bool,
+    /// Headers set on the forwarded request, replacing any client-supplied value
+    #[serde(default)]
+    pub add_headers: HashMap<String, String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -66,6 +74,8 @@ impl RoutingRule {
             model_rewrite: None,
             priority,
             enabled: true,
+            translate: false,
+            add_headers: HashMap::new(),
             created_at: now,
             updated_at: now,
         }
@@ -84,6 +94,10 @@ pub struct CreateRuleInput {
     pub model_rewrite: Option<String>,
     #[serde(default = "default_enabled")]
     pub enabled: bool,
+    #[serde(default)]
+    pub translate: bool,
+    #[serde(default)]
+    pub add_headers: HashMap<String, String>,
 }
 
 fn default_enabled() -> bool {
@@ -99,4 +113,82 @@ pub struct UpdateRuleInput {
     pub match_pattern: Option<String>,
     pub model_rewrite: Option<String>,
     pub enabled: Option<bool>,
+    pub translate: Option<bool>,
+    pub add_headers: Option<HashMap<String, String>>,
+}
+
+/// Sample request used to preview rule matching
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RulePreviewSample {
+    pub model: Option<String>,
+    /// Full request path, e.g. `/api/openai/v1/chat/completions`
+    pub path: String,
+    pub api_group: ApiGroup,
+}
+
+/// Result of previewing a candidate rule against a sample request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RulePreview {
+    /// Rule that would handle the request, None when the default provider would be used
+    pub winning_rule_id: Option<String>,
+    /// Whether the winning rule is the (unsaved) candidate
+    pub is_candidate: bool,
+}
+
+/// One row of the effective routing table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutingTableEntry {
+    /// 1-based order in which the proxy tries this rule within its api group
+    pub position: usize,
+    pub rule_id: String,
+    pub api_group: ApiGroup,
+    pub rule_type: RuleType,
+    pub match_pattern: String,
+    pub priority: i32,
+    pub provider_id: String,
+    /// None when the provider no longer exists
+    pub provider_name: Option<String>,
+    pub model_rewrite: Option<String>,
+    pub enabled: bool,
+    /// Considered when routing: the rule and its provider are both enabled
+    pub active: bool,
+}
+
+/// Samples a pattern matched in the rule editor, or why the pattern is invalid
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatternTestResult {
+    pub matches: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// Outcome of a bulk rule import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleImportResult {
+    pub created: Vec<RoutingRule>,
+    pub errors: Vec<RuleImportError>,
+    /// Imported or stored rules dropped as duplicates, when there were any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicates_removed: Option<usize>,
+}
+
+/// An import item that was skipped, identified by its position in the input list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleImportError {
+    pub index: usize,
+    pub match_pattern: String,
+    pub error: String,
+}
+
+/// A problem with a stored rule that the UI should warn about
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleIssue {
+    pub rule_id: String,
+    pub message: String,
 }
@@ -0,0 +1,177 @@
+use std::path::{Path, PathBuf};
+
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+use crate::models::AccessLogEntry;
+
+const ACCESS_LOG_FILE: &str = "access.jsonl";
+
+/// Entries buffered between the request path and the writer task; more are dropped
+const ACCESS_LOG_BUFFER: usize = 1024;
+
+/// Query parameters whose values are credentials (e.g. Gemini's `?key=`)
+const SENSITIVE_QUERY_PARAMS: &[&str] = &["key", "api_key", "apikey", "access_token", "token"];
+
+/// Access log path inside the config directory (~/.vibemate/access.jsonl)
+pub fn access_log_path(config_dir: &Path) -> PathBuf {
+    config_dir.join(ACCESS_LOG_FILE)
+}
+
+/// Append-only access log writer. Entries go through a bounded channel to a background
+/// task, so logging never waits on disk I/O in the request path.
+#[derive(Clone)]
+pub(crate) struct AccessLogWriter {
+    tx: mpsc::Sender<AccessLogEntry>,
+}
+
+impl AccessLogWriter {
+    /// Spawn the writer task. It exits once every writer handle is dropped.
+    pub(crate) fn spawn(path: PathBuf) -> Self {
+        let (tx, mut rx) = mpsc::channel::<AccessLogEntry>(ACCESS_LOG_BUFFER);
+        tokio::spawn(async move {
+            while let Some(entry) = rx.recv().await {
+                // Drain whatever else is queued so a burst becomes one write
+                let mut lines = String::new();
+                push_line(&mut lines, &entry);
+                while let Ok(entry) = rx.try_recv() {
+                    push_line(&mut lines, &entry);
+                }
+                if let Err(e) = append(&path, lines.as_bytes()).await {
+                    tracing::warn!("Failed to write access log {}: {}", path.display(), e);
+                }
+            }
+        });
+        Self { tx }
+    }
+
+    /// Queue an entry, dropping it when the writer is backed up
+    pub(crate) fn log(&self, entry: AccessLogEntry) {
+        if self.tx.try_send(entry).is_err() {
+            tracing::warn!("Access log buffer full, dropping entry");
+        }
+    }
+}
+
+fn push_line(lines: &mut String, entry: &AccessLogEntry) {
+    match serde_json::to_string(entry) {
+        Ok(line) => {
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+        Err(e) => tracing::warn!("Failed to serialize access log entry: {}", e),
+    }
+}
+
+async fn append(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(bytes).await?;
+    file.flush().await
+}
+
+/// Last `n` access log entries, oldest first. Malformed lines are skipped.
+pub async fn tail_access_log(path: &Path, n: usize) -> std::io::Result<Vec<AccessLogEntry>> {
+    let content = match fs::read_to_string(path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut entries: Vec<AccessLogEntry> = content
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .take(n)
+        .collect();
+    entries.reverse();
+    Ok(entries)
+}
+
+/// Path plus query string with credential-like parameter values masked
+pub(crate) fn redacted_path_and_query(path: &str, query: Option<&str>) -> String {
+    let Some(query) = query.filter(|q| !q.is_empty()) else {
+        return path.to_string();
+    };
+    let query = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _))
+                if SENSITIVE_QUERY_PARAMS
+                    .iter()
+                    .any(|s| name.eq_ignore_ascii_case(s)) =>
+            {
+                format!("{}=***", name)
+            }
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    format!("{}?{}", path, query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ApiGroup;
+    use chrono::Utc;
+    use std::time::Duration;
+    use tempfile::tempdir;
+
+    fn entry(status: u16) -> AccessLogEntry {
+        AccessLogEntry {
+            timestamp: Utc::now(),
+            method: "POST".to_string(),
+            path: "/api/openai/v1/chat/completions".to_string(),
+            api_group: ApiGroup::OpenAI,
+            provider: Some("OpenAI".to_string()),
+            model: Some("gpt-4o".to_string()),
+            status,
+            duration_ms: 12.5,
+            request_bytes: 42,
+            response_bytes: Some(128),
+            timings: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_access_log_write_and_tail() {
+        let temp_dir = tempdir().unwrap();
+        let path = access_log_path(temp_dir.path());
+
+        let writer = AccessLogWriter::spawn(path.clone());
+        for status in [200, 404, 502] {
+            writer.log(entry(status));
+        }
+
+        // The writer is asynchronous; wait for all lines to land
+        let mut entries = Vec::new();
+        for _ in 0..50 {
+            entries = tail_access_log(&path, 10).await.unwrap();
+            if entries.len() == 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(entries.len(), 3);
+
+        let tail = tail_access_log(&path, 2).await.unwrap();
+        let statuses: Vec<u16> = tail.iter().map(|e| e.status).collect();
+        assert_eq!(statuses, vec![404, 502]);
+    }
+
+    #[test]
+    fn test_redacted_path_and_query() {
+        assert_eq!(
+            redacted_path_and_query("/api/v1/models", None),
+            "/api/v1/models"
+        );
+        assert_eq!(
+            redacted_path_and_query("/api/v1beta/models", Some("key=secret&alt=json")),
+            "/api/v1beta/models?key=***&alt=json"
+        );
+    }
+}
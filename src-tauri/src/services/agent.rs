@@ -1,13 +1,23 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 
-use crate::agents::{agent_metadata, all_agent_definitions, is_binary_installed};
+use chrono::{Duration, Utc};
+
+use crate::agents::{
+    agent_definition, agent_metadata, all_agent_definitions, resolve_binary_path,
+};
 use crate::models::{AgentStatus, AgentType, CodingAgent};
 
+/// Cached agent versions are re-read after this long even if the binary is unchanged
+const VERSION_CACHE_MAX_AGE_HOURS: i64 = 24;
+
 #[derive(Debug, thiserror::Error)]
 pub enum AgentError {
     #[error("Command execution error: {0}")]
     CommandError(String),
+    #[error("Invalid config: {0}")]
+    InvalidConfig(String),
 }
 
 pub struct AgentService;
@@ -18,33 +28,68 @@ impl AgentService {
     }
 
     /// Discover installed coding agents in the system.
-    /// Only returns agents that are currently installed (binary present on disk).
-    pub fn discover_agents(&self) -> Result<Vec<CodingAgent>, AgentError> {
+    /// Only returns agents that are currently installed (binary present on disk), as
+    /// `Installed` or, when their auth file exists, `Authenticated`.
+    /// Versions are reused from `cached` unless the binary changed, the cache is older than
+    /// a day, or `force_refresh` is set.
+    pub fn discover_agents(
+        &self,
+        cached: &[CodingAgent],
+        force_refresh: bool,
+    ) -> Result<Vec<CodingAgent>, AgentError> {
         let installed: Vec<CodingAgent> = all_agent_definitions()
             .into_iter()
-            .map(|def| self.check_agent(&def.metadata().agent_type))
-            .filter(|a| a.status == AgentStatus::Installed)
+            .filter(|def| def.is_installed())
+            .map(|def| {
+                let agent_type = &def.metadata().agent_type;
+                let cached = cached.iter().find(|a| &a.agent_type == agent_type);
+                self.check_agent(agent_type, cached, force_refresh)
+            })
+            .filter(|a| a.status != AgentStatus::NotInstalled)
             .collect();
         Ok(installed)
     }
 
-    /// Check a specific agent's installation status by resolving its binary path.
-    fn check_agent(&self, agent_type: &AgentType) -> CodingAgent {
-        let metadata = agent_metadata(agent_type);
-        let installed = is_binary_installed(metadata.binary);
-
+    /// Check a specific agent's installation status and version by resolving its binary path.
+    ///
+    /// When the app runs as a packaged bundle (e.g. macOS .app), the process PATH is
+    /// minimal, so the binary is resolved via `resolve_binary_path`, which also searches
+    /// well-known installation directories.
+    fn check_agent(
+        &self,
+        agent_type: &AgentType,
+        cached: Option<&CodingAgent>,
+        force_refresh: bool,
+    ) -> CodingAgent {
+        let definition = agent_definition(agent_type);
         let mut agent = CodingAgent::new(agent_type.clone());
-        agent.status = if installed {
-            AgentStatus::Installed
-        } else {
-            AgentStatus::NotInstalled
+
+        let Some(binary_path) = resolve_binary_path(definition.metadata().binary) else {
+            agent.status = AgentStatus::NotInstalled;
+            return agent;
         };
+        agent.executable_path = Some(binary_path.to_string_lossy().into_owned());
+        let auth_path = agent.auth_path.clone().map(|p| self.expand_tilde_path(p));
+        agent.status = installed_status(auth_path.as_deref());
+
+        let mtime = binary_mtime(&binary_path);
+        match cached.filter(|c| !force_refresh && is_version_cache_fresh(c, mtime)) {
+            Some(cached) => {
+                agent.version = cached.version.clone();
+                agent.version_checked_at = cached.version_checked_at;
+            }
+            None => {
+                agent.version = definition.get_version();
+                agent.version_checked_at = Some(Utc::now());
+            }
+        }
+        agent.binary_mtime = mtime;
         agent
     }
 
-    /// Check status of a specific agent
+    /// Check status of a specific agent, always re-reading its version
     pub fn check_status(&self, agent_type: &AgentType) -> Result<CodingAgent, AgentError> {
-        Ok(self.check_agent(agent_type))
+        Ok(self.check_agent(agent_type, None, true))
     }
 
     /// Get the config file path for an agent
@@ -109,6 +154,7 @@ impl AgentService {
         let config_path = self
             .resolve_config_path(agent_type, config_path)
             .ok_or_else(|| AgentError::CommandError("Could not determine home directory".to_string()))?;
+        validate_config_content(&config_path, &content)?;
         
         // Create parent directory if it doesn't exist
         if let Some(parent) = config_path.parent() {
@@ -121,8 +167,104 @@ impl AgentService {
     }
 }
 
+/// Check that config content parses in the file's format (JSON object or TOML document),
+/// so a half-edited config never replaces a working one
+fn validate_config_content(path: &Path, content: &str) -> Result<(), AgentError> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => {
+            let value: serde_json::Value = serde_json::from_str(content).map_err(|e| {
+                AgentError::InvalidConfig(format!("{} is not valid JSON: {}", path.display(), e))
+            })?;
+            if !value.is_object() {
+                return Err(AgentError::InvalidConfig(format!(
+                    "{} must contain a JSON object",
+                    path.display()
+                )));
+            }
+        }
+        Some("toml") => {
+            content.parse::<toml_edit::DocumentMut>().map_err(|e| {
+                AgentError::InvalidConfig(format!("{} is not valid TOML: {}", path.display(), e))
+            })?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Status of an installed agent: `Authenticated` once its auth file exists
+fn installed_status(auth_path: Option<&Path>) -> AgentStatus {
+    if auth_path.is_some_and(Path::exists) {
+        AgentStatus::Authenticated
+    } else {
+        AgentStatus::Installed
+    }
+}
+
+/// Binary modification time in unix seconds
+fn binary_mtime(path: &Path) -> Option<i64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64)
+}
+
+/// A cached version is reusable if it was read from the same binary within the max age
+fn is_version_cache_fresh(cached: &CodingAgent, mtime: Option<i64>) -> bool {
+    let Some(checked_at) = cached.version_checked_at else {
+        return false;
+    };
+    cached.version.is_some()
+        && mtime.is_some()
+        && cached.binary_mtime == mtime
+        && Utc::now() - checked_at < Duration::hours(VERSION_CACHE_MAX_AGE_HOURS)
+}
+
 impl Default for AgentService {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_cache_freshness() {
+        let mut cached = CodingAgent::new(AgentType::Codex);
+        cached.version = Some("0.46.0".to_string());
+        cached.binary_mtime = Some(100);
+        cached.version_checked_at = Some(Utc::now());
+        assert!(is_version_cache_fresh(&cached, Some(100)));
+
+        // Binary replaced
+        assert!(!is_version_cache_fresh(&cached, Some(200)));
+
+        // Cache too old
+        cached.version_checked_at = Some(Utc::now() - Duration::hours(25));
+        assert!(!is_version_cache_fresh(&cached, Some(100)));
+    }
+
+    #[test]
+    fn test_validate_config_content() {
+        let json = Path::new("settings.json");
+        assert!(validate_config_content(json, r#"{"env": {}}"#).is_ok());
+        let err = validate_config_content(json, r#"{"env": "#).unwrap_err().to_string();
+        assert!(err.contains("line 1"), "{}", err);
+        assert!(validate_config_content(json, "[]").is_err());
+
+        let toml = Path::new("config.toml");
+        assert!(validate_config_content(toml, "model = \"o3\"\n").is_ok());
+        assert!(validate_config_content(toml, "model = \n").is_err());
+    }
+
+    #[test]
+    fn test_installed_status_reflects_auth_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let auth_path = temp_dir.path().join("auth.json");
+        assert_eq!(installed_status(None), AgentStatus::Installed);
+        assert_eq!(installed_status(Some(&auth_path)), AgentStatus::Installed);
+
+        fs::write(&auth_path, "{}").unwrap();
+        assert_eq!(installed_status(Some(&auth_path)), AgentStatus::Authenticated);
+    }
+}
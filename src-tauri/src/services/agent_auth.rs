@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use futures_util::future::join_all;
 use axum::{
@@ -12,16 +13,27 @@ use axum::{
 use serde::Deserialize;
 use tokio::net::TcpListener;
 use tokio::sync::{oneshot, Mutex};
-use tracing::{debug, info, warn};
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
 use crate::agents::auth::{auth_path_for_agent_type, read_email_from_auth, random_state};
 use crate::agents::{
-    complete_agent_auth, get_agent_quota, start_agent_auth_flow, AgentAuthContext, AgentAuthError,
+    append_quota_history, complete_agent_auth, get_agent_quota, inspect_agent_auth_file,
+    list_agent_organizations, quota_history_path, read_quota_history, start_agent_auth_flow,
+    validate_agent_token, AgentAuthContext, AgentAuthError,
+};
+use crate::models::{
+    AgentAccountInfo, AgentAuthStart, AgentOrganization, AgentProviderType, AgentQuota,
+    AgentTokenStatus, AuthFileReport, QuotaHistoryPoint,
 };
-use crate::models::{AgentAccountInfo, AgentAuthStart, AgentProviderType, AgentQuota};
 use crate::storage::ConfigStore;
 
+/// How long a login may take before its flow is abandoned and its callback server stopped
+const AUTH_FLOW_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How often abandoned flows are swept
+const AUTH_FLOW_SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
 #[derive(Clone)]
 struct AuthServerState {
     expected_state: String,
@@ -34,7 +46,15 @@ struct PendingAuth {
     state: String,
     code_verifier: String,
     receiver: Option<oneshot::Receiver<AuthCallback>>,
-    shutdown: Option<oneshot::Sender<()>>,
+    callback_port: u16,
+    started_at: Instant,
+}
+
+/// A running auth callback server, stopped by sending on (or dropping) `shutdown`
+#[derive(Debug)]
+struct CallbackServer {
+    port: u16,
+    shutdown: oneshot::Sender<()>,
 }
 
 #[derive(Debug)]
@@ -52,6 +72,11 @@ struct AuthCallbackQuery {
 pub struct AgentAuthService {
     ctx: AgentAuthContext,
     pending: Arc<Mutex<HashMap<String, PendingAuth>>>,
+    /// Callback servers by flow id, kept apart from `pending` because a flow leaves
+    /// `pending` as soon as `complete_auth` starts waiting on it
+    callback_servers: Arc<Mutex<HashMap<String, CallbackServer>>>,
+    /// Serializes quota history writes so concurrent fetches don't drop samples
+    history_lock: Arc<Mutex<()>>,
 }
 
 impl AgentAuthService {
@@ -59,20 +84,25 @@ impl AgentAuthService {
         Self {
             ctx: AgentAuthContext::new(store),
             pending: Arc::new(Mutex::new(HashMap::new())),
+            callback_servers: Arc::new(Mutex::new(HashMap::new())),
+            history_lock: Arc::new(Mutex::new(())),
         }
     }
 
     pub async fn start_auth(&self, agent_type: AgentProviderType) -> Result<AgentAuthStart, AgentAuthError> {
         info!("Starting agent auth flow for {:?}", agent_type);
+        // A flow the user walked away from must not block new logins
+        self.sweep_expired_flows().await;
+        let state = random_state();
+        let flow = start_agent_auth_flow(&agent_type, &state)?;
+
         let mut pending = self.pending.lock().await;
-        if !pending.is_empty() {
-            warn!("Auth flow already in progress");
+        if flow_conflicts(&pending, &agent_type, flow.callback_port) {
+            warn!("Auth flow for {:?} already in progress", agent_type);
             return Err(AgentAuthError::FlowInProgress);
         }
 
         let flow_id = Uuid::new_v4().to_string();
-        let state = random_state();
-        let flow = start_agent_auth_flow(&agent_type, &state)?;
 
         let (code_tx, code_rx) = oneshot::channel();
         let (shutdown_tx, shutdown_rx) = oneshot::channel();
@@ -86,18 +116,37 @@ impl AgentAuthService {
             .route(flow.callback_path, get(auth_callback))
             .with_state(server_state);
 
-        let listener = TcpListener::bind(("127.0.0.1", flow.callback_port)).await?;
-        info!(
-            "Auth callback server listening on 127.0.0.1:{}{}",
-            flow.callback_port, flow.callback_path
-        );
-        tokio::spawn(async move {
-            let _ = axum::serve(listener, app)
-                .with_graceful_shutdown(async {
-                    let _ = shutdown_rx.await;
-                })
-                .await;
-        });
+        // Without a callback server the flow falls back to pasting the code by hand
+        let manual = match self.bind_callback_port(flow.callback_port).await {
+            Ok(listener) => {
+                info!(
+                    "Auth callback server listening on 127.0.0.1:{}{}",
+                    flow.callback_port, flow.callback_path
+                );
+                tokio::spawn(async move {
+                    let _ = axum::serve(listener, app)
+                        .with_graceful_shutdown(async {
+                            let _ = shutdown_rx.await;
+                        })
+                        .await;
+                });
+                self.callback_servers.lock().await.insert(
+                    flow_id.clone(),
+                    CallbackServer {
+                        port: flow.callback_port,
+                        shutdown: shutdown_tx,
+                    },
+                );
+                false
+            }
+            Err(e) => {
+                error!(
+                    "Cannot bind auth callback port {}: {}; falling back to manual code entry",
+                    flow.callback_port, e
+                );
+                true
+            }
+        };
 
         debug!("Auth URL generated for flow {}", flow_id);
 
@@ -107,14 +156,16 @@ impl AgentAuthService {
                 agent_type,
                 state,
                 code_verifier: flow.code_verifier,
-                receiver: Some(code_rx),
-                shutdown: Some(shutdown_tx),
+                receiver: (!manual).then_some(code_rx),
+                callback_port: flow.callback_port,
+                started_at: Instant::now(),
             },
         );
 
         Ok(AgentAuthStart {
             flow_id,
             auth_url: flow.auth_url,
+            manual,
         })
     }
 
@@ -127,68 +178,218 @@ impl AgentAuthService {
                 .ok_or_else(|| AgentAuthError::FlowNotFound(flow_id.to_string()))?
         };
 
-        let mut receiver = pending
-            .receiver
-            .ok_or_else(|| AgentAuthError::FlowNotFound(flow_id.to_string()))?;
-        let mut shutdown = pending.shutdown;
+        let Some(mut receiver) = pending.receiver else {
+            return Err(AgentAuthError::InvalidCallback(
+                "No callback server for this flow; paste the code instead".to_string(),
+            ));
+        };
 
-        let callback = match tokio::time::timeout(std::time::Duration::from_secs(300), &mut receiver)
-            .await
-        {
+        let remaining = AUTH_FLOW_TIMEOUT.saturating_sub(pending.started_at.elapsed());
+        let result = tokio::time::timeout(remaining, &mut receiver).await;
+        // The server is done whatever the outcome
+        self.stop_callback_server(flow_id).await;
+
+        let callback = match result {
             Ok(Ok(callback)) => callback,
             Ok(Err(_)) => {
-                if let Some(shutdown) = shutdown.take() {
-                    let _ = shutdown.send(());
-                }
                 return Err(AgentAuthError::InvalidCallback(
                     "Callback channel closed".to_string(),
                 ));
             }
-            Err(_) => {
-                if let Some(shutdown) = shutdown.take() {
-                    let _ = shutdown.send(());
-                }
-                return Err(AgentAuthError::Timeout);
-            }
+            Err(_) => return Err(AgentAuthError::Timeout),
         };
 
         if callback.state != pending.state {
-            if let Some(shutdown) = shutdown.take() {
-                let _ = shutdown.send(());
-            }
             return Err(AgentAuthError::InvalidCallback(
                 "State mismatch".to_string(),
             ));
         }
 
-        if let Some(shutdown) = shutdown.take() {
-            let _ = shutdown.send(());
-        }
-
         debug!(
             "Auth callback received for flow {} (code length {})",
             flow_id,
             callback.code.len()
         );
-        complete_agent_auth(
-            &self.ctx,
-            &pending.agent_type,
-            &pending.state,
-            &callback.code,
-            &pending.code_verifier,
-        )
-        .await?;
+        self.finish_auth(pending.agent_type, &pending.state, &callback.code, &pending.code_verifier)
+            .await
+    }
 
-        let email = read_email_from_auth(&pending.agent_type).await;
+    /// Complete a flow with a code the user copied from the browser: the bare code,
+    /// `code#state`, or the full redirect URL
+    pub async fn complete_auth_manual(
+        &self,
+        flow_id: &str,
+        input: &str,
+    ) -> Result<AgentAccountInfo, AgentAuthError> {
+        info!("Completing agent auth flow {} with a pasted code", flow_id);
+        let not_found = || AgentAuthError::FlowNotFound(flow_id.to_string());
+        let (code, state) = parse_pasted_code(input)
+            .ok_or_else(|| AgentAuthError::InvalidCallback("Missing code".to_string()))?;
+
+        // A bad paste leaves the flow in place so the user can paste again
+        let pending = {
+            let mut flows = self.pending.lock().await;
+            let flow = flows.get(flow_id).ok_or_else(not_found)?;
+            if state.is_some_and(|state| state != flow.state) {
+                return Err(AgentAuthError::InvalidCallback(
+                    "State mismatch".to_string(),
+                ));
+            }
+            flows.remove(flow_id).ok_or_else(not_found)?
+        };
+        self.stop_callback_server(flow_id).await;
+
+        self.finish_auth(pending.agent_type, &pending.state, &code, &pending.code_verifier)
+            .await
+    }
+
+    /// Drop flows older than the auth timeout that nobody is completing, stopping their
+    /// callback servers. Returns how many were removed.
+    pub async fn sweep_expired_flows(&self) -> usize {
+        let expired: Vec<String> = {
+            let mut pending = self.pending.lock().await;
+            let expired: Vec<String> = pending
+                .iter()
+                .filter(|(_, flow)| flow.started_at.elapsed() >= AUTH_FLOW_TIMEOUT)
+                .map(|(flow_id, _)| flow_id.clone())
+                .collect();
+            for flow_id in &expired {
+                pending.remove(flow_id);
+            }
+            expired
+        };
+        for flow_id in &expired {
+            info!("Auth flow {} expired without completing", flow_id);
+            self.stop_callback_server(flow_id).await;
+        }
+        expired.len()
+    }
+
+    /// Periodically sweep abandoned auth flows; runs for the life of the app
+    pub async fn run_flow_sweeper(&self) {
+        let mut interval = tokio::time::interval(AUTH_FLOW_SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            self.sweep_expired_flows().await;
+        }
+    }
+
+    /// Bind an auth callback port. If it is taken by a callback server an earlier flow left
+    /// running, that server is stopped and the bind retried for up to a second.
+    async fn bind_callback_port(&self, port: u16) -> std::io::Result<TcpListener> {
+        let error = match TcpListener::bind(("127.0.0.1", port)).await {
+            Ok(listener) => return Ok(listener),
+            Err(e) => e,
+        };
+        if error.kind() != std::io::ErrorKind::AddrInUse || !self.reclaim_port(port).await {
+            return Err(error);
+        }
+
+        let mut error = error;
+        for _ in 0..10 {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            match TcpListener::bind(("127.0.0.1", port)).await {
+                Ok(listener) => return Ok(listener),
+                Err(e) => error = e,
+            }
+        }
+        Err(error)
+    }
+
+    /// Stop any callback server listening on `port`; false if none was ours
+    async fn reclaim_port(&self, port: u16) -> bool {
+        let mut servers = self.callback_servers.lock().await;
+        let flow_ids: Vec<String> = servers
+            .iter()
+            .filter(|(_, server)| server.port == port)
+            .map(|(flow_id, _)| flow_id.clone())
+            .collect();
+        for flow_id in &flow_ids {
+            if let Some(server) = servers.remove(flow_id) {
+                warn!(
+                    "Stopping leftover auth callback server of flow {} on port {}",
+                    flow_id, port
+                );
+                let _ = server.shutdown.send(());
+            }
+        }
+        !flow_ids.is_empty()
+    }
+
+    async fn stop_callback_server(&self, flow_id: &str) {
+        if let Some(server) = self.callback_servers.lock().await.remove(flow_id) {
+            let _ = server.shutdown.send(());
+        }
+    }
+
+    async fn finish_auth(
+        &self,
+        agent_type: AgentProviderType,
+        state: &str,
+        code: &str,
+        code_verifier: &str,
+    ) -> Result<AgentAccountInfo, AgentAuthError> {
+        complete_agent_auth(&self.ctx, &agent_type, state, code, code_verifier).await?;
+
+        let email = read_email_from_auth(&agent_type).await;
+        let auth_path = auth_path_for_agent_type(&agent_type)?;
         Ok(AgentAccountInfo {
-            agent_type: pending.agent_type,
+            agent_type,
             is_authenticated: true,
             email,
+            is_active: true,
+            auth_path: auth_path.display().to_string(),
         })
     }
 
-    pub async fn get_quota(&self, agent_type: AgentProviderType) -> Result<AgentQuota, AgentAuthError> {
-        get_agent_quota(&self.ctx, &agent_type).await
+    /// Quota for an agent account; `account_id` picks another organization (Codex).
+    /// History only records the default account, so charts never mix organizations.
+    pub async fn get_quota(
+        &self,
+        agent_type: AgentProviderType,
+        account_id: Option<String>,
+    ) -> Result<AgentQuota, AgentAuthError> {
+        let quota = get_agent_quota(&self.ctx, &agent_type, account_id.as_deref()).await?;
+        if account_id.is_none() {
+            self.record_quota_history(&agent_type, &quota);
+        }
+        Ok(quota)
+    }
+
+    pub async fn list_organizations(
+        &self,
+        agent_type: &AgentProviderType,
+    ) -> Result<Vec<AgentOrganization>, AgentAuthError> {
+        list_agent_organizations(&self.ctx, agent_type).await
+    }
+
+    /// Recorded quota samples for an agent, oldest first
+    pub async fn get_quota_history(
+        &self,
+        agent_type: &AgentProviderType,
+    ) -> Result<Vec<QuotaHistoryPoint>, AgentAuthError> {
+        let path = quota_history_path(agent_type)?;
+        let _guard = self.history_lock.lock().await;
+        read_quota_history(&path).await
+    }
+
+    /// Append a quota sample in the background. Best-effort: failures are only logged.
+    fn record_quota_history(&self, agent_type: &AgentProviderType, quota: &AgentQuota) {
+        let path = match quota_history_path(agent_type) {
+            Ok(path) => path,
+            Err(e) => {
+                warn!("Skipping quota history for {:?}: {}", agent_type, e);
+                return;
+            }
+        };
+        let quota = quota.clone();
+        let lock = self.history_lock.clone();
+        tokio::spawn(async move {
+            let _guard = lock.lock().await;
+            if let Err(e) = append_quota_history(&path, &quota).await {
+                warn!("Failed to record quota history at {}: {}", path.display(), e);
+            }
+        });
     }
 
     pub async fn list_accounts(&self) -> Vec<AgentAccountInfo> {
@@ -208,6 +409,8 @@ impl AgentAuthService {
                         agent_type,
                         is_authenticated: false,
                         email: None,
+                        is_active: false,
+                        auth_path: String::new(),
                     };
                 }
             };
@@ -221,6 +424,8 @@ impl AgentAuthService {
                 agent_type,
                 is_authenticated,
                 email,
+                is_active: is_authenticated,
+                auth_path: path.display().to_string(),
             }
             }
         }))
@@ -228,6 +433,46 @@ impl AgentAuthService {
         results
     }
 
+    /// Validate every logged-in agent account so the UI can ask for a re-login up front
+    pub async fn validate_tokens(&self) -> Vec<AgentTokenStatus> {
+        let variants = [
+            AgentProviderType::Codex,
+            AgentProviderType::ClaudeCode,
+            AgentProviderType::GeminiCli,
+            AgentProviderType::Antigravity,
+        ];
+        let logged_in = variants.into_iter().filter(|agent_type| {
+            auth_path_for_agent_type(agent_type).is_ok_and(|path| path.exists())
+        });
+        join_all(logged_in.map(|agent_type| async move {
+            match validate_agent_token(&self.ctx, &agent_type).await {
+                Ok(validity) => AgentTokenStatus {
+                    agent_type,
+                    validity: Some(validity),
+                    error: None,
+                },
+                Err(e) => {
+                    warn!("Could not validate {:?} token: {}", agent_type, e);
+                    AgentTokenStatus {
+                        agent_type,
+                        validity: None,
+                        error: Some(e.to_string()),
+                    }
+                }
+            }
+        }))
+        .await
+    }
+
+    /// What the stored login holds, without its tokens, to diagnose "please login again"
+    pub async fn inspect_auth_file(
+        &self,
+        agent_type: &AgentProviderType,
+    ) -> Result<AuthFileReport, AgentAuthError> {
+        let path = auth_path_for_agent_type(agent_type)?;
+        Ok(inspect_agent_auth_file(agent_type, &path).await)
+    }
+
     pub async fn remove_auth(&self, agent_type: &AgentProviderType) -> Result<(), AgentAuthError> {
         let path = auth_path_for_agent_type(agent_type)?;
         if path.exists() {
@@ -238,6 +483,18 @@ impl AgentAuthService {
     }
 }
 
+/// Agents use distinct callback ports, so only a pending flow for the same agent (or port)
+/// blocks a new one
+fn flow_conflicts(
+    pending: &HashMap<String, PendingAuth>,
+    agent_type: &AgentProviderType,
+    callback_port: u16,
+) -> bool {
+    pending
+        .values()
+        .any(|p| &p.agent_type == agent_type || p.callback_port == callback_port)
+}
+
 fn split_code_and_state(code: &str) -> (String, Option<String>) {
     if let Some((left, right)) = code.split_once('#') {
         let mut state_value = right.trim();
@@ -254,6 +511,26 @@ fn split_code_and_state(code: &str) -> (String, Option<String>) {
     (code.to_string(), None)
 }
 
+/// Code and optional state from user input: a redirect URL with `code`/`state` query
+/// parameters, or a code optionally followed by `#state`
+fn parse_pasted_code(input: &str) -> Option<(String, Option<String>)> {
+    let input = input.trim();
+    let (code, state) = match reqwest::Url::parse(input) {
+        Ok(url) if url.has_host() => {
+            let param = |name: &str| {
+                url.query_pairs()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.into_owned())
+            };
+            let code = param("code")?;
+            let (code, state_from_code) = split_code_and_state(&code);
+            (code, param("state").or(state_from_code))
+        }
+        _ => split_code_and_state(input),
+    };
+    Some((code, state)).filter(|(code, _)| !code.is_empty())
+}
+
 async fn auth_callback(
     State(state): State<AuthServerState>,
     Query(params): Query<AuthCallbackQuery>,
@@ -304,3 +581,129 @@ async fn auth_callback(
     )
     .into_response()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pasted_code() {
+        assert_eq!(parse_pasted_code(" abc "), Some(("abc".to_string(), None)));
+        assert_eq!(
+            parse_pasted_code("abc#state=xyz"),
+            Some(("abc".to_string(), Some("xyz".to_string())))
+        );
+        assert_eq!(
+            parse_pasted_code("http://localhost:1455/auth/callback?code=abc&state=xyz"),
+            Some(("abc".to_string(), Some("xyz".to_string())))
+        );
+        assert_eq!(parse_pasted_code("http://localhost:1455/auth/callback?error=denied"), None);
+        assert_eq!(parse_pasted_code("  "), None);
+    }
+
+    #[tokio::test]
+    async fn test_bind_reclaims_port_from_leftover_server() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ConfigStore::new(temp_dir.path().to_path_buf()));
+        let service = AgentAuthService::new(store);
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let _ = shutdown_rx.await;
+            drop(listener);
+        });
+
+        // A port held by something else is not ours to reclaim
+        assert!(!service.reclaim_port(port).await);
+
+        service.callback_servers.lock().await.insert(
+            "old-flow".to_string(),
+            CallbackServer {
+                port,
+                shutdown: shutdown_tx,
+            },
+        );
+        assert!(service.bind_callback_port(port).await.is_ok());
+        assert!(service.callback_servers.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_bad_paste_keeps_flow_retryable() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ConfigStore::new(temp_dir.path().to_path_buf()));
+        let service = AgentAuthService::new(store);
+        service.pending.lock().await.insert(
+            "flow".to_string(),
+            PendingAuth {
+                agent_type: AgentProviderType::Codex,
+                state: "state".to_string(),
+                code_verifier: "verifier".to_string(),
+                receiver: None,
+                callback_port: 1455,
+                started_at: Instant::now(),
+            },
+        );
+
+        for paste in ["  ", "https://localhost:1455/auth/callback?state=state", "code#other"] {
+            assert!(matches!(
+                service.complete_auth_manual("flow", paste).await,
+                Err(AgentAuthError::InvalidCallback(_))
+            ));
+            assert!(service.pending.lock().await.contains_key("flow"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sweep_expired_flows() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ConfigStore::new(temp_dir.path().to_path_buf()));
+        let service = AgentAuthService::new(store);
+        let (shutdown_tx, mut shutdown_rx) = oneshot::channel::<()>();
+        let flow = |started_at| PendingAuth {
+            agent_type: AgentProviderType::Codex,
+            state: "state".to_string(),
+            code_verifier: "verifier".to_string(),
+            receiver: None,
+            callback_port: 1455,
+            started_at,
+        };
+        let stale = Instant::now() - AUTH_FLOW_TIMEOUT - Duration::from_secs(1);
+        {
+            let mut pending = service.pending.lock().await;
+            pending.insert("stale".to_string(), flow(stale));
+            pending.insert("fresh".to_string(), flow(Instant::now()));
+        }
+        service.callback_servers.lock().await.insert(
+            "stale".to_string(),
+            CallbackServer {
+                port: 1455,
+                shutdown: shutdown_tx,
+            },
+        );
+
+        assert_eq!(service.sweep_expired_flows().await, 1);
+        assert!(shutdown_rx.try_recv().is_ok());
+        let pending = service.pending.lock().await;
+        assert!(pending.contains_key("fresh") && !pending.contains_key("stale"));
+    }
+
+    #[test]
+    fn test_concurrent_flows_for_different_agents() {
+        let pending = HashMap::from([(
+            "codex".to_string(),
+            PendingAuth {
+                agent_type: AgentProviderType::Codex,
+                state: "state".to_string(),
+                code_verifier: "verifier".to_string(),
+                receiver: None,
+                callback_port: 1455,
+                started_at: Instant::now(),
+            },
+        )]);
+
+        assert!(!flow_conflicts(&pending, &AgentProviderType::ClaudeCode, 54545));
+        assert!(flow_conflicts(&pending, &AgentProviderType::Codex, 54545));
+        assert!(flow_conflicts(&pending, &AgentProviderType::ClaudeCode, 1455));
+    }
+}
@@ -3,18 +3,27 @@ use std::sync::Arc;
 
 use serde_json::{Map as JsonMap, Value as JsonValue};
 use tokio::fs;
-use toml::Value as TomlValue;
+use toml_edit::DocumentMut;
 
-use crate::agents::agent_metadata;
-use crate::models::{AgentType, CodingAgent};
+use crate::agents::{agent_metadata, resolve_binary_path};
+use crate::models::{
+    AgentEndpoint, AgentProxyChangePreview, AgentProxyConfigState, AgentProxyConfigStatus,
+    AgentType, CodingAgent, ProxyEndpoints,
+};
 use crate::storage::ConfigStore;
 
+use super::{proxy_url, ANTHROPIC_API_PREFIX, GENERIC_API_PREFIX, OPENAI_API_PREFIX};
+
 const LEGACY_CLAUDE_PROXY_MARKER_KEY: &str = "proxyEnabled";
 const CLAUDE_ENV_KEY: &str = "env";
 const CLAUDE_BASE_URL_KEY: &str = "ANTHROPIC_BASE_URL";
 const LEGACY_CODEX_PROXY_MARKER_KEY: &str = "proxy_enabled";
 const CODEX_ENV_KEY: &str = "env";
 const CODEX_BASE_URL_KEY: &str = "OPENAI_BASE_URL";
+/// Gemini CLI reads environment variables from this dotenv file, not from settings.json
+const GEMINI_ENV_FILE: &str = "~/.gemini/.env";
+const GEMINI_BASE_URL_KEY: &str = "GOOGLE_GEMINI_BASE_URL";
+const CONFIG_BACKUP_SUFFIX: &str = ".vibemate.bak";
 
 #[derive(Debug, thiserror::Error)]
 pub enum AgentProxyError {
@@ -24,25 +33,46 @@ pub enum AgentProxyError {
     HomeDirectoryUnavailable,
     #[error("Invalid config format: {0}")]
     InvalidConfigFormat(String),
+    #[error("No backup found at {0}")]
+    BackupNotFound(String),
+    #[error("{0:?} is not installed")]
+    NotInstalled(AgentType),
+    #[error("No supported terminal emulator found")]
+    TerminalUnavailable,
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
     #[error("TOML parse error: {0}")]
-    TomlDeserialize(#[from] toml::de::Error),
-    #[error("TOML serialize error: {0}")]
-    TomlSerialize(#[from] toml::ser::Error),
+    Toml(#[from] toml_edit::TomlError),
     #[error("Storage error: {0}")]
     Storage(#[from] crate::storage::StorageError),
 }
 
 pub struct AgentProxyService {
     store: Arc<ConfigStore>,
+    /// Directory `~` in agent config paths expands to (None = the user's home)
+    home_dir: Option<PathBuf>,
 }
 
 impl AgentProxyService {
     pub fn new(store: Arc<ConfigStore>) -> Self {
-        Self { store }
+        Self {
+            store,
+            home_dir: None,
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn with_home_dir(store: Arc<ConfigStore>, home_dir: PathBuf) -> Self {
+        Self {
+            store,
+            home_dir: Some(home_dir),
+        }
+    }
+
+    fn config_path(&self, agent_type: &AgentType) -> Result<PathBuf, AgentProxyError> {
+        resolve_agent_config_path(agent_type, self.home_dir.as_deref())
     }
 
     pub async fn is_proxy_enabled(&self, agent_type: &AgentType) -> Result<bool, AgentProxyError> {
@@ -68,114 +98,149 @@ impl AgentProxyService {
             return Err(AgentProxyError::UnsupportedAgent(agent_type.clone()));
         }
 
-        let config = self.store.get_config().await;
-        let port = config.app.port;
-        let config_path = resolve_agent_config_path(agent_type)?;
+        let app = self.store.get_config().await.app;
+        let base_url = proxy_base_url(agent_type, app.port, &app.route_prefix);
+        let config_path = self.config_path(agent_type)?;
+        backup_config_once(&config_path).await?;
 
-        match agent_type {
-            AgentType::ClaudeCode => {
-                self.write_claude_proxy_enabled(&config_path, enabled, port)
-                    .await?
-            }
-            AgentType::Codex => {
-                self.write_codex_proxy_enabled(&config_path, enabled, port)
-                    .await?
-            }
-            _ => return Err(AgentProxyError::UnsupportedAgent(agent_type.clone())),
-        }
+        let content = render_proxy_config(agent_type, &config_path, enabled.then_some(base_url))
+            .await?;
+        write_config_file(&config_path, &content).await?;
 
         self.persist_proxy_enabled(agent_type, enabled).await?;
         Ok(())
     }
 
-    async fn write_claude_proxy_enabled(
+    /// What `set_proxy_enabled` would write to the agent's config file, as a line diff
+    /// against the current content. Nothing is written or persisted.
+    pub async fn preview_proxy_change(
         &self,
-        path: &Path,
+        agent_type: &AgentType,
         enabled: bool,
-        port: u16,
-    ) -> Result<(), AgentProxyError> {
-        let mut root = read_json_or_default(path).await?;
-        let root_obj = root.as_object_mut().ok_or_else(|| {
-            AgentProxyError::InvalidConfigFormat(
-                "Claude config root must be a JSON object".to_string(),
-            )
-        })?;
-        // Legacy cleanup: status is persisted in ~/.vibemate/settings.json now.
-        root_obj.remove(LEGACY_CLAUDE_PROXY_MARKER_KEY);
-
-        if enabled {
-            let env_value = root_obj
-                .entry(CLAUDE_ENV_KEY.to_string())
-                .or_insert_with(|| JsonValue::Object(JsonMap::new()));
-            if !env_value.is_object() {
-                *env_value = JsonValue::Object(JsonMap::new());
-            }
-            if let Some(env_obj) = env_value.as_object_mut() {
-                env_obj.insert(
-                    CLAUDE_BASE_URL_KEY.to_string(),
-                    JsonValue::String(format!("http://localhost:{port}/api/anthropic")),
-                );
-            }
+    ) -> Result<AgentProxyChangePreview, AgentProxyError> {
+        if !is_proxy_supported_agent(agent_type) {
+            return Err(AgentProxyError::UnsupportedAgent(agent_type.clone()));
+        }
+
+        let app = self.store.get_config().await.app;
+        let base_url = proxy_base_url(agent_type, app.port, &app.route_prefix);
+        let config_path = self.config_path(agent_type)?;
+        let current = if fs::try_exists(&config_path).await? {
+            fs::read_to_string(&config_path).await?
         } else {
-            let mut remove_env = false;
-            if let Some(env_value) = root_obj.get_mut(CLAUDE_ENV_KEY) {
-                if let Some(env_obj) = env_value.as_object_mut() {
-                    env_obj.remove(CLAUDE_BASE_URL_KEY);
-                    remove_env = env_obj.is_empty();
-                } else {
-                    remove_env = true;
-                }
-            }
-            if remove_env {
-                root_obj.remove(CLAUDE_ENV_KEY);
+            String::new()
+        };
+        let proposed =
+            render_proxy_config(agent_type, &config_path, enabled.then_some(base_url)).await?;
+
+        Ok(AgentProxyChangePreview {
+            agent_type: agent_type.clone(),
+            config_path: config_path.display().to_string(),
+            changed: current != proposed,
+            diff: line_diff(&current, &proposed),
+            current,
+            proposed,
+        })
+    }
+
+    /// Rewrite the base URL of every agent with the proxy enabled, e.g. after the port
+    /// changed. Returns the agents that were updated; failures are logged and skipped.
+    pub async fn rewrite_enabled_agents(&self) -> Vec<AgentType> {
+        let enabled: Vec<AgentType> = self
+            .store
+            .get_config()
+            .await
+            .coding_agents
+            .into_iter()
+            .filter(|agent| agent.proxy_enabled && is_proxy_supported_agent(&agent.agent_type))
+            .map(|agent| agent.agent_type)
+            .collect();
+
+        let mut updated = Vec::with_capacity(enabled.len());
+        for agent_type in enabled {
+            match self.set_proxy_enabled(&agent_type, true).await {
+                Ok(()) => updated.push(agent_type),
+                Err(e) => tracing::warn!("Failed to update {:?} proxy config: {}", agent_type, e),
             }
         }
-
-        write_json(path, &root).await
+        updated
     }
 
-    async fn write_codex_proxy_enabled(
+    /// Check the agent's config file itself, rather than the flag persisted in settings.json,
+    /// against the current proxy port. Catches external edits and port changes.
+    pub async fn verify_proxy_config(
         &self,
-        path: &Path,
-        enabled: bool,
-        port: u16,
-    ) -> Result<(), AgentProxyError> {
-        let mut root = read_toml_or_default(path).await?;
-        let root_table = root.as_table_mut().ok_or_else(|| {
-            AgentProxyError::InvalidConfigFormat("Codex config root must be a TOML table".to_string())
-        })?;
-        // Legacy cleanup: status is persisted in ~/.vibemate/settings.json now.
-        root_table.remove(LEGACY_CODEX_PROXY_MARKER_KEY);
-
-        if enabled {
-            let env_value = root_table
-                .entry(CODEX_ENV_KEY.to_string())
-                .or_insert_with(|| TomlValue::Table(toml::map::Map::new()));
-            if !env_value.is_table() {
-                *env_value = TomlValue::Table(toml::map::Map::new());
-            }
-            if let Some(env_table) = env_value.as_table_mut() {
-                env_table.insert(
-                    CODEX_BASE_URL_KEY.to_string(),
-                    TomlValue::String(format!("http://localhost:{port}/api/openai/v1")),
-                );
-            }
-        } else {
-            let mut remove_env = false;
-            if let Some(env_value) = root_table.get_mut(CODEX_ENV_KEY) {
-                if let Some(env_table) = env_value.as_table_mut() {
-                    env_table.remove(CODEX_BASE_URL_KEY);
-                    remove_env = env_table.is_empty();
-                } else {
-                    remove_env = true;
-                }
-            }
-            if remove_env {
-                root_table.remove(CODEX_ENV_KEY);
-            }
+        agent_type: &AgentType,
+    ) -> Result<AgentProxyConfigStatus, AgentProxyError> {
+        let config_path = self.config_path(agent_type)?;
+        let app = self.store.get_config().await.app;
+        let configured_url = read_configured_base_url(agent_type, &config_path).await?;
+        let expected_url = proxy_base_url(agent_type, app.port, &app.route_prefix);
+        Ok(AgentProxyConfigStatus {
+            agent_type: agent_type.clone(),
+            state: classify_base_url(configured_url.as_deref(), &expected_url),
+            configured_url,
+            expected_url,
+        })
+    }
+
+    /// Proxy URLs for the configured port, and the variable each agent reads its base URL from
+    pub async fn proxy_endpoints(&self) -> ProxyEndpoints {
+        let app = self.store.get_config().await.app;
+        let (port, route_prefix) = (app.port, app.route_prefix.as_str());
+        let agents = [AgentType::ClaudeCode, AgentType::Codex, AgentType::GeminiCLI]
+            .into_iter()
+            .filter_map(|agent_type| {
+                let (env_var, base_url) = proxy_env_var(&agent_type, port, route_prefix)?;
+                Some(AgentEndpoint {
+                    agent_type,
+                    env_var: env_var.to_string(),
+                    base_url,
+                })
+            })
+            .collect();
+        ProxyEndpoints {
+            port,
+            openai_url: proxy_url(port, route_prefix, OPENAI_API_PREFIX),
+            openai_v1_url: format!("{}/v1", proxy_url(port, route_prefix, OPENAI_API_PREFIX)),
+            anthropic_url: proxy_url(port, route_prefix, ANTHROPIC_API_PREFIX),
+            generic_url: proxy_url(port, route_prefix, GENERIC_API_PREFIX),
+            agents,
         }
+    }
 
-        write_toml(path, &root).await
+    /// Open the agent in a new terminal window with its base URL variable pointing at the
+    /// proxy. Only that session is affected; the agent's config file is left untouched.
+    pub async fn launch_with_proxy(&self, agent_type: &AgentType) -> Result<(), AgentProxyError> {
+        if !is_proxy_supported_agent(agent_type) {
+            return Err(AgentProxyError::UnsupportedAgent(agent_type.clone()));
+        }
+
+        let binary = resolve_binary_path(agent_metadata(agent_type).binary)
+            .ok_or_else(|| AgentProxyError::NotInstalled(agent_type.clone()))?;
+        let app = self.store.get_config().await.app;
+        let (key, value) = proxy_env_var(agent_type, app.port, &app.route_prefix)
+            .ok_or_else(|| AgentProxyError::UnsupportedAgent(agent_type.clone()))?;
+        tracing::info!("Launching {} with {}={}", binary.display(), key, value);
+        spawn_in_terminal(&binary, key, &value)
+    }
+
+    /// Roll an agent's config file back to the copy saved before vibe-mate first edited it.
+    /// The backup is consumed, so the next edit backs up the restored file.
+    pub async fn restore_agent_config(&self, agent_type: &AgentType) -> Result<(), AgentProxyError> {
+        let config_path = self.config_path(agent_type)?;
+        let backup_path = config_backup_path(&config_path);
+        if !fs::try_exists(&backup_path).await? {
+            return Err(AgentProxyError::BackupNotFound(
+                backup_path.display().to_string(),
+            ));
+        }
+
+        fs::rename(&backup_path, &config_path).await?;
+        tracing::info!("Restored {} from backup", config_path.display());
+
+        // The backup predates vibe-mate's edits, so the proxy is no longer configured
+        self.persist_proxy_enabled(agent_type, false).await
     }
 
     async fn persist_proxy_enabled(
@@ -204,32 +269,391 @@ impl AgentProxyService {
     }
 }
 
-fn resolve_agent_config_path(agent_type: &AgentType) -> Result<PathBuf, AgentProxyError> {
-    if !matches!(agent_type, AgentType::ClaudeCode | AgentType::Codex) {
+fn resolve_agent_config_path(
+    agent_type: &AgentType,
+    home_dir: Option<&Path>,
+) -> Result<PathBuf, AgentProxyError> {
+    if !is_proxy_supported_agent(agent_type) {
         return Err(AgentProxyError::UnsupportedAgent(agent_type.clone()));
     }
 
+    if *agent_type == AgentType::GeminiCLI {
+        return expand_tilde_path(GEMINI_ENV_FILE, home_dir);
+    }
     let metadata = agent_metadata(agent_type);
-    expand_tilde_path(metadata.default_config_file)
+    expand_tilde_path(metadata.default_config_file, home_dir)
 }
 
+/// Antigravity is an IDE with no base URL setting to point at the proxy
 fn is_proxy_supported_agent(agent_type: &AgentType) -> bool {
-    matches!(agent_type, AgentType::ClaudeCode | AgentType::Codex)
+    matches!(
+        agent_type,
+        AgentType::ClaudeCode | AgentType::Codex | AgentType::GeminiCLI
+    )
+}
+
+/// Environment variable (and its value) that points an agent at the proxy on `port`, for
+/// agents that read one
+fn proxy_env_var(
+    agent_type: &AgentType,
+    port: u16,
+    route_prefix: &str,
+) -> Option<(&'static str, String)> {
+    let key = match agent_type {
+        AgentType::ClaudeCode => CLAUDE_BASE_URL_KEY,
+        AgentType::Codex => CODEX_BASE_URL_KEY,
+        AgentType::GeminiCLI => GEMINI_BASE_URL_KEY,
+        AgentType::Antigravity => return None,
+    };
+    Some((key, proxy_base_url(agent_type, port, route_prefix)))
+}
+
+/// Proxy base URL an agent is pointed at
+fn proxy_base_url(agent_type: &AgentType, port: u16, route_prefix: &str) -> String {
+    match agent_type {
+        AgentType::ClaudeCode => proxy_url(port, route_prefix, ANTHROPIC_API_PREFIX),
+        AgentType::Codex => format!("{}/v1", proxy_url(port, route_prefix, OPENAI_API_PREFIX)),
+        AgentType::GeminiCLI | AgentType::Antigravity => {
+            proxy_url(port, route_prefix, GENERIC_API_PREFIX)
+        }
+    }
+}
+
+/// Base URL variable currently set in an agent's config file
+async fn read_configured_base_url(
+    agent_type: &AgentType,
+    path: &Path,
+) -> Result<Option<String>, AgentProxyError> {
+    let (env_key, base_url_key) = match agent_type {
+        AgentType::Codex => {
+            let doc = read_toml_or_default(path).await?;
+            return Ok(doc
+                .get(CODEX_ENV_KEY)
+                .and_then(|env| env.get(CODEX_BASE_URL_KEY))
+                .and_then(|value| value.as_str())
+                .map(str::to_string));
+        }
+        AgentType::GeminiCLI => {
+            let content = read_to_string_or_default(path).await?;
+            return Ok(dotenv_var(&content, GEMINI_BASE_URL_KEY));
+        }
+        AgentType::ClaudeCode => (CLAUDE_ENV_KEY, CLAUDE_BASE_URL_KEY),
+        AgentType::Antigravity => {
+            return Err(AgentProxyError::UnsupportedAgent(agent_type.clone()));
+        }
+    };
+    let root = read_json_or_default(path).await?;
+    Ok(root
+        .get(env_key)
+        .and_then(|env| env.get(base_url_key))
+        .and_then(JsonValue::as_str)
+        .map(str::to_string))
+}
+
+/// A configured base URL is the proxy's when it is a loopback URL with the proxy's path;
+/// on another port it is stale
+fn classify_base_url(configured: Option<&str>, expected: &str) -> AgentProxyConfigState {
+    let (Some(Ok(configured)), Ok(expected)) = (
+        configured.map(reqwest::Url::parse),
+        reqwest::Url::parse(expected),
+    ) else {
+        return AgentProxyConfigState::Disabled;
+    };
+    let is_loopback = matches!(
+        configured.host_str(),
+        Some("localhost" | "127.0.0.1" | "[::1]")
+    );
+    if !is_loopback || configured.path().trim_end_matches('/') != expected.path() {
+        return AgentProxyConfigState::Disabled;
+    }
+    if configured.port_or_known_default() == expected.port_or_known_default() {
+        AgentProxyConfigState::Enabled
+    } else {
+        AgentProxyConfigState::StalePort
+    }
+}
+
+/// Run `binary` in a new terminal window with `key=value` added to its environment
+fn spawn_in_terminal(binary: &Path, key: &str, value: &str) -> Result<(), AgentProxyError> {
+    #[cfg(target_os = "macos")]
+    {
+        // Terminal.app starts a fresh login shell, so the variable goes into the command
+        let command = format!(
+            "export {}={}; exec {}",
+            key,
+            shell_quote(value),
+            shell_quote(&binary.to_string_lossy())
+        );
+        let script = format!(
+            "tell application \"Terminal\" to do script \"{}\"",
+            command.replace('\\', "\\\\").replace('"', "\\\"")
+        );
+        tokio::process::Command::new("osascript")
+            .args(["-e", &script])
+            .args(["-e", "tell application \"Terminal\" to activate"])
+            .spawn()?;
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    {
+        // `start` opens a new console that inherits this environment
+        tokio::process::Command::new("cmd")
+            .args(["/C", "start", "", "cmd", "/K"])
+            .arg(binary)
+            .env(key, value)
+            .spawn()?;
+        Ok(())
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        // Pass the variable through `env`: some terminals hand the command to a server
+        // process that does not inherit our environment
+        const TERMINALS: &[(&str, &str)] = &[
+            ("x-terminal-emulator", "-e"),
+            ("gnome-terminal", "--"),
+            ("konsole", "-e"),
+            ("xterm", "-e"),
+        ];
+        for (terminal, exec_flag) in TERMINALS {
+            let spawned = tokio::process::Command::new(terminal)
+                .arg(exec_flag)
+                .arg("env")
+                .arg(format!("{}={}", key, value))
+                .arg(binary)
+                .spawn();
+            match spawned {
+                Ok(_) => return Ok(()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(AgentProxyError::TerminalUnavailable)
+    }
+}
+
+/// Quote a value for a POSIX shell command line
+#[cfg(target_os = "macos")]
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Content of the agent's config file with its base URL set to `base_url`, or removed
+/// (`None`). Only reads the file; writing is up to the caller.
+async fn render_proxy_config(
+    agent_type: &AgentType,
+    path: &Path,
+    base_url: Option<String>,
+) -> Result<String, AgentProxyError> {
+    let (env_key, base_url_key) = match agent_type {
+        AgentType::Codex => {
+            let mut doc = read_toml_or_default(path).await?;
+            set_codex_base_url(&mut doc, base_url);
+            return Ok(doc.to_string());
+        }
+        AgentType::GeminiCLI => {
+            let content = read_to_string_or_default(path).await?;
+            return Ok(set_dotenv_var(&content, GEMINI_BASE_URL_KEY, base_url.as_deref()));
+        }
+        AgentType::ClaudeCode => (CLAUDE_ENV_KEY, CLAUDE_BASE_URL_KEY),
+        AgentType::Antigravity => {
+            return Err(AgentProxyError::UnsupportedAgent(agent_type.clone()));
+        }
+    };
+
+    let mut root = read_json_or_default(path).await?;
+    set_json_env_base_url(&mut root, path, env_key, base_url_key, base_url)?;
+    if *agent_type == AgentType::ClaudeCode {
+        // Legacy cleanup: status is persisted in ~/.vibemate/settings.json now.
+        if let Some(root_obj) = root.as_object_mut() {
+            root_obj.remove(LEGACY_CLAUDE_PROXY_MARKER_KEY);
+        }
+    }
+    render_json(&root)
 }
 
-fn expand_tilde_path(path: &str) -> Result<PathBuf, AgentProxyError> {
+/// Line diff from `old` to `new`: every line prefixed with `-` (removed), `+` (added) or
+/// a space (unchanged)
+fn line_diff(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // lcs[i][j] = longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push_str(&format!(" {}\n", old[i]));
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            diff.push_str(&format!("+{}\n", new[j]));
+            j += 1;
+        } else {
+            diff.push_str(&format!("-{}\n", old[i]));
+            i += 1;
+        }
+    }
+    diff
+}
+
+/// Set (`Some`) or remove (`None`) a base URL variable in the `env` object of a JSON settings
+/// file, dropping the `env` object once it is empty
+fn set_json_env_base_url(
+    root: &mut JsonValue,
+    path: &Path,
+    env_key: &str,
+    base_url_key: &str,
+    base_url: Option<String>,
+) -> Result<(), AgentProxyError> {
+    let root_obj = root.as_object_mut().ok_or_else(|| {
+        AgentProxyError::InvalidConfigFormat(format!(
+            "{} root must be a JSON object",
+            path.display()
+        ))
+    })?;
+
+    if let Some(base_url) = base_url {
+        let env_value = root_obj
+            .entry(env_key.to_string())
+            .or_insert_with(|| JsonValue::Object(JsonMap::new()));
+        if !env_value.is_object() {
+            *env_value = JsonValue::Object(JsonMap::new());
+        }
+        if let Some(env_obj) = env_value.as_object_mut() {
+            env_obj.insert(base_url_key.to_string(), JsonValue::String(base_url));
+        }
+    } else {
+        let mut remove_env = false;
+        if let Some(env_value) = root_obj.get_mut(env_key) {
+            if let Some(env_obj) = env_value.as_object_mut() {
+                env_obj.remove(base_url_key);
+                remove_env = env_obj.is_empty();
+            } else {
+                remove_env = true;
+            }
+        }
+        if remove_env {
+            root_obj.remove(env_key);
+        }
+    }
+    Ok(())
+}
+
+/// Name of the variable a dotenv line assigns (`KEY=value` or `export KEY=value`)
+fn dotenv_key(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    line.split_once('=').map(|(key, _)| key.trim())
+}
+
+/// Value of `key` in dotenv `content`, without surrounding quotes
+fn dotenv_var(content: &str, key: &str) -> Option<String> {
+    let line = content.lines().rfind(|line| dotenv_key(line) == Some(key))?;
+    let (_, value) = line.split_once('=')?;
+    let value = value.trim();
+    let unquoted = ['"', '\'']
+        .iter()
+        .find_map(|q| value.strip_prefix(*q).and_then(|v| v.strip_suffix(*q)));
+    Some(unquoted.unwrap_or(value).to_string())
+}
+
+/// Set (`Some`) or remove (`None`) `key` in dotenv `content`, leaving other lines as they are
+fn set_dotenv_var(content: &str, key: &str, value: Option<&str>) -> String {
+    let mut lines: Vec<String> = content
+        .lines()
+        .filter(|line| dotenv_key(line) != Some(key))
+        .map(str::to_string)
+        .collect();
+    if let Some(value) = value {
+        lines.push(format!("{}={}", key, value));
+    }
+    let mut rendered = lines.join("\n");
+    if !rendered.is_empty() {
+        rendered.push('\n');
+    }
+    rendered
+}
+
+fn expand_tilde_path(path: &str, home_dir: Option<&Path>) -> Result<PathBuf, AgentProxyError> {
+    let home = || {
+        home_dir
+            .map(Path::to_path_buf)
+            .or_else(dirs::home_dir)
+            .ok_or(AgentProxyError::HomeDirectoryUnavailable)
+    };
     if path == "~" {
-        return dirs::home_dir().ok_or(AgentProxyError::HomeDirectoryUnavailable);
+        return home();
     }
 
     if let Some(stripped) = path.strip_prefix("~/") {
-        let home_dir = dirs::home_dir().ok_or(AgentProxyError::HomeDirectoryUnavailable)?;
-        return Ok(home_dir.join(stripped));
+        return Ok(home()?.join(stripped));
     }
 
     Ok(PathBuf::from(path))
 }
 
+/// Set (`Some`) or remove (`None`) `env.OPENAI_BASE_URL` in a Codex config, editing the
+/// document in place so the user's comments, formatting and key order survive
+fn set_codex_base_url(doc: &mut DocumentMut, base_url: Option<String>) {
+    // Legacy cleanup: status is persisted in ~/.vibemate/settings.json now.
+    doc.remove(LEGACY_CODEX_PROXY_MARKER_KEY);
+
+    if let Some(base_url) = base_url {
+        let env_item = doc.entry(CODEX_ENV_KEY).or_insert(toml_edit::table());
+        if !env_item.is_table_like() {
+            *env_item = toml_edit::table();
+        }
+        if let Some(env_table) = env_item.as_table_like_mut() {
+            env_table.insert(CODEX_BASE_URL_KEY, toml_edit::value(base_url));
+        }
+    } else {
+        let mut remove_env = false;
+        if let Some(env_item) = doc.get_mut(CODEX_ENV_KEY) {
+            if let Some(env_table) = env_item.as_table_like_mut() {
+                env_table.remove(CODEX_BASE_URL_KEY);
+                remove_env = env_table.is_empty();
+            } else {
+                remove_env = true;
+            }
+        }
+        if remove_env {
+            doc.remove(CODEX_ENV_KEY);
+        }
+    }
+}
+
+/// `<path>.vibemate.bak`
+fn config_backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(CONFIG_BACKUP_SUFFIX);
+    PathBuf::from(backup)
+}
+
+/// Copy the config file aside before its first edit. An existing backup is never
+/// overwritten, so it always holds the user's original settings.
+async fn backup_config_once(path: &Path) -> Result<(), AgentProxyError> {
+    let backup_path = config_backup_path(path);
+    if !fs::try_exists(path).await? || fs::try_exists(&backup_path).await? {
+        return Ok(());
+    }
+    fs::copy(path, &backup_path).await?;
+    tracing::info!("Backed up {} to {}", path.display(), backup_path.display());
+    Ok(())
+}
+
 async fn ensure_parent_dir(path: &Path) -> Result<(), AgentProxyError> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent).await?;
@@ -245,24 +669,234 @@ async fn read_json_or_default(path: &Path) -> Result<JsonValue, AgentProxyError>
     Ok(serde_json::from_str(&content)?)
 }
 
-async fn write_json(path: &Path, value: &JsonValue) -> Result<(), AgentProxyError> {
-    ensure_parent_dir(path).await?;
+fn render_json(value: &JsonValue) -> Result<String, AgentProxyError> {
     let content = serde_json::to_string_pretty(value)?;
-    fs::write(path, format!("{content}\n")).await?;
-    Ok(())
+    Ok(format!("{content}\n"))
 }
 
-async fn read_toml_or_default(path: &Path) -> Result<TomlValue, AgentProxyError> {
+async fn read_to_string_or_default(path: &Path) -> Result<String, AgentProxyError> {
     if !fs::try_exists(path).await? {
-        return Ok(TomlValue::Table(toml::map::Map::new()));
+        return Ok(String::new());
+    }
+    Ok(fs::read_to_string(path).await?)
+}
+
+async fn read_toml_or_default(path: &Path) -> Result<DocumentMut, AgentProxyError> {
+    if !fs::try_exists(path).await? {
+        return Ok(DocumentMut::new());
     }
     let content = fs::read_to_string(path).await?;
-    Ok(toml::from_str(&content)?)
+    Ok(content.parse::<DocumentMut>()?)
 }
 
-async fn write_toml(path: &Path, value: &TomlValue) -> Result<(), AgentProxyError> {
+async fn write_config_file(path: &Path, content: &str) -> Result<(), AgentProxyError> {
     ensure_parent_dir(path).await?;
-    let content = toml::to_string_pretty(value)?;
-    fs::write(path, format!("{content}\n")).await?;
+    fs::write(path, content).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_json_env_base_url_enable_disable() {
+        let path = Path::new("settings.json");
+        let mut root: JsonValue =
+            serde_json::from_str(r#"{"theme":"dark","env":{"OTHER":"1"}}"#).unwrap();
+
+        set_json_env_base_url(
+            &mut root,
+            path,
+            CLAUDE_ENV_KEY,
+            CLAUDE_BASE_URL_KEY,
+            Some("http://localhost:12345/api/anthropic".to_string()),
+        )
+        .unwrap();
+        assert_eq!(root["env"][CLAUDE_BASE_URL_KEY], "http://localhost:12345/api/anthropic");
+        assert_eq!(root["env"]["OTHER"], "1");
+
+        set_json_env_base_url(&mut root, path, CLAUDE_ENV_KEY, CLAUDE_BASE_URL_KEY, None)
+            .unwrap();
+        assert!(root["env"].get(CLAUDE_BASE_URL_KEY).is_none());
+        assert_eq!(root["theme"], "dark");
+
+        // The env block is dropped once it no longer holds anything
+        let mut root: JsonValue = serde_json::from_str(r#"{"env":{}}"#).unwrap();
+        set_json_env_base_url(&mut root, path, CLAUDE_ENV_KEY, CLAUDE_BASE_URL_KEY, None)
+            .unwrap();
+        assert!(root.get("env").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_gemini_base_url_goes_to_dotenv_file() {
+        let home = tempdir().unwrap();
+        let path = resolve_agent_config_path(&AgentType::GeminiCLI, Some(home.path())).unwrap();
+        assert_eq!(path, home.path().join(".gemini/.env"));
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "# keys\nGEMINI_API_KEY=abc\nexport GOOGLE_GEMINI_BASE_URL='x'\n")
+            .unwrap();
+
+        let base_url = "http://localhost:12345/api".to_string();
+        let enabled = render_proxy_config(&AgentType::GeminiCLI, &path, Some(base_url.clone()))
+            .await
+            .unwrap();
+        assert_eq!(
+            enabled,
+            "# keys\nGEMINI_API_KEY=abc\nGOOGLE_GEMINI_BASE_URL=http://localhost:12345/api\n"
+        );
+        assert_eq!(dotenv_var(&enabled, GEMINI_BASE_URL_KEY), Some(base_url));
+
+        std::fs::write(&path, &enabled).unwrap();
+        let disabled = render_proxy_config(&AgentType::GeminiCLI, &path, None).await.unwrap();
+        assert_eq!(disabled, "# keys\nGEMINI_API_KEY=abc\n");
+        assert_eq!(dotenv_var(&disabled, GEMINI_BASE_URL_KEY), None);
+
+        // Antigravity has nowhere to put a base URL
+        assert!(matches!(
+            resolve_agent_config_path(&AgentType::Antigravity, Some(home.path())),
+            Err(AgentProxyError::UnsupportedAgent(_))
+        ));
+    }
+
+    #[test]
+    fn test_codex_base_url_preserves_comments() {
+        let original = "# My Codex setup\nmodel = \"o3\" # default model\n\n[env]\n# keep this\nFOO = \"bar\"\n";
+        let mut doc = original.parse::<DocumentMut>().unwrap();
+
+        set_codex_base_url(&mut doc, Some("http://localhost:12345/api/openai/v1".to_string()));
+        let enabled = doc.to_string();
+        assert!(enabled.starts_with("# My Codex setup\nmodel = \"o3\" # default model\n"));
+        assert!(enabled.contains("# keep this\nFOO = \"bar\"\n"));
+        assert!(enabled.contains("OPENAI_BASE_URL = \"http://localhost:12345/api/openai/v1\""));
+
+        set_codex_base_url(&mut doc, None);
+        assert_eq!(doc.to_string(), original);
+
+        // A table left empty by disabling is removed
+        let mut doc = "model = \"o3\"\n".parse::<DocumentMut>().unwrap();
+        set_codex_base_url(&mut doc, Some("http://localhost:1/api/openai/v1".to_string()));
+        set_codex_base_url(&mut doc, None);
+        assert_eq!(doc.to_string(), "model = \"o3\"\n");
+    }
+
+    #[tokio::test]
+    async fn test_backup_config_once_keeps_original() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("config.toml");
+        let backup_path = config_backup_path(&path);
+        assert_eq!(
+            backup_path.file_name().unwrap().to_str().unwrap(),
+            "config.toml.vibemate.bak"
+        );
+
+        // Nothing to back up yet
+        backup_config_once(&path).await.unwrap();
+        assert!(!backup_path.exists());
+
+        std::fs::write(&path, "# original\n").unwrap();
+        backup_config_once(&path).await.unwrap();
+        std::fs::write(&path, "# edited\n").unwrap();
+        backup_config_once(&path).await.unwrap();
+
+        assert_eq!(std::fs::read_to_string(&backup_path).unwrap(), "# original\n");
+    }
+
+    #[test]
+    fn test_proxy_env_var_matches_config_edits() {
+        assert_eq!(
+            proxy_env_var(&AgentType::ClaudeCode, 12345, ""),
+            Some(("ANTHROPIC_BASE_URL", "http://localhost:12345/api/anthropic".to_string()))
+        );
+        assert_eq!(
+            proxy_env_var(&AgentType::Codex, 12345, ""),
+            Some(("OPENAI_BASE_URL", "http://localhost:12345/api/openai/v1".to_string()))
+        );
+        assert_eq!(
+            proxy_env_var(&AgentType::GeminiCLI, 12345, ""),
+            Some(("GOOGLE_GEMINI_BASE_URL", "http://localhost:12345/api".to_string()))
+        );
+        assert_eq!(
+            proxy_env_var(&AgentType::Codex, 12345, "/vibemate"),
+            Some(("OPENAI_BASE_URL", "http://localhost:12345/vibemate/api/openai/v1".to_string()))
+        );
+        assert_eq!(proxy_env_var(&AgentType::Antigravity, 12345, ""), None);
+    }
+
+    #[tokio::test]
+    async fn test_verify_proxy_config_reads_agent_files() {
+        let temp_dir = tempdir().unwrap();
+        let codex_path = temp_dir.path().join("config.toml");
+        let claude_path = temp_dir.path().join("settings.json");
+        let expected = proxy_base_url(&AgentType::Codex, 12345, "");
+
+        assert_eq!(read_configured_base_url(&AgentType::Codex, &codex_path).await.unwrap(), None);
+        std::fs::write(
+            &codex_path,
+            "[env]\nOPENAI_BASE_URL = \"http://localhost:4000/api/openai/v1\"\n",
+        )
+        .unwrap();
+        let configured = read_configured_base_url(&AgentType::Codex, &codex_path).await.unwrap();
+        assert_eq!(
+            classify_base_url(configured.as_deref(), &expected),
+            AgentProxyConfigState::StalePort
+        );
+
+        std::fs::write(
+            &claude_path,
+            r#"{"env":{"ANTHROPIC_BASE_URL":"http://127.0.0.1:12345/api/anthropic/"}}"#,
+        )
+        .unwrap();
+        let configured =
+            read_configured_base_url(&AgentType::ClaudeCode, &claude_path).await.unwrap();
+        assert_eq!(
+            classify_base_url(
+                configured.as_deref(),
+                &proxy_base_url(&AgentType::ClaudeCode, 12345, "")
+            ),
+            AgentProxyConfigState::Enabled
+        );
+
+        // Another endpoint, or nothing at all, means the proxy isn't in use
+        for configured in [None, Some("https://api.openai.com/v1"), Some("not a url")] {
+            assert_eq!(
+                classify_base_url(configured, &expected),
+                AgentProxyConfigState::Disabled
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_preview_proxy_change_writes_nothing() {
+        let temp_dir = tempdir().unwrap();
+        let store = Arc::new(ConfigStore::new(temp_dir.path().join("vibemate")));
+        store.init().await.unwrap();
+        let home = temp_dir.path().join("home");
+        let service = AgentProxyService::with_home_dir(store, home.clone());
+
+        let config_path = home.join(".codex/config.toml");
+        let original = "# My Codex setup\nmodel = \"o3\"\n";
+        std::fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+        std::fs::write(&config_path, original).unwrap();
+
+        let preview = service.preview_proxy_change(&AgentType::Codex, true).await.unwrap();
+        assert!(preview.changed);
+        assert_eq!(preview.current, original);
+        assert!(preview.diff.starts_with(" # My Codex setup\n model = \"o3\"\n"));
+        assert!(preview
+            .diff
+            .contains("+OPENAI_BASE_URL = \"http://localhost:12345/api/openai/v1\"\n"));
+
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), original);
+        assert!(!config_backup_path(&config_path).exists());
+        assert!(!service.is_proxy_enabled(&AgentType::Codex).await.unwrap());
+
+        // Applying the change writes exactly the previewed content
+        service.set_proxy_enabled(&AgentType::Codex, true).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), preview.proposed);
+        let preview = service.preview_proxy_change(&AgentType::Codex, true).await.unwrap();
+        assert!(!preview.changed);
+        assert!(!preview.diff.lines().any(|line| line.starts_with(['+', '-'])));
+    }
+}
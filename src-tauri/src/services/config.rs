@@ -1,22 +1,69 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use chrono::Utc;
 
-use crate::models::{AppConfig, LatencyResult, UpdateAppConfigInput};
+use super::agent_proxy::AgentProxyService;
+use super::proxy::create_http_client;
+use crate::models::{
+    normalize_route_prefix, validate_cors_origin, validate_proxy_url, ApiGroup, AppConfig,
+    LatencyResult, ProxyMode, UpdateAppConfigInput, DEFAULT_STICKY_SESSION_HEADER,
+};
 use crate::storage::ConfigStore;
 
 #[derive(Debug, thiserror::Error)]
 pub enum ConfigError {
     #[error("Storage error: {0}")]
     Storage(#[from] crate::storage::StorageError),
+    #[error("Invalid proxy URL: {0}")]
+    InvalidProxyUrl(String),
+    #[error("Invalid model alias: {0}")]
+    InvalidModelAlias(String),
+    #[error("Invalid CORS origin: {0}")]
+    InvalidCorsOrigin(String),
+    #[error("Invalid route prefix: {0}")]
+    InvalidRoutePrefix(String),
+    #[error("Provider not found: {0}")]
+    ProviderNotFound(String),
 }
 
+/// Trim and validate CORS origins. Browsers send origins without a trailing slash, so
+/// they are stored that way.
+fn normalize_cors_origins(origins: &[String]) -> Result<Vec<String>, ConfigError> {
+    let origins: Vec<String> = origins
+        .iter()
+        .map(|o| o.trim().trim_end_matches('/').to_string())
+        .filter(|o| !o.is_empty())
+        .collect();
+    for origin in &origins {
+        validate_cors_origin(origin).map_err(ConfigError::InvalidCorsOrigin)?;
+    }
+    Ok(origins)
+}
+
+/// `update_config`'s checks applied to a whole app config, normalizing what it would
+fn validate_app_config(app: &mut AppConfig) -> Result<(), ConfigError> {
+    if let Some(proxy_url) = app.proxy_url.as_deref().filter(|u| !u.trim().is_empty()) {
+        validate_proxy_url(proxy_url).map_err(ConfigError::InvalidProxyUrl)?;
+    }
+    app.cors_allowed_origins = normalize_cors_origins(&app.cors_allowed_origins)?;
+    app.route_prefix =
+        normalize_route_prefix(&app.route_prefix).map_err(ConfigError::InvalidRoutePrefix)?;
+    Ok(())
+}
+
+/// Probed by `test_latency` when neither a probe URL nor a provider base URL is configured
+const LATENCY_PROBE_URL: &str = "https://www.gstatic.com/generate_204";
+/// Give up on the latency probe after this long
+const LATENCY_PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
 pub struct ConfigService {
     store: Arc<ConfigStore>,
+    agent_proxy: Arc<AgentProxyService>,
 }
 
 impl ConfigService {
-    pub fn new(store: Arc<ConfigStore>) -> Self {
-        Self { store }
+    pub fn new(store: Arc<ConfigStore>, agent_proxy: Arc<AgentProxyService>) -> Self {
+        Self { store, agent_proxy }
     }
 
     pub async fn get_config(&self) -> Result<AppConfig, ConfigError> {
@@ -25,20 +72,208 @@ impl ConfigService {
     }
 
     pub async fn update_config(&self, input: UpdateAppConfigInput) -> Result<AppConfig, ConfigError> {
+        if let Some(proxy_url) = input.proxy_url.as_deref().filter(|u| !u.trim().is_empty()) {
+            validate_proxy_url(proxy_url).map_err(ConfigError::InvalidProxyUrl)?;
+        }
+        let cors_allowed_origins = input
+            .cors_allowed_origins
+            .as_deref()
+            .map(normalize_cors_origins)
+            .transpose()?;
+        let route_prefix = input
+            .route_prefix
+            .as_deref()
+            .map(normalize_route_prefix)
+            .transpose()
+            .map_err(ConfigError::InvalidRoutePrefix)?;
+
+        let old_app = self.store.get_config().await.app;
         self.store
             .update(|config| {
                 if let Some(port) = input.port {
                     config.app.port = port;
                 }
-                if let Some(enable_proxy) = input.enable_proxy {
-                    config.app.enable_proxy = enable_proxy;
+                if let Some(mode) = input.proxy_mode {
+                    config.app.proxy_mode = mode;
+                } else if let Some(enable_proxy) = input.enable_proxy {
+                    // Only a change of the on/off state picks a mode; keeps System when re-enabled
+                    if enable_proxy != (config.app.proxy_mode != ProxyMode::None) {
+                        config.app.proxy_mode = if enable_proxy {
+                            ProxyMode::Custom
+                        } else {
+                            ProxyMode::None
+                        };
+                    }
                 }
+                config.app.enable_proxy = config.app.proxy_mode != ProxyMode::None;
                 if let Some(proxy_url) = input.proxy_url.clone() {
                     config.app.proxy_url = Some(proxy_url);
                 }
+                if let Some(username) = input.proxy_username.clone() {
+                    config.app.proxy_username = Some(username).filter(|u| !u.is_empty());
+                }
+                if let Some(password) = input.proxy_password.clone() {
+                    config.app.proxy_password = Some(password).filter(|p| !p.is_empty());
+                }
                 if let Some(no_proxy) = input.no_proxy.clone() {
                     config.app.no_proxy = no_proxy;
                 }
+                if let Some(bypass) = input.bypass_proxy_for_localhost {
+                    config.app.bypass_proxy_for_localhost = bypass;
+                }
+                if let Some(expose) = input.expose_debug_headers {
+                    config.app.expose_debug_headers = expose;
+                }
+                if let Some(reset) = input.reset_stats_on_start {
+                    config.app.reset_stats_on_start = reset;
+                }
+                if let Some(probe_url) = input.latency_probe_url.clone() {
+                    // Empty string resets to the default probe target
+                    config.app.latency_probe_url =
+                        Some(probe_url.trim().to_string()).filter(|u| !u.is_empty());
+                }
+                if let Some(user_agent) = input.user_agent.clone() {
+                    // Empty string forwards the client's User-Agent again
+                    config.app.user_agent =
+                        Some(user_agent.trim().to_string()).filter(|u| !u.is_empty());
+                }
+                if let Some(sticky) = input.sticky_sessions {
+                    config.app.sticky_sessions = sticky;
+                }
+                if let Some(header) = input.sticky_session_header.clone() {
+                    let header = header.trim();
+                    config.app.sticky_session_header = if header.is_empty() {
+                        DEFAULT_STICKY_SESSION_HEADER.to_string()
+                    } else {
+                        header.to_string()
+                    };
+                }
+                if let Some(secs) = input.sse_keepalive_secs {
+                    config.app.sse_keepalive_secs = secs;
+                }
+                if let Some(prefix) = route_prefix.clone() {
+                    config.app.route_prefix = prefix;
+                }
+                if let Some(access_log) = input.access_log {
+                    config.app.access_log = access_log;
+                }
+                if let Some(capacity) = input.recent_requests_capacity {
+                    config.app.recent_requests_capacity = capacity;
+                }
+                if let Some(secs) = input.connect_timeout_secs {
+                    config.app.connect_timeout_secs = secs.max(1);
+                }
+                if let Some(secs) = input.idle_timeout_secs {
+                    config.app.idle_timeout_secs = secs.max(1);
+                }
+                if let Some(attempts) = input.retry_max_attempts {
+                    config.app.retry_max_attempts = attempts.max(1);
+                }
+                if let Some(delay) = input.retry_base_delay_ms {
+                    config.app.retry_base_delay_ms = delay;
+                }
+                if let Some(limit) = input.max_request_body_bytes {
+                    config.app.max_request_body_bytes = limit;
+                }
+                if let Some(origins) = cors_allowed_origins {
+                    config.app.cors_allowed_origins = origins;
+                }
+                if let Some(cap) = input.rule_count_warning {
+                    config.app.rule_count_warning = cap;
+                }
+                config.app.updated_at = Utc::now();
+            })
+            .await?;
+
+        let config = self.get_config().await?;
+        self.follow_proxy_address(&old_app, &config).await;
+        Ok(config)
+    }
+
+    /// Re-read settings.json from disk, e.g. after the user edited it by hand. The edit
+    /// goes through the same checks as `update_config`; if it fails them, the running
+    /// config is kept.
+    pub async fn reload_config(&self) -> Result<AppConfig, ConfigError> {
+        let old_app = self.get_config().await?;
+        let mut config = self.store.read_config_file().await?;
+        validate_app_config(&mut config.app)?;
+        self.store.replace(config).await?;
+        let config = self.get_config().await?;
+        self.follow_proxy_address(&old_app, &config).await;
+        tracing::info!("Reloaded config from disk");
+        Ok(config)
+    }
+
+    /// Agents still pointing at the old address would silently stop reaching the proxy.
+    /// The rewrite never touches `app`, so it cannot trigger another one.
+    async fn follow_proxy_address(&self, old_app: &AppConfig, config: &AppConfig) {
+        if config.port == old_app.port && config.route_prefix == old_app.route_prefix {
+            return;
+        }
+        let updated = self.agent_proxy.rewrite_enabled_agents().await;
+        if !updated.is_empty() {
+            tracing::info!(
+                "Proxy address changed from :{}{} to :{}{}; updated agent configs: {:?}",
+                old_app.port,
+                old_app.route_prefix,
+                config.port,
+                config.route_prefix,
+                updated
+            );
+        }
+    }
+
+    pub async fn get_model_aliases(&self) -> Result<HashMap<String, String>, ConfigError> {
+        let config = self.store.get_config().await;
+        Ok(config.app.model_aliases)
+    }
+
+    /// Replace the model alias table. Names and targets are trimmed and must be non-empty.
+    pub async fn set_model_aliases(
+        &self,
+        aliases: HashMap<String, String>,
+    ) -> Result<HashMap<String, String>, ConfigError> {
+        let mut normalized = HashMap::with_capacity(aliases.len());
+        for (alias, target) in aliases {
+            let (alias, target) = (alias.trim().to_string(), target.trim().to_string());
+            if alias.is_empty() || target.is_empty() {
+                return Err(ConfigError::InvalidModelAlias(
+                    "alias and target model must not be empty".to_string(),
+                ));
+            }
+            if alias == target {
+                return Err(ConfigError::InvalidModelAlias(format!(
+                    "{} cannot alias itself",
+                    alias
+                )));
+            }
+            normalized.insert(alias, target);
+        }
+
+        self.store
+            .update(|config| {
+                config.app.model_aliases = normalized;
+                config.app.updated_at = Utc::now();
+            })
+            .await?;
+
+        self.get_model_aliases().await
+    }
+
+    /// Route unmatched requests in `api_group` to `provider_id` instead of the global default
+    pub async fn set_group_default_provider(
+        &self,
+        api_group: ApiGroup,
+        provider_id: String,
+    ) -> Result<AppConfig, ConfigError> {
+        let config = self.store.get_config().await;
+        if !config.providers.iter().any(|p| p.id == provider_id) {
+            return Err(ConfigError::ProviderNotFound(provider_id));
+        }
+
+        self.store
+            .update(|config| {
+                config.app.default_provider_by_group.insert(api_group, provider_id);
                 config.app.updated_at = Utc::now();
             })
             .await?;
@@ -46,30 +281,194 @@ impl ConfigService {
         self.get_config().await
     }
 
+    /// Send unmatched requests in `api_group` to the global default provider again
+    pub async fn clear_group_default_provider(
+        &self,
+        api_group: ApiGroup,
+    ) -> Result<AppConfig, ConfigError> {
+        self.store
+            .update(|config| {
+                config.app.default_provider_by_group.remove(&api_group);
+                config.app.updated_at = Utc::now();
+            })
+            .await?;
+
+        self.get_config().await
+    }
+
+    /// Probe upstream connectivity through the configured proxy mode, using the same
+    /// HTTP client setup as the proxy server. Any HTTP response counts as reachable.
     pub async fn test_latency(&self) -> LatencyResult {
         let config = self.store.get_config().await;
-        
-        // Test connectivity based on proxy settings
-        let start = std::time::Instant::now();
-        
-        // For now, we'll just simulate a latency test
-        // In production, you'd actually test network connectivity
-        let success = if config.app.enable_proxy {
-            config.app.proxy_url.is_some()
-        } else {
-            true
-        };
 
+        if config.app.proxy_mode == ProxyMode::Custom && config.app.proxy_url.is_none() {
+            return LatencyResult {
+                success: false,
+                latency_ms: None,
+                error: Some("Proxy configuration is incomplete".to_string()),
+            };
+        }
+
+        let probe_url = config
+            .app
+            .latency_probe_url
+            .clone()
+            .or_else(|| {
+                config
+                    .providers
+                    .iter()
+                    .filter(|p| p.enabled)
+                    .find_map(|p| p.api_base_url.clone().filter(|u| !u.is_empty()))
+            })
+            .unwrap_or_else(|| LATENCY_PROBE_URL.to_string());
+
+        let client = create_http_client(&config);
+        let start = std::time::Instant::now();
+        let result = client
+            .get(&probe_url)
+            .timeout(LATENCY_PROBE_TIMEOUT)
+            .send()
+            .await;
         let latency_ms = start.elapsed().as_millis() as u64;
 
-        LatencyResult {
-            success,
-            latency_ms: if success { Some(latency_ms) } else { None },
-            error: if success {
-                None
-            } else {
-                Some("Proxy configuration is incomplete".to_string())
+        match result {
+            Ok(_) => LatencyResult {
+                success: true,
+                latency_ms: Some(latency_ms),
+                error: None,
+            },
+            Err(e) => LatencyResult {
+                success: false,
+                latency_ms: None,
+                error: Some(format!("Failed to reach {}: {}", probe_url, e)),
             },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use tempfile::tempdir;
+
+    async fn service_probing(probe_url: String) -> (ConfigService, tempfile::TempDir) {
+        let temp_dir = tempdir().unwrap();
+        let store = Arc::new(ConfigStore::new(temp_dir.path().to_path_buf()));
+        store.init().await.unwrap();
+        let agent_proxy = Arc::new(AgentProxyService::new(store.clone()));
+        let service = ConfigService::new(store, agent_proxy);
+        service
+            .update_config(UpdateAppConfigInput {
+                latency_probe_url: Some(probe_url),
+                ..UpdateAppConfigInput::default()
+            })
+            .await
+            .unwrap();
+        (service, temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_latency_probes_configured_url() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route(
+            "/probe",
+            get(|| async {
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                "ok"
+            }),
+        );
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let (service, _dir) = service_probing(format!("http://{}/probe", addr)).await;
+        let result = service.test_latency().await;
+        assert!(result.success);
+        assert!(result.latency_ms.unwrap() >= 20);
+    }
+
+    #[tokio::test]
+    async fn test_latency_reports_unreachable_probe() {
+        // Bind then drop to get a port nothing listens on
+        let port = {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap().port()
+        };
+
+        let (service, _dir) = service_probing(format!("http://127.0.0.1:{}/", port)).await;
+        let result = service.test_latency().await;
+        assert!(!result.success);
+        assert!(result.latency_ms.is_none());
+        assert!(result.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_port_change_rewrites_enabled_agent_configs() {
+        use crate::models::AgentType;
+
+        let temp_dir = tempdir().unwrap();
+        let store = Arc::new(ConfigStore::new(temp_dir.path().join("vibemate")));
+        store.init().await.unwrap();
+        let home = temp_dir.path().join("home");
+        let agent_proxy = Arc::new(AgentProxyService::with_home_dir(store.clone(), home.clone()));
+        let service = ConfigService::new(store, agent_proxy.clone());
+
+        agent_proxy.set_proxy_enabled(&AgentType::Codex, true).await.unwrap();
+        let codex_config = home.join(".codex/config.toml");
+        assert!(std::fs::read_to_string(&codex_config).unwrap().contains(":12345/"));
+
+        service
+            .update_config(UpdateAppConfigInput {
+                port: Some(23456),
+                ..UpdateAppConfigInput::default()
+            })
+            .await
+            .unwrap();
+        let rewritten = std::fs::read_to_string(&codex_config).unwrap();
+        assert!(rewritten.contains("http://localhost:23456/api/openai/v1"));
+        // Agents without the proxy enabled are left alone
+        assert!(!home.join(".claude/settings.json").exists());
+    }
+
+    #[tokio::test]
+    async fn test_reload_config_picks_up_hand_edits() {
+        let temp_dir = tempdir().unwrap();
+        let store = Arc::new(ConfigStore::new(temp_dir.path().to_path_buf()));
+        store.init().await.unwrap();
+        store.update(|c| c.app.port = 23456).await.unwrap();
+        let agent_proxy = Arc::new(AgentProxyService::new(store.clone()));
+        let service = ConfigService::new(store.clone(), agent_proxy);
+
+        let path = temp_dir.path().join("settings.json");
+        let mut raw: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        raw["app"]["accessLog"] = serde_json::json!(true);
+        std::fs::write(&path, raw.to_string()).unwrap();
+        assert!(service.reload_config().await.unwrap().access_log);
+
+        // Edits are normalized like `update_config` input
+        raw["app"]["routePrefix"] = serde_json::json!("vibemate/");
+        std::fs::write(&path, raw.to_string()).unwrap();
+        assert_eq!(service.reload_config().await.unwrap().route_prefix, "/vibemate");
+
+        // A broken or invalid edit is reported and the in-memory config is kept
+        std::fs::write(&path, "{ not json").unwrap();
+        assert!(matches!(service.reload_config().await, Err(ConfigError::Storage(_))));
+        raw["app"]["port"] = serde_json::json!(34567);
+        raw["app"]["corsAllowedOrigins"] = serde_json::json!(["localhost:5173"]);
+        std::fs::write(&path, raw.to_string()).unwrap();
+        assert!(matches!(
+            service.reload_config().await,
+            Err(ConfigError::InvalidCorsOrigin(_))
+        ));
+        raw["app"]["corsAllowedOrigins"] = serde_json::json!([]);
+        raw["app"]["proxyUrl"] = serde_json::json!("ftp://proxy.local");
+        std::fs::write(&path, raw.to_string()).unwrap();
+        assert!(matches!(service.reload_config().await, Err(ConfigError::InvalidProxyUrl(_))));
+        let config = store.get_config().await;
+        assert!(config.app.access_log);
+        assert_eq!(config.app.port, 23456);
+    }
+}
@@ -0,0 +1,96 @@
+use std::path::Path;
+
+use crate::agents::auth::{auth_path_for_agent_type, load_auth_file};
+use crate::agents::{all_agent_definitions, resolve_binary_path};
+use crate::models::{
+    AgentDiagnostic, AgentProviderType, AuthFileDiagnostic, DiagnosticsReport, Provider,
+};
+
+use super::{check_port_available, ProxyServer};
+
+/// Collect a readiness report: config dir, proxy port and state, agent binaries, and the
+/// auth files of agent-account providers
+pub async fn run_diagnostics(proxy: &ProxyServer) -> DiagnosticsReport {
+    let store = proxy.config_store();
+    let config = store.get_config().await;
+    let proxy_running = proxy.is_running();
+    let port = if proxy_running {
+        None
+    } else {
+        Some(check_port_available(config.app.port).await)
+    };
+
+    let agents = all_agent_definitions()
+        .into_iter()
+        .map(|def| AgentDiagnostic {
+            agent_type: def.metadata().agent_type.clone(),
+            executable_path: resolve_binary_path(def.metadata().binary)
+                .map(|p| p.to_string_lossy().into_owned()),
+        })
+        .collect();
+
+    let mut auth_files = Vec::new();
+    for provider in &config.providers {
+        if let Some(agent_type) = &provider.agent_account {
+            auth_files.push(check_auth_file(provider, agent_type).await);
+        }
+    }
+
+    DiagnosticsReport {
+        config_dir: store.config_dir().display().to_string(),
+        config_dir_writable: is_dir_writable(store.config_dir()).await,
+        proxy_running,
+        port,
+        agents,
+        auth_files,
+    }
+}
+
+/// Whether a file can be created in `dir`, checked by writing and removing a probe file
+async fn is_dir_writable(dir: &Path) -> bool {
+    let probe = dir.join(".write-probe");
+    let writable = tokio::fs::write(&probe, b"").await.is_ok();
+    let _ = tokio::fs::remove_file(&probe).await;
+    writable
+}
+
+async fn check_auth_file(provider: &Provider, agent_type: &AgentProviderType) -> AuthFileDiagnostic {
+    let mut diagnostic = AuthFileDiagnostic {
+        provider_id: provider.id.clone(),
+        provider_name: provider.name.clone(),
+        agent_type: agent_type.clone(),
+        path: None,
+        exists: false,
+        valid: false,
+        error: None,
+    };
+    let path = match auth_path_for_agent_type(agent_type) {
+        Ok(path) => path,
+        Err(e) => {
+            diagnostic.error = Some(e.to_string());
+            return diagnostic;
+        }
+    };
+    diagnostic.path = Some(path.display().to_string());
+    diagnostic.exists = path.exists();
+    if diagnostic.exists {
+        match load_auth_file::<serde_json::Value>(&path).await {
+            Ok(_) => diagnostic.valid = true,
+            Err(e) => diagnostic.error = Some(e.to_string()),
+        }
+    }
+    diagnostic
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_is_dir_writable() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        assert!(is_dir_writable(temp_dir.path()).await);
+        assert!(!temp_dir.path().join(".write-probe").exists());
+        assert!(!is_dir_writable(&temp_dir.path().join("missing")).await);
+    }
+}
@@ -5,6 +5,10 @@ mod agent_proxy;
 mod agent_auth;
 mod config;
 mod proxy;
+mod access_log;
+mod translate;
+mod diagnostics;
+mod spend;
 
 pub use provider::*;
 pub use router::*;
@@ -13,3 +17,5 @@ pub use agent_proxy::*;
 pub use agent_auth::*;
 pub use config::*;
 pub use proxy::*;
+pub use access_log::*;
+pub use diagnostics::*;
@@ -2,16 +2,34 @@ use std::sync::Arc;
 use chrono::Utc;
 
 use crate::models::{
-    ConnectionStatus, CreateProviderInput, Provider, ProviderStatus, UpdateProviderInput,
+    AgentProviderType, ConnectionStatus, CreateProviderInput, OrphanAuthFiles, Provider,
+    ProviderFilter, ProviderImportError, ProviderImportResult, ProviderPing, ProviderStatus,
+    UpdateProviderInput, AUTH_KEY_PLACEHOLDER, SECRET_KEY_PLACEHOLDER,
 };
+use super::proxy::{
+    add_provider_auth, create_http_client, provider_curl_example, provider_models_url,
+};
+use crate::agents::auth::{auth_filename, auth_path_in, is_agent_auth_filename};
+use crate::agents::{get_agent_access_token, AgentAuthContext};
 use crate::storage::ConfigStore;
 
+/// Give up on a provider ping after this long
+const PING_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
 #[derive(Debug, thiserror::Error)]
 pub enum ProviderError {
     #[error("Provider not found: {0}")]
     NotFound(String),
+    #[error("Invalid API base URL: {0}")]
+    InvalidBaseUrl(String),
+    #[error("Invalid auth header: {0}")]
+    InvalidAuthHeader(String),
+    #[error("No pricing configured for model: {0}")]
+    NoPricing(String),
     #[error("Storage error: {0}")]
     Storage(#[from] crate::storage::StorageError),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
 }
 
 pub struct ProviderService {
@@ -23,9 +41,22 @@ impl ProviderService {
         Self { store }
     }
 
-    pub async fn list_providers(&self) -> Result<Vec<Provider>, ProviderError> {
+    pub async fn list_providers(
+        &self,
+        filter: &ProviderFilter,
+    ) -> Result<Vec<Provider>, ProviderError> {
         let config = self.store.get_config().await;
-        Ok(config.providers)
+        Ok(config.providers.into_iter().filter(|p| filter.matches(p)).collect())
+    }
+
+    /// Distinct provider groups, sorted by name
+    pub async fn list_provider_groups(&self) -> Result<Vec<String>, ProviderError> {
+        let config = self.store.get_config().await;
+        let mut groups: Vec<String> =
+            config.providers.into_iter().filter_map(|p| p.group).collect();
+        groups.sort();
+        groups.dedup();
+        Ok(groups)
     }
 
     pub async fn get_provider(&self, id: &str) -> Result<Provider, ProviderError> {
@@ -41,12 +72,7 @@ impl ProviderService {
         &self,
         input: CreateProviderInput,
     ) -> Result<Provider, ProviderError> {
-        let provider = Provider::new_model(
-            input.name,
-            input.provider_type,
-            input.api_base_url.unwrap_or_default(),
-            input.api_key.unwrap_or_default(),
-        );
+        let provider = provider_from_input(input)?;
 
         let provider_clone = provider.clone();
         self.store
@@ -63,7 +89,30 @@ impl ProviderService {
         id: &str,
         input: UpdateProviderInput,
     ) -> Result<Provider, ProviderError> {
-        self.get_provider(id).await?;
+        let existing = self.get_provider(id).await?;
+        // A provider whose key lives in secrets.json keeps it there
+        let mut api_key = input.api_key.clone().map(Some);
+        if existing.api_key.as_deref() == Some(SECRET_KEY_PLACEHOLDER) {
+            api_key = None;
+            if let Some(key) = input.api_key.clone().filter(|k| k != SECRET_KEY_PLACEHOLDER) {
+                let key = Some(key).filter(|k| !k.is_empty());
+                if key.is_none() {
+                    // Clearing the key drops the placeholder too, as `set_provider_secret` does
+                    api_key = Some(None);
+                }
+                self.store.set_secret(id, key).await?;
+            }
+        }
+        let api_base_url = input
+            .api_base_url
+            .as_deref()
+            .map(normalize_api_base_url)
+            .transpose()?;
+        let non_empty = |v: &Option<String>| v.clone().filter(|v| !v.trim().is_empty());
+        validate_auth_header(
+            non_empty(&input.auth_header_name).as_deref(),
+            non_empty(&input.auth_header_template).as_deref(),
+        )?;
 
         let id_owned = id.to_string();
         self.store
@@ -72,11 +121,72 @@ impl ProviderService {
                     if let Some(name) = input.name.clone() {
                         provider.name = name;
                     }
-                    if input.api_base_url.is_some() {
-                        provider.api_base_url = input.api_base_url.clone();
+                    if api_base_url.is_some() {
+                        provider.api_base_url = api_base_url.clone();
+                    }
+                    if let Some(api_key) = api_key.clone() {
+                        provider.api_key = api_key;
+                    }
+                    if input.agent_account.is_some() {
+                        provider.agent_account = input.agent_account.clone();
+                    }
+                    if let Some(limit) = input.max_concurrent_requests {
+                        // 0 clears the limit
+                        provider.max_concurrent_requests = (limit > 0).then_some(limit);
+                    }
+                    // Empty strings restore the default attribution
+                    if let Some(referer) = input.http_referer.clone() {
+                        provider.http_referer = Some(referer).filter(|v| !v.trim().is_empty());
+                    }
+                    if let Some(title) = input.app_title.clone() {
+                        provider.app_title = Some(title).filter(|v| !v.trim().is_empty());
+                    }
+                    // Empty strings restore the default `Authorization: Bearer {key}`
+                    if let Some(name) = input.auth_header_name.clone() {
+                        provider.auth_header_name = Some(name).filter(|v| !v.trim().is_empty());
+                    }
+                    if let Some(template) = input.auth_header_template.clone() {
+                        provider.auth_header_template =
+                            Some(template).filter(|v| !v.trim().is_empty());
+                    }
+                    if let Some(passthrough) = input.passthrough_auth {
+                        provider.passthrough_auth = passthrough;
+                    }
+                    if let Some(transform) = input.response_transform.clone() {
+                        provider.response_transform = Some(transform).filter(|t| !t.is_empty());
+                    }
+                    if let Some(user_agent) = input.user_agent.clone() {
+                        provider.user_agent = Some(user_agent).filter(|v| !v.trim().is_empty());
+                    }
+                    if let Some(deployment) = input.deployment.clone() {
+                        provider.deployment = Some(deployment).filter(|v| !v.trim().is_empty());
+                    }
+                    if let Some(api_version) = input.api_version.clone() {
+                        provider.api_version = Some(api_version).filter(|v| !v.trim().is_empty());
+                    }
+                    if let Some(force_model) = input.force_model.clone() {
+                        provider.force_model = Some(force_model).filter(|v| !v.trim().is_empty());
+                    }
+                    if let Some(idempotency_keys) = input.idempotency_keys {
+                        provider.idempotency_keys = idempotency_keys;
+                    }
+                    if let Some(price) = input.input_price_per_1k {
+                        provider.input_price_per_1k = (price > 0.0).then_some(price);
+                    }
+                    if let Some(price) = input.output_price_per_1k {
+                        provider.output_price_per_1k = (price > 0.0).then_some(price);
+                    }
+                    if let Some(prices) = input.model_prices.clone() {
+                        provider.model_prices = prices;
+                    }
+                    if let Some(limit) = input.spend_limit.clone() {
+                        provider.spend_limit = Some(limit).filter(|l| l.limit_usd > 0.0);
                     }
-                    if input.api_key.is_some() {
-                        provider.api_key = input.api_key.clone();
+                    if let Some(tags) = input.tags.clone() {
+                        provider.tags = normalize_tags(tags);
+                    }
+                    if let Some(group) = input.group.as_deref() {
+                        provider.group = normalize_group(group);
                     }
                     provider.updated_at = Utc::now();
                 }
@@ -86,25 +196,315 @@ impl ProviderService {
         self.get_provider(id).await
     }
 
-    pub async fn delete_provider(&self, id: &str) -> Result<(), ProviderError> {
+    /// Create many providers with a single config write. Invalid items and duplicates
+    /// (same name and base URL as an existing or earlier provider) are reported per item
+    /// instead of aborting the batch.
+    pub async fn import_providers(
+        &self,
+        inputs: Vec<CreateProviderInput>,
+    ) -> Result<ProviderImportResult, ProviderError> {
+        let mut errors = Vec::new();
+        let mut candidates = Vec::with_capacity(inputs.len());
+        for (index, input) in inputs.into_iter().enumerate() {
+            let name = input.name.clone();
+            match provider_from_input(input) {
+                Ok(provider) => candidates.push((index, provider)),
+                Err(e) => errors.push(ProviderImportError {
+                    index,
+                    name,
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        let mut created = Vec::with_capacity(candidates.len());
+        self.store
+            .update(|config| {
+                for (index, provider) in candidates {
+                    let duplicate = config.providers.iter().any(|p| {
+                        p.name == provider.name && p.api_base_url == provider.api_base_url
+                    });
+                    if duplicate {
+                        errors.push(ProviderImportError {
+                            index,
+                            name: provider.name,
+                            error: "A provider with this name and base URL already exists"
+                                .to_string(),
+                        });
+                        continue;
+                    }
+                    config.providers.push(provider.clone());
+                    created.push(provider);
+                }
+            })
+            .await?;
+
+        errors.sort_by_key(|e| e.index);
+        Ok(ProviderImportResult { created, errors })
+    }
+
+    /// Set the provider order. Listed providers move to the front in the given order;
+    /// unlisted ones keep their relative order after them. Among equally healthy
+    /// providers, the fallback picks the first in this order.
+    pub async fn reorder_providers(&self, provider_ids: Vec<String>) -> Result<(), ProviderError> {
+        self.store
+            .update(|config| reorder_by_ids(&mut config.providers, &provider_ids))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Copy a provider's endpoint and settings into a new provider named "<name> (copy)".
+    /// Credentials and connection status are not copied.
+    pub async fn duplicate_provider(&self, id: &str) -> Result<Provider, ProviderError> {
+        let source = self.get_provider(id).await?;
+        let copy = duplicate_of(&source);
+
+        let copy_clone = copy.clone();
+        self.store
+            .update(|config| {
+                config.providers.push(copy_clone);
+            })
+            .await?;
+
+        self.get_provider(&copy.id).await
+    }
+
+    /// Take a provider in or out of rotation without deleting it or its rules
+    pub async fn set_provider_enabled(
+        &self,
+        id: &str,
+        enabled: bool,
+    ) -> Result<Provider, ProviderError> {
         self.get_provider(id).await?;
 
+        let id_owned = id.to_string();
+        self.store
+            .update(|config| {
+                if let Some(provider) = config.providers.iter_mut().find(|p| p.id == id_owned) {
+                    provider.enabled = enabled;
+                    provider.updated_at = Utc::now();
+                }
+            })
+            .await?;
+
+        self.get_provider(id).await
+    }
+
+    /// Delete a provider with its routing rules. With `remove_auth_file`, an agent provider's
+    /// OAuth file under auth/ is deleted too, unless another provider still uses that account.
+    pub async fn delete_provider(
+        &self,
+        id: &str,
+        remove_auth_file: bool,
+    ) -> Result<(), ProviderError> {
+        let provider = self.get_provider(id).await?;
+
         let id_owned = id.to_string();
         self.store
             .update(|config| {
                 config.providers.retain(|p| p.id != id_owned);
                 config.routing_rules.retain(|r| r.provider_id != id_owned);
+                config
+                    .app
+                    .default_provider_by_group
+                    .retain(|_, provider_id| *provider_id != id_owned);
             })
             .await?;
+        if self.store.get_secret(id).await.is_some() {
+            self.store.set_secret(id, None).await?;
+        }
+        if let Some(agent_type) = provider.agent_account.as_ref().filter(|_| remove_auth_file) {
+            self.remove_unused_auth_file(agent_type).await?;
+        }
 
         Ok(())
     }
 
-    pub async fn test_connection(&self, id: &str) -> Result<ConnectionStatus, ProviderError> {
+    async fn remove_unused_auth_file(
+        &self,
+        agent_type: &AgentProviderType,
+    ) -> Result<(), ProviderError> {
+        let config = self.store.get_config().await;
+        if let Some(other) = config
+            .providers
+            .iter()
+            .find(|p| p.agent_account.as_ref() == Some(agent_type))
+        {
+            tracing::info!(
+                "Keeping {:?} auth file, still used by provider {}",
+                agent_type,
+                other.name
+            );
+            return Ok(());
+        }
+        let path = auth_path_in(self.store.config_dir(), agent_type);
+        if path.exists() {
+            tokio::fs::remove_file(&path).await?;
+            tracing::info!("Removed auth file for {:?}: {}", agent_type, path.display());
+        }
+        Ok(())
+    }
+
+    /// List agent auth files (`<agent>*.json`) that no provider's agent account refers to,
+    /// deleting them when `delete` is set. Anything else in the directory is left alone.
+    pub async fn cleanup_orphan_auth_files(
+        &self,
+        delete: bool,
+    ) -> Result<OrphanAuthFiles, ProviderError> {
+        let config = self.store.get_config().await;
+        let referenced: Vec<&str> = config
+            .providers
+            .iter()
+            .filter_map(|p| p.agent_account.as_ref().map(auth_filename))
+            .collect();
+
+        let auth_dir = self.store.config_dir().join("auth");
+        let mut files = Vec::new();
+        if auth_dir.exists() {
+            let mut entries = tokio::fs::read_dir(&auth_dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if entry.file_type().await?.is_file()
+                    && is_agent_auth_filename(&name)
+                    && !referenced.contains(&name.as_str())
+                {
+                    files.push(entry.path());
+                }
+            }
+        }
+        files.sort();
+
+        if delete {
+            for path in &files {
+                tokio::fs::remove_file(path).await?;
+                tracing::info!("Removed orphan auth file {}", path.display());
+            }
+        } else if !files.is_empty() {
+            tracing::info!("Found {} orphan auth file(s) in {}", files.len(), auth_dir.display());
+        }
+        Ok(OrphanAuthFiles {
+            files: files.iter().map(|p| p.display().to_string()).collect(),
+            removed: delete,
+        })
+    }
+
+    /// Move a provider's API key into secrets.json, leaving `SECRET_KEY_PLACEHOLDER` in
+    /// settings.json. An empty key removes the stored secret.
+    pub async fn set_provider_secret(
+        &self,
+        id: &str,
+        api_key: String,
+    ) -> Result<Provider, ProviderError> {
+        self.get_provider(id).await?;
+        let api_key = Some(api_key).filter(|k| !k.is_empty());
+        let placeholder = api_key.as_ref().map(|_| SECRET_KEY_PLACEHOLDER.to_string());
+        self.store.set_secret(id, api_key).await?;
+
+        let id_owned = id.to_string();
+        self.store
+            .update(|config| {
+                if let Some(provider) = config.providers.iter_mut().find(|p| p.id == id_owned) {
+                    provider.api_key = placeholder;
+                    provider.updated_at = Utc::now();
+                }
+            })
+            .await?;
+
+        self.get_provider(id).await
+    }
+
+    /// A curl command matching what the proxy sends this provider, for debugging and docs
+    pub async fn curl_example(
+        &self,
+        id: &str,
+        sample_model: &str,
+    ) -> Result<String, ProviderError> {
+        let mut provider = self.get_provider(id).await?;
+        self.store.resolve_provider_secret(&mut provider).await;
+        let base_url = provider
+            .api_base_url
+            .clone()
+            .filter(|url| !url.is_empty())
+            .ok_or_else(|| ProviderError::InvalidBaseUrl("not configured".to_string()))?;
+        let app = self.store.get_config().await.app;
+        Ok(provider_curl_example(&provider, &base_url, sample_model, &app))
+    }
+
+    /// Time to first byte of an authenticated model-list request through the configured
+    /// upstream proxy. Unlike `test_connection` this actually reaches the provider.
+    pub async fn ping_provider(&self, id: &str) -> Result<ProviderPing, ProviderError> {
+        let mut provider = self.get_provider(id).await?;
+        self.store.resolve_provider_secret(&mut provider).await;
+        let base_url = provider
+            .api_base_url
+            .clone()
+            .filter(|url| !url.is_empty())
+            .ok_or_else(|| ProviderError::InvalidBaseUrl("not configured".to_string()))?;
+        let failed = |error: String| ProviderPing {
+            success: false,
+            status: None,
+            latency_ms: None,
+            error: Some(error),
+        };
+
+        let oauth_token = match &provider.agent_account {
+            Some(agent_type) => {
+                let ctx = AgentAuthContext::new(self.store.clone());
+                match get_agent_access_token(&ctx, agent_type).await {
+                    Ok(token) => Some(token),
+                    Err(e) => return Ok(failed(format!("Agent account login required: {}", e))),
+                }
+            }
+            None => None,
+        };
+
+        let config = self.store.get_config().await;
+        let req = create_http_client(&config)
+            .get(provider_models_url(&provider, &base_url))
+            .timeout(PING_TIMEOUT);
+        let req = add_provider_auth(req, &provider, oauth_token.as_deref());
+
+        let start = std::time::Instant::now();
+        let mut response = match req.send().await {
+            Ok(response) => response,
+            Err(e) => return Ok(failed(e.to_string())),
+        };
+        let status = response.status();
+        // Wait for the first body byte, not just the headers
+        let first_chunk = response.chunk().await;
+        let latency_ms = start.elapsed().as_millis() as u64;
+
+        Ok(ProviderPing {
+            success: status.is_success() && first_chunk.is_ok(),
+            status: Some(status.as_u16()),
+            latency_ms: Some(latency_ms),
+            error: first_chunk.err().map(|e| e.to_string()),
+        })
+    }
+
+    /// Dollar estimate for a request of `prompt_tokens` that generates up to `max_tokens`,
+    /// at the provider's price for `model`
+    pub async fn estimate_request_cost(
+        &self,
+        id: &str,
+        model: &str,
+        prompt_tokens: u64,
+        max_tokens: u64,
+    ) -> Result<f64, ProviderError> {
         let provider = self.get_provider(id).await?;
+        let price = provider
+            .price_for(model)
+            .ok_or_else(|| ProviderError::NoPricing(model.to_string()))?;
+        Ok(price.cost(prompt_tokens, max_tokens))
+    }
+
+    pub async fn test_connection(&self, id: &str) -> Result<ConnectionStatus, ProviderError> {
+        let mut provider = self.get_provider(id).await?;
+        self.store.resolve_provider_secret(&mut provider).await;
         let start = std::time::Instant::now();
 
-        let is_connected = provider.api_key.as_ref().map_or(false, |k| !k.is_empty())
+        let is_connected = provider.resolved_api_key().is_some()
             && provider.api_base_url.as_ref().map_or(false, |u| !u.is_empty());
         let latency_ms = start.elapsed().as_millis() as u64;
 
@@ -136,3 +536,480 @@ impl ProviderService {
         })
     }
 }
+
+/// Sort providers into the order of `ids`
+fn reorder_by_ids(providers: &mut [Provider], ids: &[String]) {
+    // Stable sort keeps unlisted providers in their current relative order
+    providers.sort_by_key(|p| ids.iter().position(|id| *id == p.id).unwrap_or(ids.len()));
+}
+
+/// Validate a create input and build the provider it describes
+fn provider_from_input(input: CreateProviderInput) -> Result<Provider, ProviderError> {
+    let api_base_url = normalize_api_base_url(&input.api_base_url.unwrap_or_default())?;
+    let auth_header_name = input.auth_header_name.filter(|v| !v.trim().is_empty());
+    let auth_header_template = input.auth_header_template.filter(|v| !v.trim().is_empty());
+    validate_auth_header(auth_header_name.as_deref(), auth_header_template.as_deref())?;
+
+    let mut provider = Provider::new_model(
+        input.name,
+        input.provider_type,
+        api_base_url,
+        input.api_key.unwrap_or_default(),
+    );
+    provider.agent_account = input.agent_account;
+    provider.max_concurrent_requests = input.max_concurrent_requests;
+    provider.http_referer = input.http_referer.filter(|v| !v.trim().is_empty());
+    provider.app_title = input.app_title.filter(|v| !v.trim().is_empty());
+    provider.auth_header_name = auth_header_name;
+    provider.auth_header_template = auth_header_template;
+    provider.passthrough_auth = input.passthrough_auth;
+    provider.response_transform = input.response_transform.filter(|t| !t.is_empty());
+    provider.user_agent = input.user_agent.filter(|v| !v.trim().is_empty());
+    provider.deployment = input.deployment.filter(|v| !v.trim().is_empty());
+    provider.api_version = input.api_version.filter(|v| !v.trim().is_empty());
+    provider.force_model = input.force_model.filter(|v| !v.trim().is_empty());
+    provider.idempotency_keys = input.idempotency_keys;
+    provider.input_price_per_1k = input.input_price_per_1k.filter(|p| *p > 0.0);
+    provider.output_price_per_1k = input.output_price_per_1k.filter(|p| *p > 0.0);
+    provider.model_prices = input.model_prices;
+    provider.spend_limit = input.spend_limit.filter(|l| l.limit_usd > 0.0);
+    provider.tags = normalize_tags(input.tags);
+    provider.group = input.group.as_deref().and_then(normalize_group);
+    Ok(provider)
+}
+
+/// Trimmed, non-empty tags without duplicates, in the order given
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = tag.trim();
+        if !tag.is_empty() && !normalized.iter().any(|t| t == tag) {
+            normalized.push(tag.to_string());
+        }
+    }
+    normalized
+}
+
+fn normalize_group(group: &str) -> Option<String> {
+    Some(group.trim().to_string()).filter(|g| !g.is_empty())
+}
+
+fn duplicate_of(source: &Provider) -> Provider {
+    let now = Utc::now();
+    Provider {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: format!("{} (copy)", source.name),
+        api_key: None,
+        agent_account: None,
+        status: ProviderStatus::Disconnected,
+        created_at: now,
+        updated_at: now,
+        ..source.clone()
+    }
+}
+
+/// A custom auth header name must be a valid header name, and its template must
+/// include the `{key}` placeholder
+fn validate_auth_header(name: Option<&str>, template: Option<&str>) -> Result<(), ProviderError> {
+    if let Some(name) = name {
+        reqwest::header::HeaderName::from_bytes(name.trim().as_bytes()).map_err(|_| {
+            ProviderError::InvalidAuthHeader(format!("invalid header name '{}'", name))
+        })?;
+    }
+    if let Some(template) = template {
+        if !template.contains(AUTH_KEY_PLACEHOLDER) {
+            return Err(ProviderError::InvalidAuthHeader(format!(
+                "template '{}' must contain {}",
+                template, AUTH_KEY_PLACEHOLDER
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Validate an API base URL and store it in a canonical form: parsed by a URL parser
+/// (lower-cased scheme/host) and without trailing slashes. An empty URL is left empty.
+fn normalize_api_base_url(url: &str) -> Result<String, ProviderError> {
+    let trimmed = url.trim();
+    if trimmed.is_empty() {
+        return Ok(String::new());
+    }
+
+    let parsed = reqwest::Url::parse(trimmed)
+        .map_err(|e| ProviderError::InvalidBaseUrl(format!("{} ({})", trimmed, e)))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(ProviderError::InvalidBaseUrl(format!(
+            "{} (scheme must be http or https)",
+            trimmed
+        )));
+    }
+    if parsed.host_str().is_none_or(|h| h.is_empty()) {
+        return Err(ProviderError::InvalidBaseUrl(format!("{} (missing host)", trimmed)));
+    }
+
+    Ok(parsed.as_str().trim_end_matches('/').to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_import_providers_reports_invalid_and_duplicates() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ConfigStore::new(temp_dir.path().to_path_buf()));
+        store.init().await.unwrap();
+        let service = ProviderService::new(store);
+
+        let input = |name: &str, url: &str| {
+            serde_json::from_value::<CreateProviderInput>(serde_json::json!({
+                "name": name,
+                "type": "OpenAI",
+                "apiBaseUrl": url,
+                "apiKey": "sk-test",
+            }))
+            .unwrap()
+        };
+        let result = service
+            .import_providers(vec![
+                input("A", "https://a.example.com/v1"),
+                input("B", "ftp://b.example.com"),
+                input("A", "https://a.example.com/v1/"),
+                input("C", "https://c.example.com/v1"),
+            ])
+            .await
+            .unwrap();
+
+        let created: Vec<&str> = result.created.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(created, vec!["A", "C"]);
+        let failed: Vec<usize> = result.errors.iter().map(|e| e.index).collect();
+        assert_eq!(failed, vec![1, 2]);
+        assert_eq!(service.list_providers(&ProviderFilter::default()).await.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_reorder_by_ids() {
+        let mut providers: Vec<Provider> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|name| {
+                let mut p = Provider::new_model(
+                    name.to_string(),
+                    crate::models::ProviderType::OpenAI,
+                    String::new(),
+                    String::new(),
+                );
+                p.id = name.to_string();
+                p
+            })
+            .collect();
+
+        reorder_by_ids(&mut providers, &["c".to_string(), "a".to_string(), "x".to_string()]);
+        let order: Vec<&str> = providers.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(order, vec!["c", "a", "b", "d"]);
+    }
+
+    #[test]
+    fn test_duplicate_of_clears_credentials() {
+        let mut source = Provider::new_model(
+            "OpenRouter".to_string(),
+            crate::models::ProviderType::OpenRouter,
+            "https://openrouter.ai/api/v1".to_string(),
+            "sk-or-secret".to_string(),
+        );
+        source.status = ProviderStatus::Connected;
+        source.max_concurrent_requests = Some(4);
+
+        let copy = duplicate_of(&source);
+        assert_ne!(copy.id, source.id);
+        assert_eq!(copy.name, "OpenRouter (copy)");
+        assert_eq!(copy.api_base_url, source.api_base_url);
+        assert_eq!(copy.max_concurrent_requests, Some(4));
+        assert!(copy.api_key.is_none());
+        assert_eq!(copy.status, ProviderStatus::Disconnected);
+    }
+
+    #[test]
+    fn test_validate_auth_header() {
+        assert!(validate_auth_header(None, None).is_ok());
+        assert!(validate_auth_header(Some("Api-Key"), Some("{key}")).is_ok());
+        assert!(validate_auth_header(Some("Authorization"), Some("Token {key}")).is_ok());
+        assert!(matches!(
+            validate_auth_header(None, Some("Bearer")),
+            Err(ProviderError::InvalidAuthHeader(_))
+        ));
+        assert!(matches!(
+            validate_auth_header(Some("Api Key"), None),
+            Err(ProviderError::InvalidAuthHeader(_))
+        ));
+    }
+
+    #[test]
+    fn test_normalize_api_base_url() {
+        assert_eq!(
+            normalize_api_base_url("https://api.openai.com").unwrap(),
+            "https://api.openai.com"
+        );
+        assert_eq!(
+            normalize_api_base_url("https://api.openai.com/v1").unwrap(),
+            "https://api.openai.com/v1"
+        );
+        assert_eq!(
+            normalize_api_base_url("https://api.openai.com/v1/").unwrap(),
+            "https://api.openai.com/v1"
+        );
+        assert_eq!(
+            normalize_api_base_url("  HTTPS://API.OpenAI.com/v1//  ").unwrap(),
+            "https://api.openai.com/v1"
+        );
+        assert_eq!(normalize_api_base_url("").unwrap(), "");
+    }
+
+    #[test]
+    fn test_normalize_api_base_url_rejects_invalid() {
+        assert!(normalize_api_base_url("api.openai.com/v1").is_err());
+        assert!(normalize_api_base_url("ftp://api.openai.com").is_err());
+        assert!(normalize_api_base_url("https://").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_delete_provider_removes_unshared_auth_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ConfigStore::new(temp_dir.path().to_path_buf()));
+        store.init().await.unwrap();
+        let service = ProviderService::new(store.clone());
+
+        let agent_provider = |name: &str| {
+            let mut provider = Provider::new_model(
+                name.to_string(),
+                crate::models::ProviderType::OpenAI,
+                "https://chatgpt.com/backend-api/codex".to_string(),
+                String::new(),
+            );
+            provider.agent_account = Some(AgentProviderType::Codex);
+            provider
+        };
+        let (first, second) = (agent_provider("Codex"), agent_provider("Codex 2"));
+        let ids = (first.id.clone(), second.id.clone());
+        store.update(|c| c.providers = vec![first, second]).await.unwrap();
+        let auth_path = auth_path_in(temp_dir.path(), &AgentProviderType::Codex);
+        std::fs::create_dir_all(auth_path.parent().unwrap()).unwrap();
+        std::fs::write(&auth_path, "{}").unwrap();
+
+        // Still referenced by the second provider
+        service.delete_provider(&ids.0, true).await.unwrap();
+        assert!(auth_path.exists());
+
+        service.delete_provider(&ids.1, true).await.unwrap();
+        assert!(!auth_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_orphan_auth_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ConfigStore::new(temp_dir.path().to_path_buf()));
+        store.init().await.unwrap();
+        let service = ProviderService::new(store.clone());
+
+        let mut provider = Provider::new_model(
+            "Gemini".to_string(),
+            crate::models::ProviderType::Google,
+            "https://cloudcode-pa.googleapis.com".to_string(),
+            String::new(),
+        );
+        provider.agent_account = Some(AgentProviderType::GeminiCli);
+        store.update(|c| c.providers = vec![provider]).await.unwrap();
+        let used = auth_path_in(temp_dir.path(), &AgentProviderType::GeminiCli);
+        let orphan = auth_path_in(temp_dir.path(), &AgentProviderType::Codex);
+        std::fs::create_dir_all(used.parent().unwrap()).unwrap();
+        std::fs::write(&used, "{}").unwrap();
+        std::fs::write(&orphan, "{}").unwrap();
+        // Files that aren't agent logins are never touched
+        let auth_dir = used.parent().unwrap();
+        for name in [".DS_Store", "notes.txt", "codex.json.tmp"] {
+            std::fs::write(auth_dir.join(name), "x").unwrap();
+        }
+
+        let report = service.cleanup_orphan_auth_files(false).await.unwrap();
+        assert_eq!(report.files, vec![orphan.display().to_string()]);
+        assert!(!report.removed && orphan.exists());
+
+        let report = service.cleanup_orphan_auth_files(true).await.unwrap();
+        assert!(report.removed && !orphan.exists() && used.exists());
+        assert!(auth_dir.join(".DS_Store").exists() && auth_dir.join("codex.json.tmp").exists());
+    }
+
+    #[tokio::test]
+    async fn test_ping_provider_measures_first_byte() {
+        use axum::{
+            http::{HeaderMap, StatusCode},
+            routing::get,
+            Router,
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().route(
+            "/v1/models",
+            get(|headers: HeaderMap| async move {
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                let authorized = headers
+                    .get("authorization")
+                    .is_some_and(|v| v == "Bearer sk-test");
+                let status = if authorized { StatusCode::OK } else { StatusCode::UNAUTHORIZED };
+                (status, "{\"data\":[]}")
+            }),
+        );
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ConfigStore::new(temp_dir.path().to_path_buf()));
+        store.init().await.unwrap();
+        let provider = Provider::new_model(
+            "Local".to_string(),
+            crate::models::ProviderType::OpenAI,
+            format!("http://{}", addr),
+            "sk-test".to_string(),
+        );
+        let id = provider.id.clone();
+        store.update(|config| config.providers.push(provider)).await.unwrap();
+
+        let ping = ProviderService::new(store).ping_provider(&id).await.unwrap();
+        assert!(ping.success, "{:?}", ping);
+        assert_eq!(ping.status, Some(200));
+        assert!(ping.latency_ms.unwrap() >= 20);
+    }
+
+    #[tokio::test]
+    async fn test_estimate_request_cost() {
+        use crate::models::ModelPrice;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ConfigStore::new(temp_dir.path().to_path_buf()));
+        store.init().await.unwrap();
+        let service = ProviderService::new(store.clone());
+        let mut provider = Provider::new_model(
+            "Priced".to_string(),
+            crate::models::ProviderType::OpenAI,
+            "https://api.openai.com/v1".to_string(),
+            "sk-test".to_string(),
+        );
+        let id = provider.id.clone();
+        provider.model_prices.insert(
+            "gpt-4o".to_string(),
+            ModelPrice {
+                input_per_1k: 2.5,
+                output_per_1k: 10.0,
+            },
+        );
+        store.update(|c| c.providers = vec![provider]).await.unwrap();
+
+        let cost = service.estimate_request_cost(&id, "gpt-4o", 2000, 500).await.unwrap();
+        assert!((cost - 10.0).abs() < 1e-9);
+        assert!(matches!(
+            service.estimate_request_cost(&id, "gpt-4o-mini", 2000, 500).await,
+            Err(ProviderError::NoPricing(_))
+        ));
+
+        // Provider-wide prices cover models without their own entry
+        store
+            .update(|c| c.providers[0].output_price_per_1k = Some(1.0))
+            .await
+            .unwrap();
+        let cost = service.estimate_request_cost(&id, "gpt-4o-mini", 2000, 500).await.unwrap();
+        assert!((cost - 0.5).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_provider_tags_and_groups() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ConfigStore::new(temp_dir.path().to_path_buf()));
+        store.init().await.unwrap();
+        let service = ProviderService::new(store);
+
+        let create = |name: &str, tags: &[&str], group: Option<&str>| {
+            serde_json::from_value::<CreateProviderInput>(serde_json::json!({
+                "name": name,
+                "type": "OpenAI",
+                "apiBaseUrl": "https://api.example.com/v1",
+                "tags": tags,
+                "group": group,
+            }))
+            .unwrap()
+        };
+        let work = service
+            .create_provider(create("Work", &[" fast ", "paid", "fast", ""], Some(" work ")))
+            .await
+            .unwrap();
+        assert_eq!(work.tags, vec!["fast", "paid"]);
+        assert_eq!(work.group.as_deref(), Some("work"));
+        service.create_provider(create("Home", &["fast"], Some("home"))).await.unwrap();
+        service.create_provider(create("Loose", &[], None)).await.unwrap();
+
+        let names = |providers: Vec<Provider>| -> Vec<String> {
+            providers.into_iter().map(|p| p.name).collect()
+        };
+        let filter = |tag: Option<&str>, group: Option<&str>| ProviderFilter {
+            tag: tag.map(str::to_string),
+            group: group.map(str::to_string),
+        };
+        let fast = service.list_providers(&filter(Some("fast"), None)).await.unwrap();
+        assert_eq!(names(fast), vec!["Work", "Home"]);
+        let fast_home = service.list_providers(&filter(Some("fast"), Some("home"))).await.unwrap();
+        assert_eq!(names(fast_home), vec!["Home"]);
+        assert_eq!(service.list_provider_groups().await.unwrap(), vec!["home", "work"]);
+
+        // An empty group ungroups the provider
+        let updated = service
+            .update_provider(
+                &work.id,
+                UpdateProviderInput {
+                    group: Some(String::new()),
+                    ..UpdateProviderInput::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(updated.group, None);
+        assert_eq!(updated.tags, vec!["fast", "paid"]);
+        assert_eq!(service.list_provider_groups().await.unwrap(), vec!["home"]);
+    }
+
+    #[tokio::test]
+    async fn test_update_provider_clears_secret_key() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ConfigStore::new(temp_dir.path().to_path_buf()));
+        store.init().await.unwrap();
+        let service = ProviderService::new(store.clone());
+
+        let input = serde_json::from_value::<CreateProviderInput>(serde_json::json!({
+            "name": "Secret",
+            "type": "OpenAI",
+            "apiBaseUrl": "https://api.example.com/v1",
+        }))
+        .unwrap();
+        let provider = service.create_provider(input).await.unwrap();
+        let provider = service
+            .set_provider_secret(&provider.id, "sk-secret".to_string())
+            .await
+            .unwrap();
+        assert_eq!(provider.api_key.as_deref(), Some(SECRET_KEY_PLACEHOLDER));
+
+        // Sending the placeholder back leaves the stored key alone
+        let update = |api_key: &str| UpdateProviderInput {
+            api_key: Some(api_key.to_string()),
+            ..UpdateProviderInput::default()
+        };
+        let kept = service
+            .update_provider(&provider.id, update(SECRET_KEY_PLACEHOLDER))
+            .await
+            .unwrap();
+        assert_eq!(kept.api_key.as_deref(), Some(SECRET_KEY_PLACEHOLDER));
+        assert_eq!(store.get_secret(&provider.id).await.as_deref(), Some("sk-secret"));
+
+        let cleared = service.update_provider(&provider.id, update("")).await.unwrap();
+        assert_eq!(cleared.api_key, None);
+        assert_eq!(store.get_secret(&provider.id).await, None);
+    }
+}
@@ -1,40 +1,118 @@
+use std::collections::{HashMap, VecDeque};
 use std::net::SocketAddr;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use axum::{
-    body::Body,
-    extract::State,
-    http::{header, Method, Request, Response, StatusCode},
+    body::{Body, HttpBody},
+    extract::{
+        ws::{self, rejection::WebSocketUpgradeRejection, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    http::{header, HeaderMap, HeaderValue, Method, Request, Response, StatusCode, Uri},
     routing::any,
     Router,
 };
 use bytes::Bytes;
-use futures_util::StreamExt;
+use chrono::Utc;
+use futures_util::{SinkExt, StreamExt};
 use glob::Pattern;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use tokio::sync::{oneshot, RwLock};
-use tower_http::cors::{Any, CorsLayer};
+use sha2::{Digest, Sha256};
+use tokio::sync::{oneshot, OwnedSemaphorePermit, RwLock, Semaphore};
+use tokio_tungstenite::tungstenite::{
+    self, client::IntoClientRequest, protocol::frame::coding::CloseCode,
+};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 
-use crate::models::{ApiGroup, Provider, RoutingRule, RuleType, VibeMateConfig};
+use super::access_log::{access_log_path, redacted_path_and_query, AccessLogWriter};
+use super::spend::{spend_path, SpendMeter, SpendTracker};
+use super::translate::{
+    anthropic_to_gemini_request, gemini_error_to_anthropic, gemini_generate_url,
+    gemini_to_anthropic_response, is_streaming_request,
+};
+use crate::agents::{
+    get_agent_access_token, parse_retry_after, AgentAuthContext, CLAUDE_OAUTH_BETA,
+};
+use crate::models::{
+    redact_proxy_url, validate_proxy_url, AccessLogEntry, ApiGroup, AppConfig, PortAvailability,
+    Provider, ProviderSpend, ProviderType, ProxyMode, ReplayResult, RequestRecord, RequestTimings,
+    ResponseTransform, RoutingRule, RuleType, VibeMateConfig, AUTH_KEY_PLACEHOLDER,
+};
 use crate::storage::ConfigStore;
 
-/// Create HTTP client with proxy support based on config
-fn create_http_client(config: &VibeMateConfig) -> Client {
-    let mut builder = Client::builder().timeout(std::time::Duration::from_secs(300));
+/// Attribution headers OpenRouter uses for app rankings, unless the provider overrides them
+const OPENROUTER_DEFAULT_REFERER: &str = "https://github.com/zhenlohuang/vibe-mate";
+const OPENROUTER_DEFAULT_TITLE: &str = "Vibe Mate";
+
+/// Original upstream status, added when the proxy rewrote an upstream error body
+const UPSTREAM_STATUS_HEADER: &str = "x-vibemate-upstream-status";
+
+/// Auth header for custom providers that don't configure their own
+const DEFAULT_AUTH_HEADER_NAME: &str = "Authorization";
+const DEFAULT_AUTH_HEADER_TEMPLATE: &str = "Bearer {key}";
+
+/// SSE comment sent to keep idle streams alive; parsers ignore comment lines
+const SSE_KEEPALIVE: &[u8] = b": ping\n\n";
+
+/// Header letting idempotency-aware upstreams deduplicate retried requests
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
 
-    if config.app.enable_proxy {
+/// Anthropic's token counting endpoint; its body carries `model` like a Messages request
+const ANTHROPIC_COUNT_TOKENS_PATH: &str = "/v1/messages/count_tokens";
+
+/// Returned as a 402 when every provider that could take a request is over its spend limit
+const SPEND_LIMIT_EXHAUSTED: &str =
+    "Every provider that could serve this request reached its spend limit for this period";
+
+/// Azure OpenAI `api-version` used when the provider doesn't set one
+const AZURE_DEFAULT_API_VERSION: &str = "2024-10-21";
+
+/// Longest backoff (computed or from `Retry-After`) we wait before retrying an upstream
+/// request; beyond this the 429/503 is returned to the client as is
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// How long a request waits for a free provider slot before being rejected with 429
+const PROVIDER_QUEUE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Create HTTP client with proxy support based on config.
+/// There is no total timeout: a stalled upstream fails on the connect or idle (per-read)
+/// timeout, while a stream that keeps producing data runs as long as it needs.
+pub(crate) fn create_http_client(config: &VibeMateConfig) -> Client {
+    // Response bodies are relayed with their Content-Encoding untouched, so reqwest must
+    // never decompress them (these are no-ops unless a dependency enables the features)
+    let mut builder = Client::builder()
+        .connect_timeout(Duration::from_secs(config.app.connect_timeout_secs.max(1)))
+        .read_timeout(Duration::from_secs(config.app.idle_timeout_secs.max(1)))
+        .no_gzip()
+        .no_brotli()
+        .no_deflate()
+        .no_zstd();
+
+    if config.app.proxy_mode == ProxyMode::System {
+        // reqwest picks up HTTP(S)_PROXY/ALL_PROXY and the OS proxy settings by default
+        tracing::debug!("Using system proxy settings");
+    } else if config.app.proxy_mode == ProxyMode::Custom {
         if let Some(proxy_url) = &config.app.proxy_url {
-            tracing::info!("Creating HTTP client with proxy: {}", proxy_url);
+            tracing::info!(
+                "Creating HTTP client with proxy: {}",
+                redact_proxy_url(proxy_url)
+            );
 
-            match reqwest::Proxy::all(proxy_url) {
+            let proxy = validate_proxy_url(proxy_url)
+                .and_then(|_| reqwest::Proxy::all(proxy_url).map_err(|e| e.to_string()));
+            match proxy {
                 Ok(mut proxy) => {
+                    if let Some((username, password)) = config.app.proxy_credentials() {
+                        proxy = proxy.basic_auth(username, password);
+                    }
                     // Configure no_proxy list
-                    if !config.app.no_proxy.is_empty() {
-                        tracing::debug!("Configuring no_proxy patterns: {:?}", config.app.no_proxy);
-                        let no_proxy =
-                            reqwest::NoProxy::from_string(&config.app.no_proxy.join(","));
+                    let no_proxy_list = config.app.effective_no_proxy();
+                    if !no_proxy_list.is_empty() {
+                        tracing::debug!("Configuring no_proxy patterns: {:?}", no_proxy_list);
+                        let no_proxy = reqwest::NoProxy::from_string(&no_proxy_list.join(","));
                         proxy = proxy.no_proxy(no_proxy);
                     }
                     builder = builder.proxy(proxy);
@@ -63,16 +141,32 @@ pub struct ProxyServer {
     request_count: AtomicU64,
     store: Arc<ConfigStore>,
     shutdown_tx: RwLock<Option<oneshot::Sender<()>>>,
+    provider_limiters: ProviderLimiters,
+    last_timings: Mutex<Option<RequestTimings>>,
+    /// Recent requests, newest at the back
+    recent_requests: Mutex<VecDeque<RequestRecord>>,
+    /// Capture bodies of the next proxied request
+    capture_next: AtomicBool,
+    /// Answer proxied requests with 503 instead of forwarding them
+    paused: AtomicBool,
+    spend: Arc<SpendTracker>,
 }
 
 impl ProxyServer {
     pub fn new(store: Arc<ConfigStore>) -> Self {
+        let spend = Arc::new(SpendTracker::load(spend_path(store.config_dir())));
         Self {
             is_running: AtomicBool::new(false),
             port: AtomicU64::new(12345),
             request_count: AtomicU64::new(0),
             store,
             shutdown_tx: RwLock::new(None),
+            provider_limiters: ProviderLimiters::default(),
+            last_timings: Mutex::new(None),
+            recent_requests: Mutex::new(VecDeque::new()),
+            capture_next: AtomicBool::new(false),
+            paused: AtomicBool::new(false),
+            spend,
         }
     }
 
@@ -92,6 +186,152 @@ impl ProxyServer {
         self.request_count.fetch_add(1, Ordering::SeqCst);
     }
 
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Stop forwarding requests while keeping the server bound; takes effect immediately
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    /// Current-period spend of every provider
+    pub async fn provider_spend(&self) -> Vec<ProviderSpend> {
+        let config = self.store.get_config().await;
+        config
+            .providers
+            .iter()
+            .map(|provider| self.spend.spend_for(provider))
+            .collect()
+    }
+
+    /// Zero the request counter and recorded timings
+    pub fn reset_stats(&self) {
+        self.request_count.store(0, Ordering::SeqCst);
+        *self.last_timings.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// Stage timings of the most recently completed proxied request
+    pub fn last_request_timings(&self) -> Option<RequestTimings> {
+        self.last_timings
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn record_timings(&self, timings: RequestTimings) {
+        *self.last_timings.lock().unwrap_or_else(|e| e.into_inner()) = Some(timings);
+    }
+
+    /// Recently proxied requests, newest first
+    pub fn recent_requests(&self) -> Vec<RequestRecord> {
+        self.recent_requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .rev()
+            .cloned()
+            .collect()
+    }
+
+    /// Record request and response bodies for the next proxied request only
+    pub fn capture_next_request(&self) {
+        self.capture_next.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether the current request should capture bodies; consumes the toggle
+    fn take_capture_next(&self) -> bool {
+        self.capture_next.swap(false, Ordering::SeqCst)
+    }
+
+    /// Re-send a recorded request through the proxy pipeline, optionally to a specific
+    /// provider instead of the one the routing rules pick. The record must have a captured
+    /// body. Replays are not counted, recorded, or access-logged.
+    pub async fn replay_request(
+        self: &Arc<Self>,
+        record_id: &str,
+        override_provider_id: Option<String>,
+    ) -> Result<ReplayResult, ProxyError> {
+        let record = self
+            .recent_requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find(|r| r.id == record_id)
+            .cloned()
+            .ok_or_else(|| ProxyError::Replay(format!("request {} not found", record_id)))?;
+        let body = record.request_body.ok_or_else(|| {
+            ProxyError::Replay("body was not captured; use capture_next_request".to_string())
+        })?;
+
+        let config = self.store.get_config().await;
+        if let Some(id) = &override_provider_id {
+            if !config.providers.iter().any(|p| p.id == *id) {
+                return Err(ProxyError::Replay(format!("provider {} not found", id)));
+            }
+        }
+
+        let state = AppState {
+            server: Arc::clone(self),
+            http_client: create_http_client(&config),
+            auth_ctx: AgentAuthContext::new(self.store.clone()),
+            access_log: None,
+            route_prefix: config.app.route_prefix.clone(),
+        };
+        // `path` has query secrets redacted; the captured original is sent instead
+        let mut req = Request::builder()
+            .method(record.method.as_str())
+            .uri(record.replay_uri.as_deref().unwrap_or(&record.path));
+        if let Some(content_type) = &record.request_content_type {
+            req = req.header(header::CONTENT_TYPE, content_type.as_str());
+        }
+        let req = req
+            .body(Body::from(body))
+            .map_err(|e| ProxyError::Replay(e.to_string()))?;
+
+        let mut outcome = RequestOutcome::default();
+        let result = forward_request(
+            &state,
+            req,
+            record.api_group,
+            override_provider_id.as_deref(),
+            &mut outcome,
+        )
+        .await;
+        let (status, body) = match result {
+            Ok(response) => {
+                let status = response.status().as_u16();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .map_err(|e| ProxyError::Replay(e.to_string()))?;
+                (status, String::from_utf8_lossy(&bytes).into_owned())
+            }
+            Err(status) => (status.as_u16(), String::new()),
+        };
+
+        Ok(ReplayResult {
+            status,
+            body,
+            provider: outcome.provider,
+        })
+    }
+
+    /// Add a request to the ring buffer, evicting the oldest beyond `capacity`
+    fn record_request(&self, record: RequestRecord, capacity: usize) {
+        let mut recent = self
+            .recent_requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        recent.push_back(record);
+        while recent.len() > capacity {
+            recent.pop_front();
+        }
+    }
+
     /// Start the proxy server on the given port
     pub async fn start(self: &Arc<Self>, port: u16) -> Result<(), ProxyError> {
         if self.is_running.load(Ordering::SeqCst) {
@@ -107,38 +347,41 @@ impl ProxyServer {
         // Create HTTP client based on global proxy settings
         let config = self.store.get_config().await;
         let http_client = create_http_client(&config);
+        if config.app.reset_stats_on_start {
+            self.reset_stats();
+        }
 
-        // Setup CORS
-        let cors = CorsLayer::new()
-            .allow_origin(Any)
-            .allow_methods([
-                Method::GET,
-                Method::POST,
-                Method::PUT,
-                Method::DELETE,
-                Method::OPTIONS,
-            ])
-            .allow_headers(Any);
+        // CORS is fixed for the lifetime of the server; origin changes apply on restart
+        let cors = cors_layer(&config.app.cors_allowed_origins);
 
         // Build the router
         let app_state = AppState {
             server: Arc::clone(self),
             http_client,
+            auth_ctx: AgentAuthContext::new(self.store.clone()),
+            access_log: Some(AccessLogWriter::spawn(access_log_path(
+                self.store.config_dir(),
+            ))),
+            // Like CORS, the route prefix is fixed until the server restarts
+            route_prefix: config.app.route_prefix.clone(),
         };
 
-        let app = Router::new()
-            .route("/", any(health_check))
-            .route("/health", any(health_check))
-            .route("/api/openai/{*path}", any(openai_proxy_handler))
-            .route("/api/anthropic/{*path}", any(anthropic_proxy_handler))
-            .route("/api/{*path}", any(generic_proxy_handler))
-            .layer(cors)
-            .with_state(app_state);
+        let app = proxy_router(app_state, cors);
 
         // Bind to the address
-        let listener = tokio::net::TcpListener::bind(addr)
-            .await
-            .map_err(|e| ProxyError::BindFailed(format!("Failed to bind to {}: {}", addr, e)))?;
+        let listener = match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                let holder = match find_listening_process(port).await {
+                    Some(process) => format!(" (port in use by {})", process),
+                    None => String::new(),
+                };
+                return Err(ProxyError::BindFailed(format!(
+                    "Failed to bind to {}: {}{}",
+                    addr, e, holder
+                )));
+            }
+        };
 
         self.port.store(port as u64, Ordering::SeqCst);
         self.is_running.store(true, Ordering::SeqCst);
@@ -182,18 +425,167 @@ impl ProxyServer {
     }
 }
 
+/// Check whether `port` can be bound on localhost by binding and immediately releasing it.
+/// When it is taken, tries to name the process holding it.
+pub async fn check_port_available(port: u16) -> PortAvailability {
+    let available = tokio::net::TcpListener::bind(("127.0.0.1", port))
+        .await
+        .is_ok();
+    let conflicting_process = if available {
+        None
+    } else {
+        find_listening_process(port).await
+    };
+    PortAvailability {
+        port,
+        available,
+        conflicting_process,
+    }
+}
+
+/// Best-effort lookup of the process listening on a TCP port
+#[cfg(unix)]
+async fn find_listening_process(port: u16) -> Option<String> {
+    let output = tokio::process::Command::new("lsof")
+        .args(["-nP", &format!("-iTCP:{}", port), "-sTCP:LISTEN", "-Fpc"])
+        .output()
+        .await
+        .ok()?;
+    parse_lsof_process(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Best-effort lookup of the process listening on a TCP port
+#[cfg(windows)]
+async fn find_listening_process(port: u16) -> Option<String> {
+    let output = tokio::process::Command::new("netstat")
+        .args(["-ano", "-p", "TCP"])
+        .output()
+        .await
+        .ok()?;
+    let suffix = format!(":{}", port);
+    let pid = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .find(|cols| cols.len() >= 5 && cols[1].ends_with(&suffix) && cols[3] == "LISTENING")
+        .map(|cols| cols[4].to_string())?;
+
+    let output = tokio::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+        .output()
+        .await
+        .ok()?;
+    let name = String::from_utf8_lossy(&output.stdout)
+        .split(',')
+        .next()
+        .map(|n| n.trim().trim_matches('"').to_string())
+        .filter(|n| !n.is_empty())?;
+    Some(format!("{} (pid {})", name, pid))
+}
+
+/// Extract `name (pid N)` from `lsof -Fpc` output (`p<pid>` and `c<command>` lines)
+#[cfg(unix)]
+fn parse_lsof_process(output: &str) -> Option<String> {
+    let pid = output.lines().find_map(|l| l.strip_prefix('p'))?;
+    let name = output.lines().find_map(|l| l.strip_prefix('c'))?;
+    Some(format!("{} (pid {})", name, pid))
+}
+
+/// Measures consecutive pipeline stages of a proxied request
+struct StageTimer {
+    start: Instant,
+    last: Instant,
+}
+
+impl StageTimer {
+    fn start() -> Self {
+        let now = Instant::now();
+        Self { start: now, last: now }
+    }
+
+    /// Milliseconds since the previous lap (or start)
+    fn lap(&mut self) -> f64 {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last);
+        self.last = now;
+        elapsed.as_secs_f64() * 1000.0
+    }
+
+    /// Milliseconds since start, measured at the last lap so stages sum to the total
+    fn total(&self) -> f64 {
+        self.last.duration_since(self.start).as_secs_f64() * 1000.0
+    }
+}
+
+/// Per-provider semaphores enforcing `Provider::max_concurrent_requests`
+#[derive(Default)]
+struct ProviderLimiters {
+    semaphores: Mutex<HashMap<String, (u32, Arc<Semaphore>)>>,
+}
+
+impl ProviderLimiters {
+    /// Get the semaphore for a provider, or None if the provider is unlimited.
+    /// A changed limit replaces the semaphore; in-flight permits of the old one drain naturally.
+    fn semaphore_for(&self, provider: &Provider) -> Option<Arc<Semaphore>> {
+        let limit = provider.max_concurrent_requests.filter(|l| *l > 0)?;
+        let mut semaphores = self.semaphores.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = semaphores
+            .entry(provider.id.clone())
+            .or_insert_with(|| (limit, Arc::new(Semaphore::new(limit as usize))));
+        if entry.0 != limit {
+            *entry = (limit, Arc::new(Semaphore::new(limit as usize)));
+        }
+        Some(entry.1.clone())
+    }
+}
+
+/// Wait up to `timeout` for a provider slot. Returns `Err` with a 429 response when none frees up.
+async fn acquire_provider_permit(
+    semaphore: Option<Arc<Semaphore>>,
+    provider_name: &str,
+    timeout: Duration,
+) -> Result<Option<OwnedSemaphorePermit>, Response<Body>> {
+    let Some(semaphore) = semaphore else {
+        return Ok(None);
+    };
+
+    match tokio::time::timeout(timeout, semaphore.acquire_owned()).await {
+        Ok(Ok(permit)) => Ok(Some(permit)),
+        _ => {
+            tracing::warn!(
+                "Concurrency limit reached for provider {}, rejecting request",
+                provider_name
+            );
+            Err(error_response(
+                StatusCode::TOO_MANY_REQUESTS,
+                &format!(
+                    "Too many concurrent requests to provider {}. Please retry later.",
+                    provider_name
+                ),
+            ))
+        }
+    }
+}
+
 #[derive(Clone)]
 struct AppState {
     server: Arc<ProxyServer>,
     /// HTTP client with global proxy settings
     http_client: Client,
+    /// Access to agent OAuth tokens for providers backed by an agent account
+    auth_ctx: AgentAuthContext,
+    /// Writes ~/.vibemate/access.jsonl when `access_log` is enabled; None for replays
+    access_log: Option<AccessLogWriter>,
+    /// `app.route_prefix` the routes were built with ("" for none)
+    route_prefix: String,
 }
 
+/// Client headers the proxy never copies upstream; Content-Type is set by the forwarder
 fn should_skip_request_header(name: &header::HeaderName) -> bool {
     matches!(
         name,
         &header::HOST
             | &header::AUTHORIZATION
+            | &header::CONTENT_TYPE
             | &header::CONTENT_LENGTH
             | &header::TRANSFER_ENCODING
             | &header::CONNECTION
@@ -201,6 +593,73 @@ fn should_skip_request_header(name: &header::HeaderName) -> bool {
     )
 }
 
+/// API routes are mounted under `app_state.route_prefix`; the health check also stays at
+/// the root so local probes keep working
+fn proxy_router(app_state: AppState, cors: CorsLayer) -> Router {
+    let prefix = app_state.route_prefix.clone();
+    let mut router = Router::new()
+        .route("/", any(health_check))
+        .route("/health", any(health_check));
+    if !prefix.is_empty() {
+        router = router.route(&format!("{prefix}/health"), any(health_check));
+    }
+    // More specific than the OpenAI wildcard route; non-upgrade requests are proxied as usual
+    for path in REALTIME_PATHS {
+        router = router.route(
+            &format!("{prefix}{OPENAI_API_PREFIX}{path}"),
+            any(openai_realtime_handler),
+        );
+    }
+    router
+        .route(&format!("{prefix}{OPENAI_API_PREFIX}/{{*path}}"), any(openai_proxy_handler))
+        .route(&format!("{prefix}{ANTHROPIC_API_PREFIX}/{{*path}}"), any(anthropic_proxy_handler))
+        .route(&format!("{prefix}{GENERIC_API_PREFIX}/{{*path}}"), any(generic_proxy_handler))
+        .layer(cors)
+        .with_state(app_state)
+}
+
+/// CORS layer for the proxy. No origins, or `*` among them, allows any origin; otherwise
+/// only the listed exact origins are allowed.
+fn cors_layer(allowed_origins: &[String]) -> CorsLayer {
+    let allow_origin = if allowed_origins.is_empty() || allowed_origins.iter().any(|o| o == "*") {
+        AllowOrigin::from(Any)
+    } else {
+        let origins: Vec<HeaderValue> = allowed_origins
+            .iter()
+            .filter_map(|origin| match HeaderValue::from_str(origin) {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    tracing::warn!("Ignoring invalid CORS origin: {}", origin);
+                    None
+                }
+            })
+            .collect();
+        AllowOrigin::list(origins)
+    };
+
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
+        .allow_headers(Any)
+}
+
+/// Reply to an `OPTIONS` request without contacting the provider. The CORS layer normally
+/// answers these first; handlers still guard against forwarding them so an `OPTIONS` never
+/// counts as a proxied request or reaches upstream.
+fn options_response() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::ALLOW, "GET, POST, PUT, DELETE, OPTIONS")
+        .body(Body::empty())
+        .unwrap()
+}
+
 /// Health check endpoint
 async fn health_check() -> Response<Body> {
     Response::builder()
@@ -215,7 +674,7 @@ async fn generic_proxy_handler(
     State(state): State<AppState>,
     req: Request<Body>,
 ) -> Result<Response<Body>, StatusCode> {
-    proxy_handler_inner(state, req, "/api", ApiGroup::Generic, true).await
+    proxy_handler_inner(state, req, ApiGroup::Generic).await
 }
 
 /// OpenAI compatible API proxy handler
@@ -223,7 +682,7 @@ async fn openai_proxy_handler(
     State(state): State<AppState>,
     req: Request<Body>,
 ) -> Result<Response<Body>, StatusCode> {
-    proxy_handler_inner(state, req, "/api/openai", ApiGroup::OpenAI, true).await
+    proxy_handler_inner(state, req, ApiGroup::OpenAI).await
 }
 
 /// Anthropic API proxy handler
@@ -231,20 +690,486 @@ async fn anthropic_proxy_handler(
     State(state): State<AppState>,
     req: Request<Body>,
 ) -> Result<Response<Body>, StatusCode> {
-    proxy_handler_inner(state, req, "/api/anthropic", ApiGroup::Anthropic, false).await
+    proxy_handler_inner(state, req, ApiGroup::Anthropic).await
+}
+
+/// OpenAI paths served as a WebSocket passthrough (OpenAI Realtime)
+const REALTIME_PATHS: &[&str] = &["/realtime", "/v1/realtime"];
+
+/// Realtime API paths: WebSocket upgrades are relayed frame by frame, while plain HTTP
+/// requests (e.g. a WebRTC client posting its SDP offer) are proxied like any other
+async fn openai_realtime_handler(
+    State(state): State<AppState>,
+    upgrade: Result<WebSocketUpgrade, WebSocketUpgradeRejection>,
+    req: Request<Body>,
+) -> Result<Response<Body>, StatusCode> {
+    match upgrade {
+        Ok(upgrade) => {
+            let (parts, _) = req.into_parts();
+            Ok(openai_realtime_websocket(state, upgrade, parts.uri, parts.headers).await)
+        }
+        Err(_) => proxy_handler_inner(state, req, ApiGroup::OpenAI).await,
+    }
+}
+
+/// WebSocket passthrough for realtime APIs. Routes by path rules, or by the `model` query
+/// parameter against model rules, connects upstream with the provider's credentials before
+/// accepting the client upgrade, then relays frames until either side closes.
+/// The upstream connection is always direct, so sessions whose provider would be reached
+/// through the outbound proxy are refused rather than silently bypassing it.
+async fn openai_realtime_websocket(
+    state: AppState,
+    upgrade: WebSocketUpgrade,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response<Body> {
+    state.server.increment_request_count();
+    if state.server.is_paused() {
+        return error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Vibe Mate proxy is paused; resume it to forward requests",
+        );
+    }
+
+    let config = state.server.config_store().get_config().await;
+    let uri_path = uri.path();
+    let full_path = uri_path
+        .strip_prefix(state.route_prefix.as_str())
+        .unwrap_or(uri_path);
+    let path = full_path.strip_prefix(OPENAI_API_PREFIX).unwrap_or(full_path);
+    let requested_model = query_param(uri.query(), "model");
+    let (model_name, _) = resolve_model_alias(&config.app.model_aliases, requested_model.clone());
+
+    let exhausted = without_exhausted_providers(&config, &state.server.spend);
+    let routing_config = exhausted.as_ref().unwrap_or(&config);
+    let resolved =
+        resolve_provider(routing_config, ApiGroup::OpenAI, full_path, model_name.as_deref(), None);
+    let Some(mut resolved) = resolved else {
+        if exhausted.is_some() {
+            return error_response(StatusCode::PAYMENT_REQUIRED, SPEND_LIMIT_EXHAUSTED);
+        }
+        return error_response(
+            StatusCode::BAD_GATEWAY,
+            "No provider configured. Please add a provider in Vibe Mate settings.",
+        );
+    };
+    state.server.config_store().resolve_provider_secret(&mut resolved.provider).await;
+    let Some(base_url) = resolved.provider.api_base_url.clone() else {
+        return error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Provider has no API base URL configured",
+        );
+    };
+
+    // Aliases and rule rewrites apply to the model in the query string
+    let query = match uri.query() {
+        Some(query) if requested_model.as_deref() != Some(resolved.final_model.as_str()) => {
+            set_query_param(query, "model", &resolved.final_model)
+        }
+        query => query.unwrap_or_default().to_string(),
+    };
+    let target_url = realtime_target_url(&base_url, path, &query);
+    if realtime_needs_proxy(&config.app, &target_url) {
+        return error_response(
+            StatusCode::BAD_GATEWAY,
+            "Realtime WebSocket sessions can't go through the outbound proxy; \
+             add the provider's host to No Proxy or set the proxy mode to None",
+        );
+    }
+
+    let request =
+        realtime_upstream_request(&state, &config.app, &resolved, &headers, &target_url).await;
+    let request = match request {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+    let connect_timeout = Duration::from_secs(config.app.connect_timeout_secs.max(1));
+    let connected =
+        tokio::time::timeout(connect_timeout, tokio_tungstenite::connect_async(request)).await;
+    let (upstream, upstream_response) = match connected {
+        Ok(Ok(connected)) => connected,
+        Ok(Err(e)) => {
+            tracing::warn!("Realtime connection to {} failed: {}", resolved.provider.name, e);
+            return error_response(
+                StatusCode::BAD_GATEWAY,
+                &format!("Failed to connect to provider: {}", e),
+            );
+        }
+        Err(_) => {
+            tracing::warn!("Realtime connection to {} timed out", resolved.provider.name);
+            return error_response(StatusCode::GATEWAY_TIMEOUT, "Timed out connecting to provider");
+        }
+    };
+    tracing::info!(
+        "Realtime session routed to provider {} ({})",
+        resolved.provider.name,
+        target_url
+    );
+
+    // Echo the subprotocol upstream picked from the client's offer
+    let upgrade = match upstream_response
+        .headers()
+        .get(header::SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|v| v.to_str().ok())
+    {
+        Some(protocol) => upgrade.protocols([protocol.to_string()]),
+        None => upgrade,
+    };
+    let provider_name = resolved.provider.name;
+    upgrade.on_upgrade(move |client| async move {
+        relay_websocket(client, upstream).await;
+        tracing::info!("Realtime session with provider {} closed", provider_name);
+    })
+}
+
+/// Handshake request for the upstream WebSocket: the client's headers (minus its own
+/// handshake), the rule's headers and the provider's credentials, as for HTTP requests
+async fn realtime_upstream_request(
+    state: &AppState,
+    app: &AppConfig,
+    resolved: &ResolvedProvider,
+    client_headers: &HeaderMap,
+    target_url: &str,
+) -> Result<tungstenite::handshake::client::Request, Response<Body>> {
+    let mut client_headers = client_headers.clone();
+    for name in [
+        header::UPGRADE,
+        header::SEC_WEBSOCKET_KEY,
+        header::SEC_WEBSOCKET_VERSION,
+        header::SEC_WEBSOCKET_EXTENSIONS,
+    ] {
+        client_headers.remove(name);
+    }
+
+    // Build the headers on a throwaway HTTP request to share the auth logic
+    let http_url = target_url.replacen("ws", "http", 1);
+    let user_agent = upstream_user_agent(&resolved.provider, app);
+    let req = state.http_client.get(http_url);
+    let req = apply_request_headers(req, &client_headers, resolved, user_agent);
+    let req = add_upstream_auth(state, req, resolved, &client_headers).await?;
+    let invalid = |e: &dyn std::fmt::Display| {
+        error_response(
+            StatusCode::BAD_GATEWAY,
+            &format!("Invalid upstream WebSocket request: {}", e),
+        )
+    };
+    let headers = req.build().map_err(|e| invalid(&e))?.headers().clone();
+
+    let mut request = target_url.into_client_request().map_err(|e| invalid(&e))?;
+    request.headers_mut().extend(headers);
+    Ok(request)
+}
+
+/// Whether `proxy_mode` would send a request to `target_url` through a proxy. System
+/// proxies can't be inspected here, so that mode always counts.
+fn realtime_needs_proxy(app: &AppConfig, target_url: &str) -> bool {
+    match app.proxy_mode {
+        ProxyMode::None => false,
+        ProxyMode::System => true,
+        ProxyMode::Custom => {
+            let host = target_url.parse::<Uri>().ok();
+            let host = host.as_ref().and_then(Uri::host).unwrap_or_default();
+            app.proxy_url.is_some() && !no_proxy_matches(&app.effective_no_proxy(), host)
+        }
+    }
+}
+
+/// `no_proxy` matching: `*`, the host itself, or a domain the host is under
+fn no_proxy_matches(entries: &[String], host: &str) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']').to_ascii_lowercase();
+    entries.iter().any(|entry| {
+        let entry = entry.trim().to_ascii_lowercase();
+        let domain = entry.trim_start_matches("*.").trim_start_matches('.');
+        entry == "*" || host == domain || host.ends_with(&format!(".{}", domain))
+    })
+}
+
+/// `ws(s)://` URL for a realtime path on the provider, collapsing a duplicated `/v1`
+fn realtime_target_url(base_url: &str, path: &str, query: &str) -> String {
+    let base_url = base_url.trim_end_matches('/');
+    let base_url = match base_url.split_once("://") {
+        Some(("https", rest)) => format!("wss://{}", rest),
+        Some(("http", rest)) => format!("ws://{}", rest),
+        _ => base_url.to_string(),
+    };
+    let path = if base_url.ends_with("/v1") {
+        path.strip_prefix("/v1").unwrap_or(path)
+    } else {
+        path
+    };
+    if query.is_empty() {
+        format!("{}{}", base_url, path)
+    } else {
+        format!("{}{}?{}", base_url, path, query)
+    }
+}
+
+/// Raw value of a query parameter
+fn query_param(query: Option<&str>, key: &str) -> Option<String> {
+    query?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == key)
+        .map(|(_, value)| value.to_string())
+}
+
+/// Query string with `key` set to `value`, keeping the other parameters and their order
+fn set_query_param(query: &str, key: &str, value: &str) -> String {
+    let mut found = false;
+    let mut pairs: Vec<String> = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            if pair.split_once('=').map_or(pair, |(name, _)| name) == key {
+                found = true;
+                format!("{}={}", key, value)
+            } else {
+                pair.to_string()
+            }
+        })
+        .collect();
+    if !found {
+        pairs.push(format!("{}={}", key, value));
+    }
+    pairs.join("&")
+}
+
+type UpstreamSocket =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Relay frames between the client and upstream until either side closes. Pings are
+/// answered on each hop by the WebSocket libraries, so they aren't relayed.
+async fn relay_websocket(client: WebSocket, upstream: UpstreamSocket) {
+    let (mut client_tx, mut client_rx) = client.split();
+    let (mut upstream_tx, mut upstream_rx) = upstream.split();
+
+    let client_to_upstream = async {
+        while let Some(Ok(message)) = client_rx.next().await {
+            let message = match message {
+                ws::Message::Text(text) => tungstenite::Message::text(text.as_str()),
+                ws::Message::Binary(data) => tungstenite::Message::Binary(data),
+                ws::Message::Close(frame) => tungstenite::Message::Close(frame.map(|frame| {
+                    tungstenite::protocol::CloseFrame {
+                        code: CloseCode::from(frame.code),
+                        reason: frame.reason.as_str().into(),
+                    }
+                })),
+                ws::Message::Ping(_) | ws::Message::Pong(_) => continue,
+            };
+            if upstream_tx.send(message).await.is_err() {
+                break;
+            }
+        }
+        let _ = upstream_tx.close().await;
+    };
+    let upstream_to_client = async {
+        while let Some(Ok(message)) = upstream_rx.next().await {
+            let message = match message {
+                tungstenite::Message::Text(text) => ws::Message::Text(text.as_str().into()),
+                tungstenite::Message::Binary(data) => ws::Message::Binary(data),
+                tungstenite::Message::Close(frame) => {
+                    ws::Message::Close(frame.map(|frame| ws::CloseFrame {
+                        code: frame.code.into(),
+                        reason: frame.reason.as_str().into(),
+                    }))
+                }
+                tungstenite::Message::Ping(_)
+                | tungstenite::Message::Pong(_)
+                | tungstenite::Message::Frame(_) => continue,
+            };
+            if client_tx.send(message).await.is_err() {
+                break;
+            }
+        }
+        let _ = client_tx.close().await;
+    };
+
+    tokio::select! {
+        _ = client_to_upstream => {}
+        _ = upstream_to_client => {}
+    }
 }
 
-/// Shared proxy handler logic parameterized by path prefix, API group, and v1 dedup behavior
+/// Path prefixes the API groups are mounted under (after `app.route_prefix`)
+pub const OPENAI_API_PREFIX: &str = "/api/openai";
+pub const ANTHROPIC_API_PREFIX: &str = "/api/anthropic";
+pub const GENERIC_API_PREFIX: &str = "/api";
+
+/// Base URL of the local proxy for a path prefix, e.g. `http://localhost:12345/api/openai`
+pub fn proxy_url(port: u16, route_prefix: &str, prefix: &str) -> String {
+    format!("http://localhost:{}{}{}", port, route_prefix, prefix)
+}
+
+/// Path prefix each API group is mounted under, and whether a `/v1` duplicated between the
+/// provider base URL and the request path is collapsed
+fn route_for(api_group: &ApiGroup) -> (&'static str, bool) {
+    match api_group {
+        ApiGroup::OpenAI => (OPENAI_API_PREFIX, true),
+        ApiGroup::Anthropic => (ANTHROPIC_API_PREFIX, false),
+        ApiGroup::Generic => (GENERIC_API_PREFIX, true),
+    }
+}
+
+/// What the proxy learned about a request while forwarding it, for access logging
+/// and the recent requests buffer
+#[derive(Default)]
+struct RequestOutcome {
+    access_log: bool,
+    recent_requests_capacity: usize,
+    /// Keep the request body in `captured_request` (set by `capture_next_request`)
+    capture_body: bool,
+    provider: Option<String>,
+    model: Option<String>,
+    rule: Option<String>,
+    fallback: bool,
+    request_bytes: u64,
+    timings: Option<RequestTimings>,
+    captured_request: Option<String>,
+}
+
+/// Shared proxy handler logic for all API groups.
+/// Every completed request, including rejected ones, is added to the recent requests buffer
+/// and gets an access log entry when enabled.
 async fn proxy_handler_inner(
     state: AppState,
     req: Request<Body>,
-    prefix: &str,
     api_group: ApiGroup,
-    dedup_v1: bool,
 ) -> Result<Response<Body>, StatusCode> {
+    if req.method() == Method::OPTIONS {
+        return Ok(options_response());
+    }
     state.server.increment_request_count();
+    let started = Instant::now();
+    let timestamp = Utc::now();
+    let method = req.method().to_string();
+    let path = redacted_path_and_query(req.uri().path(), req.uri().query());
+    let mut outcome = RequestOutcome {
+        capture_body: state.server.take_capture_next(),
+        ..RequestOutcome::default()
+    };
+    let replay_uri = req.uri().path_and_query().map(|pq| pq.to_string());
+    let request_content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    let mut result = forward_request(&state, req, api_group.clone(), None, &mut outcome).await;
+    let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    let (status, response_bytes) = match &result {
+        // Streaming bodies have no exact size
+        Ok(response) => (response.status().as_u16(), response.body().size_hint().exact()),
+        Err(status) => (status.as_u16(), None),
+    };
+
+    let mut captured_response = None;
+    if outcome.captured_request.is_some() {
+        if let Ok(response) = result {
+            let (response, body) = capture_response_body(response).await;
+            result = Ok(response);
+            captured_response = body;
+        }
+    }
+
+    if outcome.recent_requests_capacity > 0 {
+        let captured = outcome.captured_request.is_some();
+        state.server.record_request(
+            RequestRecord {
+                id: uuid::Uuid::new_v4().to_string(),
+                timestamp,
+                method: method.clone(),
+                path: path.clone(),
+                api_group: api_group.clone(),
+                provider: outcome.provider.clone(),
+                model: outcome.model.clone(),
+                rule: outcome.rule.clone(),
+                fallback: outcome.fallback,
+                status,
+                duration_ms,
+                request_body: outcome.captured_request,
+                response_body: captured_response,
+                replay_uri: replay_uri.filter(|_| captured),
+                request_content_type: request_content_type.filter(|_| captured),
+            },
+            outcome.recent_requests_capacity,
+        );
+    }
+
+    if let Some(access_log) = state.access_log.as_ref().filter(|_| outcome.access_log) {
+        access_log.log(AccessLogEntry {
+            timestamp,
+            method,
+            path,
+            api_group,
+            provider: outcome.provider,
+            model: outcome.model,
+            status,
+            duration_ms,
+            request_bytes: outcome.request_bytes,
+            response_bytes,
+            timings: outcome.timings,
+        });
+    }
+
+    result
+}
+
+/// Buffer a request body, answering 413 when it exceeds `limit` bytes
+async fn read_request_body(body: Body, limit: usize) -> Result<Bytes, Response<Body>> {
+    axum::body::to_bytes(body, limit).await.map_err(|e| {
+        let e = e.into_inner();
+        if e.is::<http_body_util::LengthLimitError>() {
+            tracing::warn!("Rejected request body larger than {} bytes", limit);
+            error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                &format!("Request body exceeds the {} byte limit", limit),
+            )
+        } else {
+            tracing::error!("Failed to read request body: {}", e);
+            error_response(StatusCode::BAD_REQUEST, "Failed to read request body")
+        }
+    })
+}
 
-    let full_path = req.uri().path().to_string();
+/// Buffer a fully sized response body so it can be recorded. Streaming bodies pass through
+/// untouched and are not captured.
+async fn capture_response_body(response: Response<Body>) -> (Response<Body>, Option<String>) {
+    if response.body().size_hint().exact().is_none() {
+        return (response, None);
+    }
+    let (parts, body) = response.into_parts();
+    match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => {
+            let text = String::from_utf8_lossy(&bytes).into_owned();
+            (Response::from_parts(parts, Body::from(bytes)), Some(text))
+        }
+        Err(e) => {
+            tracing::warn!("Failed to capture response body: {}", e);
+            (Response::from_parts(parts, Body::empty()), None)
+        }
+    }
+}
+
+/// Route a request to its provider and relay the response. `provider_override` bypasses the
+/// routing rules and sends the request to that provider as is.
+async fn forward_request(
+    state: &AppState,
+    req: Request<Body>,
+    api_group: ApiGroup,
+    provider_override: Option<&str>,
+    outcome: &mut RequestOutcome,
+) -> Result<Response<Body>, StatusCode> {
+    let (prefix, dedup_v1) = route_for(&api_group);
+    let mut timer = StageTimer::start();
+    let mut timings = RequestTimings::default();
+
+    // Path rules match the path without the route prefix, so they survive prefix changes
+    let uri_path = req.uri().path();
+    let full_path = uri_path
+        .strip_prefix(state.route_prefix.as_str())
+        .unwrap_or(uri_path)
+        .to_string();
     let path = full_path
         .strip_prefix(prefix)
         .unwrap_or(&full_path)
@@ -259,31 +1184,77 @@ async fn proxy_handler_inner(
         full_path
     );
 
-    // Read the request body
+    let config = state.server.config_store().get_config().await;
+
+    // Read the request body, up to the configured limit
     let (parts, body) = req.into_parts();
-    let body_bytes = match axum::body::to_bytes(body, usize::MAX).await {
+    let body_bytes = match read_request_body(body, config.app.max_request_body_bytes).await {
         Ok(bytes) => bytes,
-        Err(e) => {
-            tracing::error!("Failed to read request body: {}", e);
-            return Err(StatusCode::BAD_REQUEST);
-        }
+        Err(response) => return Ok(response),
     };
+    timings.body_read_ms = timer.lap();
+    outcome.request_bytes = body_bytes.len() as u64;
 
     // Extract model from request body
-    let model_name = extract_model_from_body(&body_bytes);
+    outcome.access_log = config.app.access_log;
+    outcome.recent_requests_capacity = config.app.recent_requests_capacity;
+    if outcome.capture_body {
+        outcome.captured_request = Some(String::from_utf8_lossy(&body_bytes).into_owned());
+    }
+    if state.server.is_paused() {
+        return Ok(error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Vibe Mate proxy is paused; resume it to forward requests",
+        ));
+    }
+    // Multipart uploads, forms and other non-JSON bodies are forwarded byte for byte;
+    // they route by path rules and the default provider only
+    let json_body = is_json_request(&parts.headers);
+    let requested_model = if json_body {
+        extract_model_from_body(&body_bytes)
+    } else {
+        tracing::debug!(
+            "Skipping model extraction for {:?} body",
+            parts.headers.get(header::CONTENT_TYPE)
+        );
+        None
+    };
 
-    tracing::debug!("Request model: {:?}", model_name);
+    // Resolve model aliases before routing so rules see the real model name
+    let (model_name, alias_applied) =
+        resolve_model_alias(&config.app.model_aliases, requested_model);
 
-    // Get config and find the matching provider
-    let config = state.server.config_store().get_config().await;
+    tracing::debug!("Request model: {:?}", model_name);
 
-    let resolved = match resolve_provider(
-        &config,
-        api_group,
-        &full_path,
-        model_name.as_deref(),
-    ) {
+    // Find the matching provider; providers over their spend limit are skipped like
+    // disabled ones
+    let exhausted = without_exhausted_providers(&config, &state.server.spend);
+    let resolved = match provider_override {
+        Some(id) => override_provider(&config, id, model_name.as_deref()),
+        None => {
+            let routing_config = exhausted.as_ref().unwrap_or(&config);
+            let sticky_key = config
+                .app
+                .sticky_sessions
+                .then(|| parts.headers.get(config.app.sticky_session_header.as_str()))
+                .flatten()
+                .and_then(|v| v.to_str().ok())
+                .filter(|v| !v.is_empty());
+            resolve_provider(
+                routing_config,
+                api_group,
+                &full_path,
+                model_name.as_deref(),
+                sticky_key,
+            )
+        }
+    };
+    let mut resolved = match resolved {
         Some(r) => r,
+        None if exhausted.is_some() => {
+            tracing::warn!("Every provider for model {:?} reached its spend limit", model_name);
+            return Ok(error_response(StatusCode::PAYMENT_REQUIRED, SPEND_LIMIT_EXHAUSTED));
+        }
         None => {
             tracing::error!("No provider found for model: {:?}", model_name);
             return Ok(error_response(
@@ -293,9 +1264,30 @@ async fn proxy_handler_inner(
         }
     };
 
-    // Ensure we have a valid API base URL
-    let api_base_url = match resolved.provider.api_base_url.as_ref() {
-        Some(url) => url,
+    // Keys kept in secrets.json are only read at request time
+    state.server.config_store().resolve_provider_secret(&mut resolved.provider).await;
+
+    outcome.provider = Some(resolved.provider.name.clone());
+    outcome.model = Some(resolved.final_model.clone()).filter(|m| !m.is_empty());
+    outcome.rule = Some(resolved.rule_label().to_string());
+    outcome.fallback = resolved.fallback;
+
+    // 402 lets clients tell an exhausted budget apart from upstream rate limiting. Only an
+    // explicitly targeted provider can still be over its limit here.
+    if state.server.spend.limit_reached(&resolved.provider) {
+        tracing::warn!("Provider {} reached its spend limit", resolved.provider.name);
+        return Ok(error_response(
+            StatusCode::PAYMENT_REQUIRED,
+            &format!(
+                "Provider {} reached its spend limit for this period",
+                resolved.provider.name
+            ),
+        ));
+    }
+
+    // Ensure we have a valid API base URL
+    let api_base_url = match resolved.provider.api_base_url.as_ref() {
+        Some(url) => url,
         None => {
             return Ok(error_response(
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -305,6 +1297,8 @@ async fn proxy_handler_inner(
     };
 
     tracing::info!(
+        rule = resolved.rule_label(),
+        fallback = resolved.fallback,
         "Routing to provider: {} ({}), model: {} -> {}",
         resolved.provider.name,
         api_base_url,
@@ -312,16 +1306,48 @@ async fn proxy_handler_inner(
         resolved.final_model
     );
 
+    timings.routing_ms = timer.lap();
+
+    if resolved.translate && is_streaming_request(&body_bytes) {
+        return Ok(error_response(
+            StatusCode::BAD_REQUEST,
+            "Streaming is not supported yet when translating requests to Gemini",
+        ));
+    }
+    // Translation targets generateContent, which would turn a token count into a billed
+    // generation
+    if resolved.translate && path == ANTHROPIC_COUNT_TOKENS_PATH {
+        return Ok(error_response(
+            StatusCode::BAD_REQUEST,
+            "Token counting is not supported when translating requests to Gemini",
+        ));
+    }
+
     // Build the target URL
     let base_url = api_base_url.trim_end_matches('/');
-    let target_url = if dedup_v1 && base_url.ends_with("/v1") && path.starts_with("/v1") {
+    let target_url = if resolved.translate {
+        gemini_generate_url(base_url, &resolved.final_model)
+    } else if resolved.provider.provider_type == ProviderType::Azure {
+        azure_target_url(base_url, &resolved.provider, &resolved.final_model, &path)
+    } else if dedup_v1 && base_url.ends_with("/v1") && path.starts_with("/v1") {
         format!("{}{}", base_url, &path[3..])
     } else {
         format!("{}{}", base_url, path)
     };
 
-    // Prepare the request body (potentially rewrite the model)
-    let final_body = if resolved.model_rewritten {
+    // Prepare the request body (translate it, or potentially rewrite the model)
+    let rewrite_model = json_body && (resolved.model_rewritten || alias_applied);
+    let final_body = if resolved.translate {
+        match anthropic_to_gemini_request(&body_bytes) {
+            Ok(body) => body,
+            Err(e) => {
+                return Ok(error_response(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Failed to translate request for Gemini: {}", e),
+                ));
+            }
+        }
+    } else if rewrite_model {
         rewrite_model_in_body(&body_bytes, &resolved.final_model)
     } else {
         body_bytes.to_vec()
@@ -330,27 +1356,63 @@ async fn proxy_handler_inner(
     // Build the outgoing request
     let mut outgoing_req = state.http_client.request(method.clone(), &target_url);
 
-    // Copy headers, skipping hop-by-hop and auth headers
-    for (key, value) in parts.headers.iter() {
-        if should_skip_request_header(key) {
-            continue;
-        }
-        if let Ok(v) = value.to_str() {
-            outgoing_req = outgoing_req.header(key.as_str(), v);
-        }
+    // Copy client headers and apply the rule's injected headers
+    let user_agent = upstream_user_agent(&resolved.provider, &config.app);
+    outgoing_req = apply_request_headers(outgoing_req, &parts.headers, &resolved, user_agent);
+
+    // A fresh key per client request (unless it brought its own); retries clone the
+    // built request and so resend the same key
+    if resolved.provider.idempotency_keys && !parts.headers.contains_key(IDEMPOTENCY_KEY_HEADER) {
+        let key = uuid::Uuid::new_v4().to_string();
+        outgoing_req = outgoing_req.header(IDEMPOTENCY_KEY_HEADER, key);
     }
 
-    // Add the API key based on provider type
-    outgoing_req = add_auth_header(outgoing_req, &resolved.provider);
+    // Add the API key (or agent OAuth token) based on provider type
+    outgoing_req = match add_upstream_auth(state, outgoing_req, &resolved, &parts.headers).await {
+        Ok(req) => req,
+        Err(response) => return Ok(response),
+    };
 
-    // Set content type and body
+    // Bodies the proxy produced are JSON; anything else keeps the client's type (and so
+    // its multipart boundary), defaulting to JSON when the client sent none
+    let content_type = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .filter(|_| !resolved.translate && !rewrite_model)
+        .cloned()
+        .unwrap_or_else(|| HeaderValue::from_static("application/json"));
     outgoing_req = outgoing_req
-        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::CONTENT_TYPE, content_type)
         .body(final_body);
+    timings.body_transform_ms = timer.lap();
+
+    // Hold a provider slot until the response (including any stream) completes
+    let permit = match acquire_provider_permit(
+        state.server.provider_limiters.semaphore_for(&resolved.provider),
+        &resolved.provider.name,
+        PROVIDER_QUEUE_TIMEOUT,
+    )
+    .await
+    {
+        Ok(permit) => permit,
+        Err(response) => return Ok(response),
+    };
 
-    // Send the request
+    // Send the request, retrying transient 429/503 responses against the same provider
     tracing::debug!("Sending request to: {}", target_url);
-    let response = match outgoing_req.send().await {
+    let retry = RetryPolicy {
+        max_attempts: config.app.retry_max_attempts,
+        base_delay: Duration::from_millis(config.app.retry_base_delay_ms),
+    };
+    let mut retried_rate_limits = HeaderMap::new();
+    let response = match send_with_retry(
+        outgoing_req,
+        &retry,
+        &target_url,
+        &mut retried_rate_limits,
+    )
+    .await
+    {
         Ok(resp) => {
             tracing::info!("Received response: {} from {}", resp.status(), target_url);
             resp
@@ -371,12 +1433,185 @@ async fn proxy_handler_inner(
         .and_then(|v| v.to_str().ok())
         .map(|ct| ct.contains("text/event-stream"))
         .unwrap_or(false);
+    // Upstream time includes any wait for a provider concurrency slot
+    timings.upstream_ms = timer.lap();
+
+    // Headers added unless the final upstream response already has them: debug info, and
+    // rate-limit state from retried attempts so clients can still back off themselves
+    let mut extra_headers = if config.app.expose_debug_headers {
+        routing_debug_headers(&resolved)
+    } else {
+        HeaderMap::new()
+    };
+    for (key, value) in retried_rate_limits.iter() {
+        extra_headers.insert(key.clone(), value.clone());
+    }
 
-    if is_streaming {
-        handle_streaming_response(response).await
+    let meter = SpendMeter::new(&state.server.spend, &resolved.provider, &resolved.final_model);
+    let result = if resolved.translate {
+        let model = &resolved.final_model;
+        handle_translated_response(response, permit, model, &extra_headers, meter).await
+    } else if is_streaming {
+        let keepalive = Some(Duration::from_secs(config.app.sse_keepalive_secs))
+            .filter(|interval| !interval.is_zero());
+        handle_streaming_response(response, permit, &extra_headers, meter, keepalive).await
     } else {
-        handle_regular_response(response).await
+        let transform = resolved.provider.response_transform.as_ref();
+        handle_regular_response(response, permit, &extra_headers, transform, meter).await
+    };
+    timings.response_ms = timer.lap();
+    timings.total_ms = timer.total();
+
+    tracing::debug!(
+        "Request timings (ms): body_read={:.2} routing={:.2} transform={:.2} upstream={:.2} response={:.2} total={:.2}",
+        timings.body_read_ms,
+        timings.routing_ms,
+        timings.body_transform_ms,
+        timings.upstream_ms,
+        timings.response_ms,
+        timings.total_ms
+    );
+    outcome.timings = Some(timings.clone());
+    state.server.record_timings(timings);
+
+    result
+}
+
+/// Retry settings for transient upstream failures
+struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Exponential backoff before retry number `retry` (1-based)
+    fn backoff(&self, retry: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+    }
+}
+
+/// 429 and 503 mean the upstream did not process the request, so retrying is safe
+/// for any method
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    matches!(
+        status,
+        reqwest::StatusCode::TOO_MANY_REQUESTS | reqwest::StatusCode::SERVICE_UNAVAILABLE
+    )
+}
+
+/// Send an upstream request, retrying 429/503 responses with exponential backoff or the
+/// upstream's `Retry-After`. Connection errors are not retried. The status is known before
+/// any body bytes reach the client, so this is safe for streaming requests too.
+/// Rate-limit headers of discarded attempts are collected into `rate_limits`; later attempts win.
+async fn send_with_retry(
+    req: reqwest::RequestBuilder,
+    policy: &RetryPolicy,
+    target_url: &str,
+    rate_limits: &mut HeaderMap,
+) -> reqwest::Result<reqwest::Response> {
+    let mut attempt = 1;
+    loop {
+        let next = (attempt < policy.max_attempts).then(|| req.try_clone()).flatten();
+        let Some(this_attempt) = next else {
+            return req.send().await;
+        };
+
+        let response = this_attempt.send().await?;
+        if !is_retryable_status(response.status()) {
+            return Ok(response);
+        }
+
+        let delay = response
+            .headers()
+            .get(header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_retry_after)
+            .map(Duration::from_secs)
+            .unwrap_or_else(|| policy.backoff(attempt));
+        if delay > MAX_RETRY_DELAY {
+            tracing::warn!(
+                "Upstream {} returned {}, not retrying (retry after {:?})",
+                target_url,
+                response.status(),
+                delay
+            );
+            return Ok(response);
+        }
+
+        tracing::warn!(
+            "Upstream {} returned {}, retrying in {:?} (attempt {}/{})",
+            target_url,
+            response.status(),
+            delay,
+            attempt + 1,
+            policy.max_attempts
+        );
+        for (key, value) in rate_limit_headers(response.headers()).iter() {
+            rate_limits.insert(key.clone(), value.clone());
+        }
+        drop(response);
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// User-Agent configured for upstream requests: the provider's, else the global one
+fn upstream_user_agent<'a>(provider: &'a Provider, app: &'a AppConfig) -> Option<&'a str> {
+    provider.user_agent.as_deref().or(app.user_agent.as_deref()).filter(|ua| !ua.is_empty())
+}
+
+/// Copy client headers onto the outgoing request, skipping hop-by-hop and auth headers,
+/// then set the matched rule's `add_headers`, which replace any client value of the same name.
+/// A configured `user_agent` replaces the client's User-Agent.
+fn apply_request_headers(
+    mut req: reqwest::RequestBuilder,
+    client_headers: &HeaderMap,
+    resolved: &ResolvedProvider,
+    user_agent: Option<&str>,
+) -> reqwest::RequestBuilder {
+    let uses_oauth = resolved.provider.agent_account.is_some();
+    // Bodies the proxy rewrites, or reads token usage from, must arrive uncompressed
+    let reads_body = resolved.translate
+        || resolved.provider.response_transform.is_some()
+        || resolved.provider.price_for(&resolved.final_model).is_some();
+    for (key, value) in client_headers.iter() {
+        if should_skip_request_header(key) {
+            continue;
+        }
+        // OAuth requests must not leak a client API key, and get a merged beta header below
+        if uses_oauth && (key == "x-api-key" || key == "anthropic-beta") {
+            continue;
+        }
+        // Anthropic-specific headers mean nothing to a translated upstream
+        if resolved.translate && (key == "x-api-key" || key.as_str().starts_with("anthropic-")) {
+            continue;
+        }
+        if reads_body && key == header::ACCEPT_ENCODING {
+            continue;
+        }
+        if user_agent.is_some() && key == header::USER_AGENT {
+            continue;
+        }
+        if resolved
+            .add_headers
+            .keys()
+            .any(|name| name.eq_ignore_ascii_case(key.as_str()))
+        {
+            continue;
+        }
+        if let Ok(v) = value.to_str() {
+            req = req.header(key.as_str(), v);
+        }
+    }
+
+    if let Some(user_agent) = user_agent {
+        req = req.header(header::USER_AGENT, user_agent);
+    }
+    for (name, value) in &resolved.add_headers {
+        req = req.header(name.as_str(), value.as_str());
     }
+    req
 }
 
 /// Resolved provider information
@@ -384,34 +1619,71 @@ struct ResolvedProvider {
     provider: Provider,
     final_model: String,
     model_rewritten: bool,
+    /// Id of the matched routing rule, None when the default provider was used
+    rule_id: Option<String>,
+    /// A rule matched but its provider is missing, so the default provider was used
+    fallback: bool,
+    /// Translate Anthropic bodies to/from Gemini generateContent
+    translate: bool,
+    /// Headers injected by the matched rule
+    add_headers: HashMap<String, String>,
+}
+
+impl ResolvedProvider {
+    /// Matched rule id, or "default" for the default provider
+    fn rule_label(&self) -> &str {
+        self.rule_id.as_deref().unwrap_or("default")
+    }
+
+    /// Replace the model with the provider's `force_model`, if any. Requests without a
+    /// model (e.g. model listings) are left alone.
+    fn with_forced_model(mut self) -> Self {
+        if let Some(forced) = self.provider.force_model.as_ref() {
+            if !self.final_model.is_empty() {
+                self.final_model = forced.clone();
+                self.model_rewritten = true;
+            }
+        }
+        self
+    }
 }
 
-/// Resolve which provider to use based on routing rules and model name
+/// Resolve which provider to use based on routing rules and model name. With a sticky
+/// session key, the rule is picked by hashing the key among the equally-ranked matches.
 fn resolve_provider(
     config: &VibeMateConfig,
     api_group: ApiGroup,
     request_path: &str,
     model_name: Option<&str>,
+    sticky_key: Option<&str>,
 ) -> Option<ResolvedProvider> {
     // If there are no providers, return None
     if config.providers.is_empty() {
         return None;
     }
 
-    // Get enabled routing rules sorted by priority
-    let mut rules: Vec<&RoutingRule> = config.routing_rules.iter().filter(|r| r.enabled).collect();
-    rules.sort_by_key(|r| r.priority);
-
-    let rule = match_rule_for_group(&rules, &api_group, request_path, model_name).or_else(|| {
-        if api_group == ApiGroup::Generic {
-            None
-        } else {
-            match_rule_for_group(&rules, &ApiGroup::Generic, request_path, model_name)
-        }
+    let rule = select_rule(
+        &config.routing_rules,
+        &config.providers,
+        &api_group,
+        request_path,
+        model_name,
+    )
+    .map(|rule| match sticky_key {
+        Some(key) => sticky_rule(config, rule, request_path, model_name, key),
+        None => rule,
     });
 
     if let Some(rule) = rule {
-        if let Some(provider) = config.providers.iter().find(|p| p.id == rule.provider_id) {
+        let provider = config.providers.iter().find(|p| p.id == rule.provider_id);
+        if provider.is_none() {
+            tracing::warn!(
+                "Routing rule {} references missing provider {}, using default provider",
+                rule.id,
+                rule.provider_id
+            );
+        }
+        if let Some(provider) = provider.filter(|p| p.enabled) {
             let final_model = model_name
                 .map(|model| {
                     rule.model_rewrite
@@ -419,72 +1691,272 @@ fn resolve_provider(
                         .unwrap_or_else(|| model.to_string())
                 })
                 .unwrap_or_default();
-            return Some(ResolvedProvider {
-                provider: provider.clone(),
-                final_model,
-                model_rewritten: rule.model_rewrite.is_some() && model_name.is_some(),
-            });
+            return Some(
+                ResolvedProvider {
+                    provider: provider.clone(),
+                    final_model,
+                    model_rewritten: rule.model_rewrite.is_some() && model_name.is_some(),
+                    rule_id: Some(rule.id.clone()),
+                    fallback: false,
+                    translate: rule.translate && translates_to_gemini(&api_group, provider),
+                    add_headers: rule.add_headers.clone(),
+                }
+                .with_forced_model(),
+            );
         }
     }
 
-    // Fall back to default provider
-    let default_provider = config.providers.first()?;
+    // Fall back to the group's default provider, then the global default
+    let default_provider = config.default_provider_for(&api_group)?;
 
-    Some(ResolvedProvider {
-        provider: default_provider.clone(),
-        final_model: model_name.unwrap_or("").to_string(),
-        model_rewritten: false,
-    })
+    Some(
+        ResolvedProvider {
+            provider: default_provider.clone(),
+            final_model: model_name.unwrap_or("").to_string(),
+            model_rewritten: false,
+            rule_id: None,
+            fallback: rule.is_some(),
+            translate: false,
+            add_headers: HashMap::new(),
+        }
+        .with_forced_model(),
+    )
 }
 
-fn match_rule_for_group<'a>(
-    rules: &'a [&RoutingRule],
+/// Copy of `config` in which providers that reached their spend limit are disabled, so
+/// routing moves on to the next rule or the default provider. None when none is over.
+fn without_exhausted_providers(
+    config: &VibeMateConfig,
+    spend: &SpendTracker,
+) -> Option<VibeMateConfig> {
+    if !config.providers.iter().any(|p| spend.limit_reached(p)) {
+        return None;
+    }
+    let mut config = config.clone();
+    for provider in config.providers.iter_mut() {
+        if spend.limit_reached(provider) {
+            provider.enabled = false;
+        }
+    }
+    Some(config)
+}
+
+/// Target a specific provider, skipping routing rules and their model rewrites
+/// (the provider's own `force_model` still applies)
+fn override_provider(
+    config: &VibeMateConfig,
+    provider_id: &str,
+    model_name: Option<&str>,
+) -> Option<ResolvedProvider> {
+    let provider = config.providers.iter().find(|p| p.id == provider_id)?;
+    Some(
+        ResolvedProvider {
+            provider: provider.clone(),
+            final_model: model_name.unwrap_or("").to_string(),
+            model_rewritten: false,
+            rule_id: None,
+            fallback: false,
+            translate: false,
+            add_headers: HashMap::new(),
+        }
+        .with_forced_model(),
+    )
+}
+
+/// Pick the enabled rule that handles a request, falling back to generic rules for
+/// OpenAI/Anthropic requests. Rules pointing at a disabled provider are skipped.
+/// Shared by the proxy and rule previews.
+pub(crate) fn select_rule<'a>(
+    rules: &'a [RoutingRule],
+    providers: &[Provider],
     api_group: &ApiGroup,
     request_path: &str,
     model_name: Option<&str>,
 ) -> Option<&'a RoutingRule> {
-    let mut model_rules: Vec<&RoutingRule> = rules
+    let provider_disabled =
+        |id: &str| providers.iter().any(|p| p.id == id && !p.enabled);
+
+    // Get enabled routing rules sorted by priority
+    let mut rules: Vec<&RoutingRule> = rules
         .iter()
-        .copied()
-        .filter(|r| &r.api_group == api_group && r.rule_type == RuleType::Model)
+        .filter(|r| r.enabled && !provider_disabled(&r.provider_id))
         .collect();
-    model_rules.sort_by_key(|r| r.priority);
+    rules.sort_by_key(|r| r.priority);
 
-    if let Some(model) = model_name {
-        for rule in model_rules {
-            if matches_pattern(&rule.match_pattern, model) {
-                return Some(rule);
-            }
+    match_rule_for_group(&rules, api_group, request_path, model_name).or_else(|| {
+        if *api_group == ApiGroup::Generic {
+            None
+        } else {
+            match_rule_for_group(&rules, &ApiGroup::Generic, request_path, model_name)
         }
-    }
+    })
+}
 
-    let mut path_rules: Vec<&RoutingRule> = rules
+/// Among the rules tied with `selected` (same group, type and priority) that also match
+/// the request, pick one by hashing the session key. Only the hash is used, so a key maps
+/// to the same provider across restarts as long as the rules don't change.
+fn sticky_rule<'a>(
+    config: &'a VibeMateConfig,
+    selected: &'a RoutingRule,
+    request_path: &str,
+    model_name: Option<&str>,
+    key: &str,
+) -> &'a RoutingRule {
+    let candidates: Vec<&RoutingRule> = config
+        .routing_rules
         .iter()
-        .copied()
-        .filter(|r| &r.api_group == api_group && r.rule_type == RuleType::Path)
+        .filter(|r| {
+            r.enabled
+                && r.api_group == selected.api_group
+                && r.rule_type == selected.rule_type
+                && r.priority == selected.priority
+                && config.providers.iter().any(|p| p.id == r.provider_id && p.enabled)
+        })
+        .filter(|r| match r.rule_type {
+            RuleType::Model => model_name.is_some_and(|m| matches_pattern(&r.match_pattern, m)),
+            RuleType::Path => matches_pattern(&r.match_pattern, request_path),
+        })
         .collect();
-    if *api_group == ApiGroup::Generic {
-        path_rules.sort_by_key(|r| (r.match_pattern == "/api/*", r.priority));
-    } else {
-        path_rules.sort_by_key(|r| r.priority);
+    if candidates.len() < 2 {
+        return selected;
+    }
+
+    let digest = Sha256::digest(key.as_bytes());
+    let hash = u64::from_be_bytes(digest[..8].try_into().expect("digest is 32 bytes"));
+    candidates[(hash % candidates.len() as u64) as usize]
+}
+
+/// Whether a request in `api_group` format needs translating for `provider`.
+/// Only Anthropic Messages to Google generateContent is supported so far.
+fn translates_to_gemini(api_group: &ApiGroup, provider: &Provider) -> bool {
+    *api_group == ApiGroup::Anthropic && provider.provider_type == ProviderType::Google
+}
+
+/// Routing debug headers (`X-VibeMate-*`) describing where a request was sent.
+/// Values that are not valid header values (e.g. non-ASCII provider names) are skipped.
+fn routing_debug_headers(resolved: &ResolvedProvider) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let values = [
+        ("x-vibemate-provider", resolved.provider.name.as_str()),
+        ("x-vibemate-model", resolved.final_model.as_str()),
+        ("x-vibemate-rule", resolved.rule_label()),
+    ];
+    for (name, value) in values {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            headers.insert(name, value);
+        }
+    }
+    headers
+}
+
+/// Upstream rate-limit headers (`x-ratelimit-*`, `anthropic-ratelimit-*`, `retry-after`)
+fn is_rate_limit_header(name: &str) -> bool {
+    name.starts_with("x-ratelimit-")
+        || name.starts_with("anthropic-ratelimit-")
+        || name == header::RETRY_AFTER.as_str()
+}
+
+fn rate_limit_headers(upstream: &reqwest::header::HeaderMap) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (key, value) in upstream.iter() {
+        if is_rate_limit_header(key.as_str()) {
+            headers.insert(key.clone(), value.clone());
+        }
     }
+    headers
+}
 
-    for rule in path_rules {
-        if matches_pattern(&rule.match_pattern, request_path) {
-            return Some(rule);
+/// Add extra headers to a response without overriding any header the upstream already set
+fn append_missing_headers(
+    mut builder: axum::http::response::Builder,
+    upstream: &reqwest::header::HeaderMap,
+    extra: &HeaderMap,
+) -> axum::http::response::Builder {
+    for (key, value) in extra.iter() {
+        if !upstream.contains_key(key.as_str()) {
+            builder = builder.header(key, value);
         }
     }
+    builder
+}
+
+fn match_rule_for_group<'a>(
+    rules: &[&'a RoutingRule],
+    api_group: &ApiGroup,
+    request_path: &str,
+    model_name: Option<&str>,
+) -> Option<&'a RoutingRule> {
+    rules_in_match_order(rules, api_group)
+        .into_iter()
+        .find(|rule| match rule.rule_type {
+            RuleType::Model => {
+                model_name.is_some_and(|model| matches_pattern(&rule.match_pattern, model))
+            }
+            RuleType::Path => matches_pattern(&rule.match_pattern, request_path),
+        })
+}
 
-    None
+/// Rules of `api_group` in the order they are tried: model rules by priority, then path
+/// rules by priority, with Generic's catch-all `/api/*` last. Ties keep their input order.
+pub(crate) fn rules_in_match_order<'a>(
+    rules: &[&'a RoutingRule],
+    api_group: &ApiGroup,
+) -> Vec<&'a RoutingRule> {
+    let of_type = |rule_type: RuleType| -> Vec<&'a RoutingRule> {
+        rules
+            .iter()
+            .copied()
+            .filter(|r| &r.api_group == api_group && r.rule_type == rule_type)
+            .collect()
+    };
+
+    let mut ordered = of_type(RuleType::Model);
+    ordered.sort_by_key(|r| r.priority);
+
+    let mut path_rules = of_type(RuleType::Path);
+    if *api_group == ApiGroup::Generic {
+        path_rules.sort_by_key(|r| (r.match_pattern == "/api/*", r.priority));
+    } else {
+        path_rules.sort_by_key(|r| r.priority);
+    }
+    ordered.extend(path_rules);
+    ordered
 }
 
 /// Match a pattern against a model name using glob-style matching
-fn matches_pattern(pattern: &str, model_name: &str) -> bool {
+pub(crate) fn matches_pattern(pattern: &str, model_name: &str) -> bool {
     Pattern::new(pattern)
         .map(|p| p.matches(model_name))
         .unwrap_or(false)
 }
 
+/// Map an aliased model name to its target. Aliases are resolved once, not recursively.
+/// Returns the effective model name and whether an alias was applied.
+fn resolve_model_alias(
+    aliases: &HashMap<String, String>,
+    model_name: Option<String>,
+) -> (Option<String>, bool) {
+    match model_name {
+        Some(model) => match aliases.get(&model) {
+            Some(target) => {
+                tracing::debug!("Model alias {} -> {}", model, target);
+                (Some(target.clone()), true)
+            }
+            None => (Some(model), false),
+        },
+        None => (None, false),
+    }
+}
+
+/// Whether a request body should be treated as JSON: a JSON content type, or none at all
+/// since many clients omit it on JSON posts
+fn is_json_request(headers: &HeaderMap) -> bool {
+    match headers.get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok()) {
+        Some(content_type) => content_type.to_ascii_lowercase().contains("json"),
+        None => !headers.contains_key(header::CONTENT_TYPE),
+    }
+}
+
 /// Extract model name from request body
 fn extract_model_from_body(body: &Bytes) -> Option<String> {
     #[derive(Deserialize)]
@@ -513,11 +1985,175 @@ fn rewrite_model_in_body(body: &Bytes, new_model: &str) -> Vec<u8> {
     }
 }
 
+/// Add the provider's credentials: its agent account's OAuth token, or its API key.
+/// Returns a 401 response when the agent account needs a new login.
+async fn add_upstream_auth(
+    state: &AppState,
+    req: reqwest::RequestBuilder,
+    resolved: &ResolvedProvider,
+    client_headers: &HeaderMap,
+) -> Result<reqwest::RequestBuilder, Response<Body>> {
+    let Some(agent_type) = &resolved.provider.agent_account else {
+        return Ok(add_api_key_auth(req, &resolved.provider, client_headers));
+    };
+    match get_agent_access_token(&state.auth_ctx, agent_type).await {
+        Ok(token) => Ok(add_oauth_header(req, &token, client_headers)),
+        Err(e) => {
+            tracing::error!(
+                "Failed to load {:?} OAuth token for provider {}: {}",
+                agent_type,
+                resolved.provider.name,
+                e
+            );
+            Err(error_response(
+                StatusCode::UNAUTHORIZED,
+                &format!("Agent account login required: {}", e),
+            ))
+        }
+    }
+}
+
+/// Authenticate a provider without an agent account. Precedence:
+/// 1. the provider's stored API key, in its provider-specific header
+/// 2. with `passthrough_auth`, the client's `Authorization` header, forwarded unchanged
+/// 3. otherwise the request goes out without credentials
+fn add_api_key_auth(
+    req: reqwest::RequestBuilder,
+    provider: &Provider,
+    client_headers: &HeaderMap,
+) -> reqwest::RequestBuilder {
+    if has_api_key(provider) {
+        return add_auth_header(req, provider);
+    }
+    match client_headers.get(header::AUTHORIZATION) {
+        Some(value) if provider.passthrough_auth => req.header(header::AUTHORIZATION, value),
+        _ => req,
+    }
+}
+
+fn has_api_key(provider: &Provider) -> bool {
+    provider.resolved_api_key().is_some()
+}
+
+/// A curl command reproducing how the proxy calls `provider` for a minimal chat request
+/// with `model`: the same target URL and auth headers, with the API key masked. Agent
+/// accounts show a placeholder instead of their OAuth token.
+pub(crate) fn provider_curl_example(
+    provider: &Provider,
+    base_url: &str,
+    model: &str,
+    app: &AppConfig,
+) -> String {
+    let base_url = base_url.trim_end_matches('/');
+    let (url, body) = match provider.provider_type {
+        ProviderType::Anthropic => (
+            format!("{}/v1/messages", base_url),
+            serde_json::json!({
+                "model": model,
+                "max_tokens": 16,
+                "messages": [{"role": "user", "content": "Hello"}],
+            }),
+        ),
+        ProviderType::Google => (
+            gemini_generate_url(base_url, model),
+            serde_json::json!({"contents": [{"role": "user", "parts": [{"text": "Hello"}]}]}),
+        ),
+        _ => {
+            let url = if provider.provider_type == ProviderType::Azure {
+                azure_target_url(base_url, provider, model, "/v1/chat/completions")
+            } else if base_url.ends_with("/v1") {
+                format!("{}/chat/completions", base_url)
+            } else {
+                format!("{}/v1/chat/completions", base_url)
+            };
+            let body = serde_json::json!({
+                "model": model,
+                "messages": [{"role": "user", "content": "Hello"}],
+            });
+            (url, body)
+        }
+    };
+
+    let mut req = Client::new().post(&url);
+    if let Some(user_agent) = upstream_user_agent(provider, app) {
+        req = req.header(header::USER_AGENT, user_agent);
+    }
+    let mut masked = provider.clone();
+    masked.api_key = provider.resolved_api_key().map(|key| mask_api_key(&key));
+    let oauth_placeholder = provider.agent_account.is_some().then_some("<oauth-token>");
+    req = add_provider_auth(req, &masked, oauth_placeholder);
+    req = req.header(header::CONTENT_TYPE, "application/json");
+
+    let quote = |s: &str| format!("'{}'", s.replace('\'', "'\\''"));
+    let mut lines = vec![format!("curl -X POST {}", quote(&url))];
+    if let Ok(request) = req.build() {
+        for (name, value) in request.headers() {
+            let value = String::from_utf8_lossy(value.as_bytes());
+            lines.push(format!("  -H {}", quote(&format!("{}: {}", name, value))));
+        }
+    }
+    lines.push(format!("  -d {}", quote(&body.to_string())));
+    lines.join(" \\\n")
+}
+
+/// The provider's model list, a cheap authenticated endpoint for probing latency
+pub(crate) fn provider_models_url(provider: &Provider, base_url: &str) -> String {
+    let base_url = base_url.trim_end_matches('/');
+    match provider.provider_type {
+        ProviderType::Google if !base_url.ends_with("/v1beta") && !base_url.ends_with("/v1") => {
+            format!("{}/v1beta/models", base_url)
+        }
+        ProviderType::Google => format!("{}/models", base_url),
+        ProviderType::Azure => format!(
+            "{}/openai/models?api-version={}",
+            base_url,
+            provider.api_version.as_deref().unwrap_or(AZURE_DEFAULT_API_VERSION)
+        ),
+        ProviderType::Anthropic => format!("{}/v1/models", base_url),
+        _ if base_url.ends_with("/v1") => format!("{}/models", base_url),
+        _ => format!("{}/v1/models", base_url),
+    }
+}
+
+/// Provider credentials as forwarded requests get them, minus anything taken from client
+/// headers: the API key, or `oauth_token` for agent-backed providers
+pub(crate) fn add_provider_auth(
+    req: reqwest::RequestBuilder,
+    provider: &Provider,
+    oauth_token: Option<&str>,
+) -> reqwest::RequestBuilder {
+    match oauth_token {
+        Some(token) => add_oauth_header(req, token, &HeaderMap::new()),
+        None => add_auth_header(req, provider),
+    }
+}
+
+/// Keep only enough of an API key to recognize it
+fn mask_api_key(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() <= 8 {
+        return "****".to_string();
+    }
+    let head: String = chars[..4].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}...{}", head, tail)
+}
+
+/// Azure OpenAI addresses a deployment instead of a model:
+/// `{base}/openai/deployments/{deployment}/chat/completions?api-version=...`
+fn azure_target_url(base_url: &str, provider: &Provider, model: &str, path: &str) -> String {
+    let deployment = provider.deployment.as_deref().unwrap_or(model);
+    let api_version = provider.api_version.as_deref().unwrap_or(AZURE_DEFAULT_API_VERSION);
+    let path = path.strip_prefix("/v1").unwrap_or(path);
+    format!(
+        "{}/openai/deployments/{}{}?api-version={}",
+        base_url, deployment, path, api_version
+    )
+}
+
 /// Add authentication header based on provider type
 fn add_auth_header(req: reqwest::RequestBuilder, provider: &Provider) -> reqwest::RequestBuilder {
-    use crate::models::ProviderType;
-
-    let api_key = match provider.api_key.as_ref() {
+    let api_key = match provider.resolved_api_key() {
         Some(key) => key,
         None => return req, // No API key, return request as-is
     };
@@ -530,34 +2166,107 @@ fn add_auth_header(req: reqwest::RequestBuilder, provider: &Provider) -> reqwest
         ProviderType::Google => {
             req.header("x-goog-api-key", api_key)
         }
+        ProviderType::Azure => {
+            req.header("api-key", api_key)
+        }
+        ProviderType::OpenRouter => {
+            req.header(header::AUTHORIZATION, format!("Bearer {}", api_key))
+                .header(
+                    "HTTP-Referer",
+                    provider.http_referer.as_deref().unwrap_or(OPENROUTER_DEFAULT_REFERER),
+                )
+                .header(
+                    "X-Title",
+                    provider.app_title.as_deref().unwrap_or(OPENROUTER_DEFAULT_TITLE),
+                )
+        }
+        ProviderType::Custom => {
+            let name = provider
+                .auth_header_name
+                .as_deref()
+                .unwrap_or(DEFAULT_AUTH_HEADER_NAME);
+            let value = provider
+                .auth_header_template
+                .as_deref()
+                .unwrap_or(DEFAULT_AUTH_HEADER_TEMPLATE)
+                .replace(AUTH_KEY_PLACEHOLDER, &api_key);
+            req.header(name.trim(), value)
+        }
         _ => {
             req.header(header::AUTHORIZATION, format!("Bearer {}", api_key))
         }
     }
 }
 
+/// Add a bearer OAuth token for an agent-backed Anthropic provider, merging the required
+/// `anthropic-beta` flag into any beta flags the client already requested
+fn add_oauth_header(
+    req: reqwest::RequestBuilder,
+    access_token: &str,
+    client_headers: &HeaderMap,
+) -> reqwest::RequestBuilder {
+    let mut betas: Vec<String> = client_headers
+        .get_all("anthropic-beta")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+    if !betas.iter().any(|b| b == CLAUDE_OAUTH_BETA) {
+        betas.push(CLAUDE_OAUTH_BETA.to_string());
+    }
+
+    let req = req
+        .bearer_auth(access_token)
+        .header("anthropic-beta", betas.join(","));
+    if client_headers.contains_key("anthropic-version") {
+        req
+    } else {
+        req.header("anthropic-version", "2023-06-01")
+    }
+}
+
 /// Handle regular (non-streaming) response
 async fn handle_regular_response(
     response: reqwest::Response,
+    permit: Option<OwnedSemaphorePermit>,
+    extra_headers: &HeaderMap,
+    transform: Option<&ResponseTransform>,
+    meter: Option<SpendMeter>,
 ) -> Result<Response<Body>, StatusCode> {
     let status = response.status();
     let headers = response.headers().clone();
 
-    let body_bytes = response.bytes().await.map_err(|e| {
+    let mut body_bytes = response.bytes().await.map_err(|e| {
         tracing::error!("Failed to read response body: {}", e);
         StatusCode::BAD_GATEWAY
     })?;
+    drop(permit);
+    if let Some(mut meter) = meter {
+        meter.observe_body(&body_bytes);
+    }
 
     tracing::debug!("Response body size: {} bytes", body_bytes.len());
 
+    let transformed = match transform.and_then(|t| transform_json_body(&headers, &body_bytes, t)) {
+        Some(body) => {
+            body_bytes = body;
+            true
+        }
+        None => false,
+    };
+
     let mut builder = Response::builder().status(status);
 
-    // Copy relevant headers (skip transfer-encoding as we're using a known body length)
+    // Copy relevant headers (skip transfer-encoding as we're using a known body length,
+    // and content-length when the transform changed the body)
     for (key, value) in headers.iter() {
-        if key != header::TRANSFER_ENCODING {
+        if key != header::TRANSFER_ENCODING && !(transformed && key == header::CONTENT_LENGTH) {
             builder = builder.header(key, value);
         }
     }
+    builder = append_missing_headers(builder, &headers, extra_headers);
 
     builder.body(Body::from(body_bytes)).map_err(|e| {
         tracing::error!("Failed to build response: {}", e);
@@ -565,34 +2274,161 @@ async fn handle_regular_response(
     })
 }
 
-/// Handle streaming (SSE) response
-async fn handle_streaming_response(
+/// Apply a provider's response transform to a JSON body. Returns None (forward the body
+/// unchanged) for other content types or bodies that don't parse.
+fn transform_json_body(
+    headers: &HeaderMap,
+    body: &[u8],
+    transform: &ResponseTransform,
+) -> Option<Bytes> {
+    let is_json = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.contains("json"));
+    if !is_json {
+        return None;
+    }
+    let mut value: serde_json::Value = serde_json::from_slice(body).ok()?;
+    transform.apply(&mut value);
+    serde_json::to_vec(&value).ok().map(Bytes::from)
+}
+
+/// Handle a non-streaming Gemini response, translating it back to the Anthropic format
+async fn handle_translated_response(
     response: reqwest::Response,
+    permit: Option<OwnedSemaphorePermit>,
+    model: &str,
+    extra_headers: &HeaderMap,
+    meter: Option<SpendMeter>,
 ) -> Result<Response<Body>, StatusCode> {
     let status = response.status();
-    let headers = response.headers().clone();
-
-    // Create a stream from the response body
-    let stream = response.bytes_stream().map(|result| {
-        result.map_err(|e| {
-            tracing::error!("Streaming error: {}", e);
-            std::io::Error::new(std::io::ErrorKind::Other, e)
-        })
-    });
-
-    let body = Body::from_stream(stream);
+    let upstream_rate_limits = rate_limit_headers(response.headers());
 
-    let mut builder = Response::builder().status(status);
-
-    for (key, value) in headers.iter() {
-        builder = builder.header(key, value);
+    let body_bytes = response.bytes().await.map_err(|e| {
+        tracing::error!("Failed to read response body: {}", e);
+        StatusCode::BAD_GATEWAY
+    })?;
+    drop(permit);
+    if let Some(mut meter) = meter {
+        meter.observe_body(&body_bytes);
     }
 
+    let body = if status.is_success() {
+        match gemini_to_anthropic_response(&body_bytes, model) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::error!("Failed to translate Gemini response: {}", e);
+                return Ok(error_response(
+                    StatusCode::BAD_GATEWAY,
+                    &format!("Failed to translate Gemini response: {}", e),
+                ));
+            }
+        }
+    } else {
+        gemini_error_to_anthropic(&body_bytes)
+    };
+
+    // Only rate-limit headers are carried over; the body no longer matches the rest
+    let mut builder = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json");
+    for (key, value) in upstream_rate_limits.iter() {
+        builder = builder.header(key, value);
+    }
+    if !status.is_success() {
+        builder = builder.header(UPSTREAM_STATUS_HEADER, status.as_str());
+    }
+    builder = append_missing_headers(builder, &upstream_rate_limits, extra_headers);
+
+    builder.body(Body::from(body)).map_err(|e| {
+        tracing::error!("Failed to build response: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Handle streaming (SSE) response
+async fn handle_streaming_response(
+    response: reqwest::Response,
+    permit: Option<OwnedSemaphorePermit>,
+    extra_headers: &HeaderMap,
+    mut meter: Option<SpendMeter>,
+    keepalive: Option<Duration>,
+) -> Result<Response<Body>, StatusCode> {
+    let status = response.status();
+    let headers = response.headers().clone();
+
+    // Create a stream from the response body; the stream owns the provider permit
+    // (and spend meter) so the slot is released only once the stream is finished or dropped
+    let stream = response.bytes_stream().map(move |result| {
+        let _permit = &permit;
+        if let (Some(meter), Ok(chunk)) = (meter.as_mut(), result.as_ref()) {
+            meter.observe_chunk(chunk);
+        }
+        result.map_err(|e| {
+            tracing::error!("Streaming error: {}", e);
+            std::io::Error::new(std::io::ErrorKind::Other, e)
+        })
+    });
+
+    // A comment frame spliced into a compressed body would corrupt it
+    let keepalive = keepalive.filter(|_| !headers.contains_key(header::CONTENT_ENCODING));
+    let body = match keepalive {
+        Some(interval) => Body::from_stream(with_sse_keepalive(stream, interval)),
+        None => Body::from_stream(stream),
+    };
+
+    let mut builder = Response::builder().status(status);
+
+    for (key, value) in headers.iter() {
+        builder = builder.header(key, value);
+    }
+    builder = append_missing_headers(builder, &headers, extra_headers);
+
     builder
         .body(body)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
 
+/// Relay `stream`, sending an SSE keepalive comment whenever no chunk arrives for
+/// `interval`. Comments only go between events (never inside a partially relayed one)
+/// and stop with the stream.
+fn with_sse_keepalive<S>(
+    stream: S,
+    interval: Duration,
+) -> impl futures_util::Stream<Item = Result<Bytes, std::io::Error>> + Send + 'static
+where
+    S: futures_util::Stream<Item = Result<Bytes, std::io::Error>> + Send + 'static,
+{
+    // Last bytes relayed, to tell whether the client is between two events
+    let tail = Vec::<u8>::new();
+    futures_util::stream::unfold((Box::pin(stream), tail), move |(mut stream, mut tail)| {
+        async move {
+            loop {
+                match tokio::time::timeout(interval, stream.next()).await {
+                    Ok(Some(chunk)) => {
+                        if let Ok(bytes) = &chunk {
+                            tail.extend_from_slice(bytes);
+                            let excess = tail.len().saturating_sub(4);
+                            tail.drain(..excess);
+                        }
+                        return Some((chunk, (stream, tail)));
+                    }
+                    Ok(None) => return None,
+                    Err(_) if at_event_boundary(&tail) => {
+                        return Some((Ok(Bytes::from_static(SSE_KEEPALIVE)), (stream, tail)));
+                    }
+                    // Mid-event: wait for the rest of it instead
+                    Err(_) => continue,
+                }
+            }
+        }
+    })
+}
+
+fn at_event_boundary(tail: &[u8]) -> bool {
+    tail.is_empty() || tail.ends_with(b"\n\n") || tail.ends_with(b"\r\n\r\n")
+}
+
 /// Create an error response
 fn error_response(status: StatusCode, message: &str) -> Response<Body> {
     #[derive(Serialize)]
@@ -639,4 +2475,1713 @@ pub enum ProxyError {
     BindFailed(String),
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
+    #[error("Cannot replay request: {0}")]
+    Replay(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ProviderStatus, ProviderType};
+
+    fn test_provider(limit: Option<u32>) -> Provider {
+        let mut provider = Provider::new_model(
+            "Test".to_string(),
+            ProviderType::OpenAI,
+            "https://api.openai.com/v1".to_string(),
+            "sk-test".to_string(),
+        );
+        provider.max_concurrent_requests = limit;
+        provider
+    }
+
+    /// Serve `app` on a free local port
+    async fn spawn_upstream(app: Router) -> std::net::SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        addr
+    }
+
+    /// Upstream that logs what `record` extracts from each request, then answers with
+    /// `respond` given the log so far. Returns the router and the log.
+    fn recording_upstream<T, F, R, O>(
+        record: F,
+        respond: R,
+    ) -> (Router, Arc<std::sync::Mutex<Vec<T>>>)
+    where
+        T: Send + 'static,
+        F: Fn(&Uri, &HeaderMap, &Bytes) -> T + Clone + Send + Sync + 'static,
+        R: Fn(&[T]) -> O + Clone + Send + Sync + 'static,
+        O: axum::response::IntoResponse,
+    {
+        let seen: Arc<std::sync::Mutex<Vec<T>>> = Arc::default();
+        let recorded = seen.clone();
+        let router = Router::new().fallback(move |uri: Uri, headers: HeaderMap, body: Bytes| {
+            let mut log = recorded.lock().unwrap();
+            log.push(record(&uri, &headers, &body));
+            let response = respond(&log).into_response();
+            async move { response }
+        });
+        (router, seen)
+    }
+
+    /// A provider named `name` pointing at `base_url`
+    fn upstream_provider(name: &str, provider_type: ProviderType, base_url: String) -> Provider {
+        Provider::new_model(name.to_string(), provider_type, base_url, "sk-test".to_string())
+    }
+
+    /// Proxy router over a fresh config store. Bind `_config_dir` when destructuring, or
+    /// the store's directory is deleted before the test runs.
+    struct TestProxy {
+        app: Router,
+        server: Arc<ProxyServer>,
+        _config_dir: tempfile::TempDir,
+    }
+
+    async fn spawn_test_proxy(configure: impl FnOnce(&mut VibeMateConfig)) -> TestProxy {
+        let config_dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ConfigStore::new(config_dir.path().to_path_buf()));
+        store.init().await.unwrap();
+        store.update(configure).await.unwrap();
+        let route_prefix = store.get_config().await.app.route_prefix;
+        let server = Arc::new(ProxyServer::new(store.clone()));
+        let app = proxy_router(
+            AppState {
+                server: server.clone(),
+                http_client: Client::new(),
+                auth_ctx: AgentAuthContext::new(store),
+                access_log: None,
+                route_prefix,
+            },
+            cors_layer(&[]),
+        );
+        TestProxy {
+            app,
+            server,
+            _config_dir: config_dir,
+        }
+    }
+
+    #[test]
+    fn test_recent_requests_ring_buffer() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let server = ProxyServer::new(Arc::new(ConfigStore::new(temp_dir.path().to_path_buf())));
+        for status in [200, 201, 202] {
+            server.record_request(
+                RequestRecord {
+                    id: status.to_string(),
+                    timestamp: Utc::now(),
+                    method: "POST".to_string(),
+                    path: "/api/openai/v1/chat/completions".to_string(),
+                    api_group: ApiGroup::OpenAI,
+                    provider: None,
+                    model: None,
+                    rule: None,
+                    fallback: false,
+                    status,
+                    duration_ms: 1.0,
+                    request_body: None,
+                    response_body: None,
+                    replay_uri: None,
+                    request_content_type: None,
+                },
+                2,
+            );
+        }
+
+        let statuses: Vec<u16> = server.recent_requests().iter().map(|r| r.status).collect();
+        assert_eq!(statuses, vec![202, 201]);
+
+        server.capture_next_request();
+        assert!(server.take_capture_next());
+        assert!(!server.take_capture_next());
+    }
+
+    #[test]
+    fn test_provider_limiter_reuses_and_resizes() {
+        let limiters = ProviderLimiters::default();
+        assert!(limiters.semaphore_for(&test_provider(None)).is_none());
+        assert!(limiters.semaphore_for(&test_provider(Some(0))).is_none());
+
+        let mut provider = test_provider(Some(2));
+        let first = limiters.semaphore_for(&provider).unwrap();
+        let second = limiters.semaphore_for(&provider).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(first.available_permits(), 2);
+
+        provider.max_concurrent_requests = Some(5);
+        let resized = limiters.semaphore_for(&provider).unwrap();
+        assert!(!Arc::ptr_eq(&first, &resized));
+        assert_eq!(resized.available_permits(), 5);
+    }
+
+    #[test]
+    fn test_fallback_prefers_connected_provider() {
+        let mut errored = test_provider(None);
+        errored.name = "Errored".to_string();
+        errored.status = ProviderStatus::Error;
+        let mut connected = test_provider(None);
+        connected.name = "Connected".to_string();
+        connected.status = ProviderStatus::Connected;
+
+        let config = VibeMateConfig {
+            providers: vec![errored.clone(), connected.clone()],
+            ..VibeMateConfig::default()
+        };
+        let resolved = resolve_provider(
+            &config,
+            ApiGroup::OpenAI,
+            "/api/openai/v1/chat",
+            Some("gpt-4o"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(resolved.provider.id, connected.id);
+
+        // With no healthy provider, the unhealthy default is still used
+        let config = VibeMateConfig {
+            providers: vec![errored.clone()],
+            ..VibeMateConfig::default()
+        };
+        let resolved = resolve_provider(
+            &config,
+            ApiGroup::OpenAI,
+            "/api/openai/v1/chat",
+            Some("gpt-4o"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(resolved.provider.id, errored.id);
+    }
+
+    #[test]
+    fn test_group_default_provider_before_global_default() {
+        let global = test_provider(None);
+        let anthropic = test_provider(None);
+        let mut config = VibeMateConfig {
+            providers: vec![global.clone(), anthropic.clone()],
+            routing_rules: vec![RoutingRule::new(
+                global.id.clone(),
+                "claude-opus-*".to_string(),
+                1,
+                RuleType::Model,
+                ApiGroup::Anthropic,
+            )],
+            ..VibeMateConfig::default()
+        };
+        config
+            .app
+            .default_provider_by_group
+            .insert(ApiGroup::Anthropic, anthropic.id.clone());
+
+        let resolve = |config: &VibeMateConfig, api_group: ApiGroup, model: &str| {
+            resolve_provider(config, api_group, "/api/anthropic/v1/messages", Some(model), None)
+                .unwrap()
+                .provider
+                .id
+        };
+        // Matching rules still win; only unmatched traffic uses the group default
+        assert_eq!(resolve(&config, ApiGroup::Anthropic, "claude-opus-4"), global.id);
+        assert_eq!(resolve(&config, ApiGroup::Anthropic, "claude-sonnet-4"), anthropic.id);
+        assert_eq!(resolve(&config, ApiGroup::OpenAI, "gpt-4o"), global.id);
+
+        // A disabled group default falls back to the global default
+        config.providers[1].enabled = false;
+        assert_eq!(resolve(&config, ApiGroup::Anthropic, "claude-sonnet-4"), global.id);
+    }
+
+    #[test]
+    fn test_disabled_provider_never_selected() {
+        let mut disabled = test_provider(None);
+        disabled.status = ProviderStatus::Connected;
+        disabled.enabled = false;
+        let fallback = test_provider(None);
+        let config = VibeMateConfig {
+            providers: vec![disabled.clone(), fallback.clone()],
+            routing_rules: vec![RoutingRule::new(
+                disabled.id.clone(),
+                "gpt-*".to_string(),
+                1,
+                RuleType::Model,
+                ApiGroup::OpenAI,
+            )],
+            ..VibeMateConfig::default()
+        };
+
+        // Neither the matching rule nor the default fallback picks the disabled provider
+        for model in ["gpt-4o", "o3"] {
+            let resolved = resolve_provider(
+                &config,
+                ApiGroup::OpenAI,
+                "/api/openai/v1/chat",
+                Some(model),
+                None,
+            )
+            .unwrap();
+            assert_eq!(resolved.provider.id, fallback.id);
+        }
+
+        // A rule whose provider was deleted falls back to the default and says so
+        let mut config = config;
+        config.routing_rules[0].provider_id = "deleted".to_string();
+        let resolved = resolve_provider(
+            &config,
+            ApiGroup::OpenAI,
+            "/api/openai/v1/chat",
+            Some("gpt-4o"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(resolved.provider.id, fallback.id);
+        assert_eq!(resolved.rule_label(), "default");
+        assert!(resolved.fallback);
+
+        let config = VibeMateConfig {
+            providers: vec![disabled],
+            ..VibeMateConfig::default()
+        };
+        assert!(
+            resolve_provider(&config, ApiGroup::OpenAI, "/api/openai/v1/chat", Some("gpt-4o"), None)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_openrouter_attribution_headers() {
+        let mut provider = Provider::new_model(
+            "OpenRouter".to_string(),
+            ProviderType::OpenRouter,
+            "https://openrouter.ai/api/v1".to_string(),
+            "sk-or-test".to_string(),
+        );
+        let build = |provider: &Provider| {
+            add_auth_header(Client::new().post("http://localhost/v1/chat/completions"), provider)
+                .build()
+                .unwrap()
+        };
+
+        let req = build(&provider);
+        assert_eq!(req.headers()[header::AUTHORIZATION], "Bearer sk-or-test");
+        assert_eq!(req.headers()["http-referer"], OPENROUTER_DEFAULT_REFERER);
+        assert_eq!(req.headers()["x-title"], OPENROUTER_DEFAULT_TITLE);
+
+        provider.http_referer = Some("https://example.com".to_string());
+        provider.app_title = Some("My App".to_string());
+        let req = build(&provider);
+        assert_eq!(req.headers()["http-referer"], "https://example.com");
+        assert_eq!(req.headers()["x-title"], "My App");
+    }
+
+    #[tokio::test]
+    async fn test_replay_request_with_provider_override() {
+        let app = Router::new()
+            .route(
+                "/a/v1/chat/completions",
+                axum::routing::post(|headers: HeaderMap| async move {
+                    let content_type = headers.get(header::CONTENT_TYPE).cloned();
+                    let content_type = content_type.as_ref().and_then(|v| v.to_str().ok());
+                    format!("from a {}", content_type.unwrap_or(""))
+                }),
+            )
+            .route("/b/v1/chat/completions", axum::routing::post(|| async { "from b" }));
+        let addr = spawn_upstream(app).await;
+
+        let provider = |name: &str| {
+            upstream_provider(name, ProviderType::OpenAI, format!("http://{}/{}", addr, name))
+        };
+        let (a, b) = (provider("a"), provider("b"));
+        let b_id = b.id.clone();
+        let TestProxy { server, _config_dir, .. } =
+            spawn_test_proxy(|c| c.providers = vec![a, b]).await;
+
+        let record = |id: &str, body: Option<&str>| RequestRecord {
+            id: id.to_string(),
+            timestamp: Utc::now(),
+            method: "POST".to_string(),
+            path: "/api/openai/v1/chat/completions".to_string(),
+            api_group: ApiGroup::OpenAI,
+            provider: None,
+            model: Some("gpt-4o".to_string()),
+            rule: None,
+            fallback: false,
+            status: 200,
+            duration_ms: 1.0,
+            request_body: body.map(str::to_string),
+            response_body: None,
+            replay_uri: Some("/api/openai/v1/chat/completions?key=secret".to_string()),
+            request_content_type: Some("application/json; charset=utf-8".to_string()),
+        };
+        server.record_request(record("captured", Some(r#"{"model":"gpt-4o"}"#)), 10);
+        server.record_request(record("uncaptured", None), 10);
+
+        let routed = server.replay_request("captured", None).await.unwrap();
+        assert_eq!(routed.status, 200);
+        // The original Content-Type is replayed, not a hardcoded JSON one
+        assert_eq!(routed.body, "from a application/json; charset=utf-8");
+
+        let overridden = server.replay_request("captured", Some(b_id)).await.unwrap();
+        assert_eq!(overridden.body, "from b");
+        assert_eq!(overridden.provider.as_deref(), Some("b"));
+
+        assert!(matches!(
+            server.replay_request("uncaptured", None).await,
+            Err(ProxyError::Replay(_))
+        ));
+        assert_eq!(server.request_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_read_request_body_limit() {
+        let body = read_request_body(Body::from("{}"), 16).await.unwrap();
+        assert_eq!(&body[..], b"{}");
+
+        let response = read_request_body(Body::from(vec![b'x'; 32]), 16)
+            .await
+            .unwrap_err();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_send_with_retry_on_rate_limit() {
+        use axum::response::IntoResponse;
+        use std::sync::atomic::AtomicUsize;
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        // Rate limited twice, then succeeds; /down is always unavailable
+        let app = Router::new()
+            .route(
+                "/flaky",
+                axum::routing::post(move || {
+                    let counter = counter.clone();
+                    async move {
+                        if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                            (
+                                StatusCode::TOO_MANY_REQUESTS,
+                                [
+                                    (header::RETRY_AFTER.as_str(), "0"),
+                                    ("x-ratelimit-remaining-requests", "0"),
+                                ],
+                            )
+                                .into_response()
+                        } else {
+                            "ok".into_response()
+                        }
+                    }
+                }),
+            )
+            .route(
+                "/down",
+                axum::routing::post(|| async { StatusCode::SERVICE_UNAVAILABLE }),
+            );
+        let addr = spawn_upstream(app).await;
+
+        let client = Client::new();
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(10),
+        };
+
+        let url = format!("http://{}/flaky", addr);
+        let mut rate_limits = HeaderMap::new();
+        let response =
+            send_with_retry(client.post(&url).body("{}"), &policy, &url, &mut rate_limits)
+                .await
+                .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+        assert_eq!(rate_limits["x-ratelimit-remaining-requests"], "0");
+
+        let url = format!("http://{}/down", addr);
+        let response =
+            send_with_retry(client.post(&url).body("{}"), &policy, &url, &mut HeaderMap::new())
+                .await
+                .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(10));
+        assert_eq!(policy.backoff(3), Duration::from_millis(40));
+    }
+
+    #[tokio::test]
+    async fn test_translated_error_keeps_upstream_status() {
+        let upstream = axum::http::Response::builder()
+            .status(StatusCode::TOO_MANY_REQUESTS)
+            .header(header::RETRY_AFTER, "7")
+            .header("x-goog-trace", "dropped")
+            .body(r#"{"error":{"code":429,"message":"quota","status":"RESOURCE_EXHAUSTED"}}"#)
+            .unwrap();
+        let response = handle_translated_response(
+            reqwest::Response::from(upstream),
+            None,
+            "gemini-2.5-pro",
+            &HeaderMap::new(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "7");
+        assert_eq!(response.headers()[UPSTREAM_STATUS_HEADER], "429");
+        assert!(response.headers().get("x-goog-trace").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_spares_slow_streams() {
+        // Emits `chunks` lines, sleeping `gap_ms` before each one
+        fn drip(chunks: usize, gap_ms: u64) -> Body {
+            Body::from_stream(futures_util::stream::iter(0..chunks).then(move |i| async move {
+                tokio::time::sleep(Duration::from_millis(gap_ms)).await;
+                Ok::<_, std::io::Error>(Bytes::from(format!("chunk {}\n", i)))
+            }))
+        }
+
+        let app = Router::new()
+            .route("/slow", axum::routing::get(|| async { drip(4, 400) }))
+            .route("/stalled", axum::routing::get(|| async { drip(1, 2500) }));
+        let addr = spawn_upstream(app).await;
+
+        let mut config = VibeMateConfig::default();
+        config.app.idle_timeout_secs = 1;
+        let client = create_http_client(&config);
+
+        // Longer than the idle timeout overall, but never idle for that long
+        let body = client
+            .get(format!("http://{}/slow", addr))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body.lines().count(), 4);
+
+        let stalled = client
+            .get(format!("http://{}/stalled", addr))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await;
+        assert!(stalled.is_err());
+    }
+
+    #[test]
+    fn test_custom_provider_auth_header() {
+        let mut provider = Provider::new_model(
+            "Gateway".to_string(),
+            ProviderType::Custom,
+            "https://gateway.example.com/v1".to_string(),
+            "secret".to_string(),
+        );
+        let build = |provider: &Provider| {
+            add_auth_header(Client::new().post("http://localhost/v1/chat/completions"), provider)
+                .build()
+                .unwrap()
+        };
+
+        let req = build(&provider);
+        assert_eq!(req.headers()[header::AUTHORIZATION], "Bearer secret");
+
+        provider.auth_header_name = Some("Api-Key".to_string());
+        provider.auth_header_template = Some("{key}".to_string());
+        let req = build(&provider);
+        assert_eq!(req.headers()["api-key"], "secret");
+        assert!(req.headers().get(header::AUTHORIZATION).is_none());
+    }
+
+    #[test]
+    fn test_api_key_env_interpolation() {
+        let env = HashMap::from([("VIBEMATE_KEY", "sk-from-env"), ("VIBEMATE_EMPTY_KEY", "")]);
+        let lookup = |name: &str| env.get(name).map(|v| v.to_string());
+        let resolve = |key: &str| {
+            let mut provider = test_provider(None);
+            provider.api_key = Some(key.to_string());
+            provider.resolved_api_key_from(lookup)
+        };
+
+        assert_eq!(resolve("${VIBEMATE_KEY}").as_deref(), Some("sk-from-env"));
+        // Only a whole-value reference is interpolated
+        assert_eq!(resolve("sk-${VIBEMATE_KEY}").as_deref(), Some("sk-${VIBEMATE_KEY}"));
+        // Unset variables are sent literally
+        assert_eq!(resolve("${VIBEMATE_UNSET_KEY}").as_deref(), Some("${VIBEMATE_UNSET_KEY}"));
+        // A variable set to nothing counts as no key
+        assert_eq!(resolve("${VIBEMATE_EMPTY_KEY}"), None);
+    }
+
+    #[test]
+    fn test_passthrough_auth_only_without_api_key() {
+        let mut provider = test_provider(None);
+        provider.passthrough_auth = true;
+        let mut client_headers = HeaderMap::new();
+        client_headers.insert(header::AUTHORIZATION, "Bearer client-key".parse().unwrap());
+        let build = |provider: &Provider| {
+            let req = Client::new().post("http://localhost/v1/chat/completions");
+            add_api_key_auth(req, provider, &client_headers).build().unwrap()
+        };
+
+        // A stored key always wins
+        provider.api_key = Some("sk-stored".to_string());
+        let req = build(&provider);
+        assert_eq!(req.headers()[header::AUTHORIZATION], "Bearer sk-stored");
+
+        provider.api_key = Some(String::new());
+        let req = build(&provider);
+        assert_eq!(req.headers()[header::AUTHORIZATION], "Bearer client-key");
+
+        provider.passthrough_auth = false;
+        let req = build(&provider);
+        assert!(req.headers().get(header::AUTHORIZATION).is_none());
+    }
+
+    #[test]
+    fn test_rule_headers_override_client_headers() {
+        let resolved = ResolvedProvider {
+            provider: test_provider(None),
+            final_model: "gpt-4o".to_string(),
+            model_rewritten: false,
+            rule_id: Some("rule".to_string()),
+            fallback: false,
+            translate: false,
+            add_headers: HashMap::from([
+                ("X-Title".to_string(), "Vibe Mate".to_string()),
+                ("OpenAI-Organization".to_string(), "org-rule".to_string()),
+            ]),
+        };
+        let mut client_headers = HeaderMap::new();
+        client_headers.insert("openai-organization", HeaderValue::from_static("org-client"));
+        client_headers.insert("x-client", HeaderValue::from_static("kept"));
+        client_headers.insert(header::USER_AGENT, HeaderValue::from_static("client/1.0"));
+
+        let req = apply_request_headers(
+            Client::new().post("http://localhost/v1/chat/completions"),
+            &client_headers,
+            &resolved,
+            None,
+        )
+        .build()
+        .unwrap();
+        let headers = req.headers();
+        assert_eq!(headers["x-title"], "Vibe Mate");
+        assert_eq!(headers.get_all("openai-organization").iter().count(), 1);
+        assert_eq!(headers["openai-organization"], "org-rule");
+        assert_eq!(headers["x-client"], "kept");
+        assert_eq!(headers[header::USER_AGENT], "client/1.0");
+
+        // A configured User-Agent replaces the client's, with the provider's taking priority
+        let app = AppConfig {
+            user_agent: Some("vibe-mate".to_string()),
+            ..AppConfig::default()
+        };
+        let mut provider = test_provider(None);
+        assert_eq!(upstream_user_agent(&provider, &app), Some("vibe-mate"));
+        provider.user_agent = Some("gateway-client/2".to_string());
+        let user_agent = upstream_user_agent(&provider, &app);
+        assert_eq!(user_agent, Some("gateway-client/2"));
+        let req = apply_request_headers(
+            Client::new().post("http://localhost/v1/chat/completions"),
+            &client_headers,
+            &resolved,
+            user_agent,
+        )
+        .build()
+        .unwrap();
+        assert_eq!(req.headers().get_all(header::USER_AGENT).iter().count(), 1);
+        assert_eq!(req.headers()[header::USER_AGENT], "gateway-client/2");
+    }
+
+    #[test]
+    fn test_routing_debug_headers() {
+        let resolved = ResolvedProvider {
+            provider: test_provider(None),
+            final_model: "gpt-4o".to_string(),
+            model_rewritten: false,
+            rule_id: None,
+            fallback: false,
+            translate: false,
+            add_headers: HashMap::new(),
+        };
+        let headers = routing_debug_headers(&resolved);
+        assert_eq!(headers["x-vibemate-provider"], "Test");
+        assert_eq!(headers["x-vibemate-model"], "gpt-4o");
+        assert_eq!(headers["x-vibemate-rule"], "default");
+
+        let mut upstream = reqwest::header::HeaderMap::new();
+        upstream.insert("x-vibemate-model", "upstream".parse().unwrap());
+        let response = append_missing_headers(Response::builder(), &upstream, &headers)
+            .body(Body::empty())
+            .unwrap();
+        assert!(response.headers().get("x-vibemate-model").is_none());
+        assert_eq!(response.headers()["x-vibemate-provider"], "Test");
+    }
+
+    #[test]
+    fn test_translate_only_for_anthropic_to_google() {
+        let mut gemini = test_provider(None);
+        gemini.provider_type = ProviderType::Google;
+        let mut rule = RoutingRule::new(
+            gemini.id.clone(),
+            "gemini-*".to_string(),
+            1,
+            RuleType::Model,
+            ApiGroup::Anthropic,
+        );
+        rule.translate = true;
+        let config = VibeMateConfig {
+            providers: vec![gemini.clone()],
+            routing_rules: vec![rule],
+            ..VibeMateConfig::default()
+        };
+
+        let resolved = resolve_provider(
+            &config,
+            ApiGroup::Anthropic,
+            "/api/anthropic/v1/messages",
+            Some("gemini-2.5-pro"),
+            None,
+        )
+        .unwrap();
+        assert!(resolved.translate);
+
+        // The default fallback never translates
+        let resolved = resolve_provider(
+            &config,
+            ApiGroup::Anthropic,
+            "/api/anthropic/v1/messages",
+            Some("claude-sonnet-4"),
+            None,
+        )
+        .unwrap();
+        assert!(!resolved.translate);
+    }
+
+    #[test]
+    fn test_add_oauth_header_merges_beta_flags() {
+        let mut client_headers = HeaderMap::new();
+        client_headers.insert("anthropic-beta", "claude-code-20250219".parse().unwrap());
+        client_headers.insert("anthropic-version", "2023-06-01".parse().unwrap());
+
+        let request = add_oauth_header(
+            Client::new().post("http://localhost/v1/messages"),
+            "token",
+            &client_headers,
+        )
+        .build()
+        .unwrap();
+        assert_eq!(request.headers()["authorization"], "Bearer token");
+        assert_eq!(
+            request.headers()["anthropic-beta"],
+            "claude-code-20250219,oauth-2025-04-20"
+        );
+        assert!(request.headers().get("anthropic-version").is_none());
+    }
+
+    #[test]
+    fn test_model_alias_resolved_before_routing() {
+        let alias_provider = test_provider(None);
+        let target_provider = test_provider(None);
+        let config = VibeMateConfig {
+            providers: vec![alias_provider.clone(), target_provider.clone()],
+            routing_rules: vec![
+                RoutingRule::new(
+                    alias_provider.id.clone(),
+                    "fast".to_string(),
+                    1,
+                    RuleType::Model,
+                    ApiGroup::OpenAI,
+                ),
+                RoutingRule::new(
+                    target_provider.id.clone(),
+                    "gpt-4o-*".to_string(),
+                    2,
+                    RuleType::Model,
+                    ApiGroup::OpenAI,
+                ),
+            ],
+            ..VibeMateConfig::default()
+        };
+        let aliases = HashMap::from([("fast".to_string(), "gpt-4o-mini".to_string())]);
+
+        let (model, applied) = resolve_model_alias(&aliases, Some("fast".to_string()));
+        assert!(applied);
+        assert_eq!(model.as_deref(), Some("gpt-4o-mini"));
+
+        let resolved = resolve_provider(
+            &config,
+            ApiGroup::OpenAI,
+            "/api/openai/v1/chat",
+            model.as_deref(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(resolved.provider.id, target_provider.id);
+        assert_eq!(resolved.final_model, "gpt-4o-mini");
+
+        let body = rewrite_model_in_body(
+            &Bytes::from(r#"{"model":"fast","stream":true}"#),
+            &resolved.final_model,
+        );
+        assert_eq!(
+            extract_model_from_body(&Bytes::from(body)).as_deref(),
+            Some("gpt-4o-mini")
+        );
+
+        let (model, applied) = resolve_model_alias(&aliases, Some("gpt-4o".to_string()));
+        assert!(!applied);
+        assert_eq!(model.as_deref(), Some("gpt-4o"));
+    }
+
+    #[test]
+    fn test_stage_timer_laps_sum_to_total() {
+        let mut timer = StageTimer::start();
+        let mut timings = RequestTimings::default();
+        let pause = || std::thread::sleep(Duration::from_millis(2));
+
+        pause();
+        timings.body_read_ms = timer.lap();
+        pause();
+        timings.routing_ms = timer.lap();
+        pause();
+        timings.body_transform_ms = timer.lap();
+        pause();
+        timings.upstream_ms = timer.lap();
+        pause();
+        timings.response_ms = timer.lap();
+        timings.total_ms = timer.total();
+
+        let stages = [
+            timings.body_read_ms,
+            timings.routing_ms,
+            timings.body_transform_ms,
+            timings.upstream_ms,
+            timings.response_ms,
+        ];
+        assert!(stages.iter().all(|ms| *ms >= 2.0));
+        let sum: f64 = stages.iter().sum();
+        assert!((sum - timings.total_ms).abs() < 0.01);
+    }
+
+    #[tokio::test]
+    async fn test_check_port_available() {
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(!check_port_available(port).await.available);
+
+        drop(listener);
+        let availability = check_port_available(port).await;
+        assert!(availability.available);
+        assert!(availability.conflicting_process.is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_parse_lsof_process() {
+        assert_eq!(
+            parse_lsof_process("p4242\ncnode\n").as_deref(),
+            Some("node (pid 4242)")
+        );
+        assert_eq!(parse_lsof_process(""), None);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_provider_permit_rejects_when_full() {
+        let semaphore = Arc::new(Semaphore::new(1));
+        let held = acquire_provider_permit(Some(semaphore.clone()), "Test", Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert!(held.is_some());
+
+        let rejected =
+            acquire_provider_permit(Some(semaphore.clone()), "Test", Duration::from_millis(10)).await;
+        assert_eq!(rejected.unwrap_err().status(), StatusCode::TOO_MANY_REQUESTS);
+
+        drop(held);
+        assert!(
+            acquire_provider_permit(Some(semaphore), "Test", Duration::from_millis(10))
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cors_layer_restricts_origins() {
+        use tower::ServiceExt;
+
+        async fn allowed_origin(origins: Vec<&str>, origin: &str) -> Option<String> {
+            let origins: Vec<String> = origins.into_iter().map(String::from).collect();
+            let app = Router::new()
+                .route("/health", any(health_check))
+                .layer(cors_layer(&origins));
+            let req = Request::builder()
+                .uri("/health")
+                .header(header::ORIGIN, origin)
+                .body(Body::empty())
+                .unwrap();
+            let response = app.oneshot(req).await.unwrap();
+            response
+                .headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .map(|v| v.to_str().unwrap().to_string())
+        }
+
+        assert_eq!(
+            allowed_origin(vec![], "http://evil.example").await.as_deref(),
+            Some("*")
+        );
+        assert_eq!(
+            allowed_origin(vec!["https://app.example.com"], "https://app.example.com")
+                .await
+                .as_deref(),
+            Some("https://app.example.com")
+        );
+        assert!(allowed_origin(vec!["https://app.example.com"], "http://evil.example")
+            .await
+            .is_none());
+    }
+
+    #[test]
+    fn test_response_transform_drops_configured_keys() {
+        let transform = ResponseTransform {
+            drop_keys: vec!["provider".to_string(), "native_finish_reason".to_string()],
+            rename_keys: HashMap::new(),
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, "application/json".parse().unwrap());
+        let body = br#"{"id":"gen-1","provider":"Groq","choices":[{"index":0,
+            "native_finish_reason":"stop","message":{"role":"assistant","content":"hi"}}]}"#;
+
+        let out = transform_json_body(&headers, body, &transform).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "id": "gen-1",
+                "choices": [{"index": 0, "message": {"role": "assistant", "content": "hi"}}]
+            })
+        );
+
+        headers.insert(header::CONTENT_TYPE, "text/plain".parse().unwrap());
+        assert!(transform_json_body(&headers, body, &transform).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_gzip_response_passes_through_verbatim() {
+        // gzip of `{"ok":true}`
+        const GZIP_BODY: &[u8] = &[
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xab, 0x56, 0xca, 0xcf,
+            0x56, 0xb2, 0x2a, 0x29, 0x2a, 0x4d, 0xad, 0x05, 0x00, 0x90, 0x5f, 0xd4, 0xa7, 0x0b,
+            0x00, 0x00, 0x00,
+        ];
+        let app = Router::new().route(
+            "/v1/models",
+            axum::routing::get(|| async {
+                (
+                    [
+                        (header::CONTENT_TYPE, "application/json"),
+                        (header::CONTENT_ENCODING, "gzip"),
+                    ],
+                    GZIP_BODY,
+                )
+            }),
+        );
+        let addr = spawn_upstream(app).await;
+
+        let client = create_http_client(&VibeMateConfig::default());
+        let upstream = client
+            .get(format!("http://{}/v1/models", addr))
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .send()
+            .await
+            .unwrap();
+        let transform = ResponseTransform {
+            drop_keys: vec!["ok".to_string()],
+            rename_keys: HashMap::new(),
+        };
+        let response =
+            handle_regular_response(upstream, None, &HeaderMap::new(), Some(&transform), None)
+                .await
+                .unwrap();
+
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], GZIP_BODY.len().to_string());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], GZIP_BODY);
+    }
+
+    #[test]
+    fn test_azure_target_url() {
+        let mut provider = Provider::new_model(
+            "Azure".to_string(),
+            ProviderType::Azure,
+            "https://res.openai.azure.com".to_string(),
+            "key".to_string(),
+        );
+        let base = "https://res.openai.azure.com";
+        assert_eq!(
+            azure_target_url(base, &provider, "gpt-4o", "/v1/chat/completions"),
+            format!("{base}/openai/deployments/gpt-4o/chat/completions?api-version=2024-10-21")
+        );
+
+        provider.deployment = Some("prod-gpt".to_string());
+        provider.api_version = Some("2025-01-01-preview".to_string());
+        assert_eq!(
+            azure_target_url(base, &provider, "gpt-4o", "/embeddings"),
+            format!("{base}/openai/deployments/prod-gpt/embeddings?api-version=2025-01-01-preview")
+        );
+    }
+
+    #[test]
+    fn test_sticky_session_routing() {
+        let first = test_provider(None);
+        let second = test_provider(None);
+        let rule = |provider: &Provider| {
+            RoutingRule::new(
+                provider.id.clone(),
+                "gpt-*".to_string(),
+                1,
+                RuleType::Model,
+                ApiGroup::OpenAI,
+            )
+        };
+        let config = VibeMateConfig {
+            providers: vec![first.clone(), second.clone()],
+            routing_rules: vec![rule(&first), rule(&second)],
+            ..VibeMateConfig::default()
+        };
+        let resolve = |key: Option<&str>| {
+            resolve_provider(&config, ApiGroup::OpenAI, "/api/openai/v1/chat", Some("gpt-4o"), key)
+                .unwrap()
+                .provider
+                .id
+        };
+
+        // Without a key the first matching rule wins, as before
+        assert_eq!(resolve(None), first.id);
+
+        // A key always maps to the same provider, and keys spread over both
+        let picked: Vec<String> = (0..32).map(|i| resolve(Some(&format!("session-{i}")))).collect();
+        for (i, id) in picked.iter().enumerate() {
+            assert_eq!(resolve(Some(&format!("session-{i}"))), *id);
+        }
+        assert!(picked.contains(&first.id) && picked.contains(&second.id));
+    }
+
+    #[test]
+    fn test_provider_curl_example() {
+        let provider = Provider::new_model(
+            "Anthropic".to_string(),
+            ProviderType::Anthropic,
+            "https://api.anthropic.com".to_string(),
+            "sk-ant-secret-key-1234".to_string(),
+        );
+        let curl = provider_curl_example(
+            &provider,
+            "https://api.anthropic.com/",
+            "claude-sonnet-4",
+            &AppConfig::default(),
+        );
+        assert!(curl.starts_with("curl -X POST 'https://api.anthropic.com/v1/messages' \\\n"));
+        assert!(curl.contains("-H 'x-api-key: sk-a...1234'"));
+        assert!(curl.contains("-H 'anthropic-version: 2023-06-01'"));
+        assert!(curl.contains("\"model\":\"claude-sonnet-4\""));
+        assert!(!curl.contains("secret"));
+    }
+
+    #[test]
+    fn test_force_model_overrides_client_and_rule_models() {
+        let mut forced = test_provider(None);
+        forced.force_model = Some("only-model".to_string());
+        let mut rule = RoutingRule::new(
+            forced.id.clone(),
+            "gpt-*".to_string(),
+            1,
+            RuleType::Model,
+            ApiGroup::OpenAI,
+        );
+        rule.model_rewrite = Some("gpt-4o-mini".to_string());
+        let config = VibeMateConfig {
+            providers: vec![forced.clone()],
+            routing_rules: vec![rule],
+            ..VibeMateConfig::default()
+        };
+
+        // Through a rewriting rule and through the default-provider fallback
+        for model in ["gpt-4o", "claude-sonnet-4"] {
+            let resolved = resolve_provider(
+                &config,
+                ApiGroup::OpenAI,
+                "/api/openai/v1/chat",
+                Some(model),
+                None,
+            )
+            .unwrap();
+            assert!(resolved.model_rewritten);
+            let body = Bytes::from(format!(r#"{{"model":"{}","messages":[]}}"#, model));
+            let sent: serde_json::Value =
+                serde_json::from_slice(&rewrite_model_in_body(&body, &resolved.final_model))
+                    .unwrap();
+            assert_eq!(sent["model"], "only-model");
+        }
+
+        let resolved = override_provider(&config, &forced.id, Some("gpt-4o")).unwrap();
+        assert_eq!(resolved.final_model, "only-model");
+    }
+
+    #[tokio::test]
+    async fn test_options_answered_without_upstream_call() {
+        use std::sync::atomic::AtomicUsize;
+        use tower::ServiceExt;
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let upstream = Router::new().fallback(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async { "upstream" }
+        });
+        let addr = spawn_upstream(upstream).await;
+
+        let provider =
+            upstream_provider("Upstream", ProviderType::OpenAI, format!("http://{}", addr));
+        let TestProxy { app, _config_dir, .. } =
+            spawn_test_proxy(|c| c.providers = vec![provider]).await;
+
+        let request = |method: Method, origin: Option<&str>| {
+            let mut builder = Request::builder()
+                .method(method)
+                .uri("/api/openai/v1/chat/completions");
+            if let Some(origin) = origin {
+                builder = builder
+                    .header(header::ORIGIN, origin)
+                    .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST");
+            }
+            builder.body(Body::from(r#"{"model":"gpt-4o"}"#)).unwrap()
+        };
+
+        for origin in [None, Some("http://localhost:5173")] {
+            let response = app.clone().oneshot(request(Method::OPTIONS, origin)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+
+        let response = app.oneshot(request(Method::POST, None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_idempotency_key_stable_across_retries() {
+        use tower::ServiceExt;
+
+        // The upstream rejects every first attempt with a retryable 503
+        let (upstream, seen) = recording_upstream(
+            |_, headers, _| {
+                headers
+                    .get(IDEMPOTENCY_KEY_HEADER)
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_string)
+            },
+            |seen| {
+                if seen.len() % 2 == 1 {
+                    StatusCode::SERVICE_UNAVAILABLE
+                } else {
+                    StatusCode::OK
+                }
+            },
+        );
+        let addr = spawn_upstream(upstream).await;
+
+        let mut provider =
+            upstream_provider("Upstream", ProviderType::OpenAI, format!("http://{}", addr));
+        provider.idempotency_keys = true;
+        let TestProxy { app, _config_dir, .. } = spawn_test_proxy(|c| {
+            c.providers = vec![provider];
+            c.app.retry_base_delay_ms = 1;
+        })
+        .await;
+
+        let request = |client_key: Option<&str>| {
+            let mut builder = Request::builder()
+                .method(Method::POST)
+                .uri("/api/openai/v1/chat/completions");
+            if let Some(key) = client_key {
+                builder = builder.header(IDEMPOTENCY_KEY_HEADER, key);
+            }
+            builder.body(Body::from(r#"{"model":"gpt-4o"}"#)).unwrap()
+        };
+        for client_key in [None, None, Some("client-key")] {
+            let response = app.clone().oneshot(request(client_key)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let seen = seen.lock().unwrap().clone();
+        assert_eq!(seen.len(), 6);
+        assert!(seen[0].is_some());
+        assert_eq!(seen[0], seen[1]);
+        assert_eq!(seen[2], seen[3]);
+        assert_ne!(seen[0], seen[2]);
+        assert_eq!(seen[4].as_deref(), Some("client-key"));
+        assert_eq!(seen[5].as_deref(), Some("client-key"));
+    }
+
+    #[tokio::test]
+    async fn test_paused_proxy_does_not_forward() {
+        use std::sync::atomic::AtomicUsize;
+        use tower::ServiceExt;
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let upstream = Router::new().fallback(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async { "upstream" }
+        });
+        let addr = spawn_upstream(upstream).await;
+
+        let provider =
+            upstream_provider("Upstream", ProviderType::OpenAI, format!("http://{}", addr));
+        let TestProxy { app, server, _config_dir } =
+            spawn_test_proxy(|c| c.providers = vec![provider]).await;
+        let request = || {
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/openai/v1/chat/completions")
+                .body(Body::from(r#"{"model":"gpt-4o"}"#))
+                .unwrap()
+        };
+
+        server.pause();
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("paused"));
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+
+        server.resume();
+        let response = app.oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_spend_limit_fails_over_then_rejects() {
+        use crate::models::{SpendLimit, SpendPeriod};
+        use tower::ServiceExt;
+
+        // Records which provider (first path segment) each request reached
+        let (upstream, seen) = recording_upstream(
+            |uri, _, _| uri.path().split('/').nth(1).unwrap_or_default().to_string(),
+            |_| {
+                axum::Json(serde_json::json!({
+                    "usage": { "prompt_tokens": 500, "completion_tokens": 250 }
+                }))
+            },
+        );
+        let addr = spawn_upstream(upstream).await;
+
+        let provider = |name: &str| {
+            upstream_provider(name, ProviderType::OpenAI, format!("http://{}/{}", addr, name))
+        };
+        let (mut limited, backup) = (provider("limited"), provider("backup"));
+        limited.input_price_per_1k = Some(0.5);
+        limited.output_price_per_1k = Some(1.0);
+        limited.spend_limit = Some(SpendLimit {
+            limit_usd: 1.0,
+            period: SpendPeriod::Daily,
+        });
+        let rule = RoutingRule::new(
+            limited.id.clone(),
+            "gpt-*".to_string(),
+            1,
+            RuleType::Model,
+            ApiGroup::OpenAI,
+        );
+        let TestProxy { app, server, _config_dir } = spawn_test_proxy(|c| {
+            c.providers = vec![limited, backup];
+            c.routing_rules = vec![rule];
+        })
+        .await;
+        let request = || {
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/openai/v1/chat/completions")
+                .body(Body::from(r#"{"model":"gpt-4o"}"#))
+                .unwrap()
+        };
+
+        // $0.50 + $0.50 reaches the $1 limit
+        for _ in 0..2 {
+            let response = app.clone().oneshot(request()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let spend = server.provider_spend().await;
+        assert!((spend[0].spent_usd - 1.0).abs() < 1e-9);
+
+        // The rule's provider is skipped like a disabled one and the default takes over
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(*seen.lock().unwrap(), ["limited", "limited", "backup"]);
+
+        // With no candidate left the request is refused
+        server
+            .config_store()
+            .update(|c| c.providers[1].enabled = false)
+            .await
+            .unwrap();
+        let response = app.oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(seen.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_metered_provider_gets_uncompressed_responses() {
+        use axum::response::IntoResponse;
+        use tower::ServiceExt;
+
+        // gzip of `{"usage":{"prompt_tokens":500,"completion_tokens":250}}`
+        const GZIP_USAGE: &[u8] = &[
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xab, 0x56, 0x2a, 0x2d,
+            0x4e, 0x4c, 0x4f, 0x55, 0xb2, 0xaa, 0x56, 0x2a, 0x28, 0xca, 0xcf, 0x2d, 0x28, 0x89,
+            0x2f, 0xc9, 0xcf, 0x4e, 0xcd, 0x2b, 0x56, 0xb2, 0x32, 0x35, 0x30, 0xd0, 0x51, 0x4a,
+            0x06, 0x0a, 0xe5, 0xa4, 0x96, 0x64, 0xe6, 0xe7, 0xc1, 0xc5, 0x8d, 0x4c, 0x0d, 0x6a,
+            0x6b, 0x01, 0x84, 0x8d, 0xfc, 0x5e, 0x37, 0x00, 0x00, 0x00,
+        ];
+        // The upstream compresses whenever the request accepts gzip
+        let (upstream, seen) = recording_upstream(
+            |_, headers, _| {
+                headers
+                    .get(header::ACCEPT_ENCODING)
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_string)
+            },
+            |seen| {
+                if seen.last().unwrap().as_ref().is_some_and(|v| v.contains("gzip")) {
+                    ([(header::CONTENT_ENCODING, "gzip")], GZIP_USAGE).into_response()
+                } else {
+                    axum::Json(serde_json::json!({
+                        "usage": { "prompt_tokens": 500, "completion_tokens": 250 }
+                    }))
+                    .into_response()
+                }
+            },
+        );
+        let addr = spawn_upstream(upstream).await;
+
+        let provider = |name: &str| {
+            upstream_provider(name, ProviderType::OpenAI, format!("http://{}/{}", addr, name))
+        };
+        let (mut metered, plain) = (provider("metered"), provider("plain"));
+        metered.input_price_per_1k = Some(0.5);
+        metered.output_price_per_1k = Some(1.0);
+        let rule = RoutingRule::new(
+            plain.id.clone(),
+            "plain-*".to_string(),
+            1,
+            RuleType::Model,
+            ApiGroup::OpenAI,
+        );
+        let TestProxy { app, server, _config_dir } = spawn_test_proxy(|c| {
+            c.providers = vec![metered, plain];
+            c.routing_rules = vec![rule];
+        })
+        .await;
+        let request = |model: &str| {
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/openai/v1/chat/completions")
+                .header(header::ACCEPT_ENCODING, "gzip")
+                .body(Body::from(format!(r#"{{"model":"{}"}}"#, model)))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request("gpt-4o")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+        axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let spend = server.provider_spend().await;
+        assert!((spend[0].spent_usd - 0.5).abs() < 1e-9);
+
+        // Providers without prices keep the client's compression
+        let response = app.oneshot(request("plain-model")).await.unwrap();
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(*seen.lock().unwrap(), [None, Some("gzip".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_sse_keepalive_between_events() {
+        let (tx, rx) = tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(4);
+        let upstream = futures_util::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|chunk| (chunk, rx))
+        });
+        let mut stream = Box::pin(with_sse_keepalive(upstream, Duration::from_millis(20)));
+
+        // Idle before the first event
+        assert_eq!(stream.next().await.unwrap().unwrap(), SSE_KEEPALIVE);
+
+        // No ping inside a partially relayed event
+        tx.send(Ok(Bytes::from_static(b"data: {\"a\""))).await.unwrap();
+        assert_eq!(stream.next().await.unwrap().unwrap(), &b"data: {\"a\""[..]);
+        let sender = tx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(70)).await;
+            sender.send(Ok(Bytes::from_static(b":1}\n\n"))).await.unwrap();
+        });
+        assert_eq!(stream.next().await.unwrap().unwrap(), &b":1}\n\n"[..]);
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), SSE_KEEPALIVE);
+
+        // Stops with the stream
+        drop(tx);
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_no_keepalive_in_encoded_streams() {
+        // The only event arrives after a pause longer than the keepalive interval
+        let slow = |encoding: Option<&'static str>| async move {
+            let body = Body::from_stream(futures_util::stream::once(async {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                Ok::<_, std::io::Error>(Bytes::from_static(b"data: {}\n\n"))
+            }));
+            let mut response =
+                Response::builder().header(header::CONTENT_TYPE, "text/event-stream");
+            if let Some(encoding) = encoding {
+                response = response.header(header::CONTENT_ENCODING, encoding);
+            }
+            response.body(body).unwrap()
+        };
+        let upstream = Router::new()
+            .route("/plain", axum::routing::get(move || slow(None)))
+            .route("/encoded", axum::routing::get(move || slow(Some("br"))));
+        let addr = spawn_upstream(upstream).await;
+
+        let client = create_http_client(&VibeMateConfig::default());
+        let mut bodies = Vec::new();
+        for path in ["plain", "encoded"] {
+            let upstream = client
+                .get(format!("http://{}/{}", addr, path))
+                .send()
+                .await
+                .unwrap();
+            let keepalive = Some(Duration::from_millis(20));
+            let response =
+                handle_streaming_response(upstream, None, &HeaderMap::new(), None, keepalive)
+                    .await
+                    .unwrap();
+            bodies.push(axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap());
+        }
+
+        assert!(bodies[0].starts_with(SSE_KEEPALIVE));
+        assert_eq!(&bodies[1][..], b"data: {}\n\n");
+    }
+
+    #[tokio::test]
+    async fn test_route_prefix_mounts_api_routes() {
+        use tower::ServiceExt;
+
+        let (upstream, seen) =
+            recording_upstream(|uri, _, _| uri.path().to_string(), |_| "upstream");
+        let addr = spawn_upstream(upstream).await;
+
+        let provider =
+            upstream_provider("Upstream", ProviderType::OpenAI, format!("http://{}/v1", addr));
+        let TestProxy { app, _config_dir, .. } = spawn_test_proxy(|c| {
+            c.providers = vec![provider];
+            c.app.route_prefix = "/vibemate".to_string();
+        })
+        .await;
+        let request = |method: Method, uri: &str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::from(r#"{"model":"gpt-4o"}"#))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(request(Method::POST, "/vibemate/api/openai/v1/chat/completions"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(*seen.lock().unwrap(), vec!["/v1/chat/completions".to_string()]);
+
+        // The unprefixed API routes are gone; health checks answer in both places
+        let response = app
+            .clone()
+            .oneshot(request(Method::POST, "/api/openai/v1/chat/completions"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        for uri in ["/health", "/vibemate/health"] {
+            let response = app.clone().oneshot(request(Method::GET, uri)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        assert_eq!(seen.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_multipart_body_forwarded_unchanged() {
+        use tower::ServiceExt;
+
+        // Path, Content-Type values and body of each forwarded request
+        let (upstream, seen) = recording_upstream(
+            |uri, headers, body| {
+                let content_types: Vec<String> = headers
+                    .get_all(header::CONTENT_TYPE)
+                    .iter()
+                    .map(|v| v.to_str().unwrap().to_string())
+                    .collect();
+                (uri.path().to_string(), content_types, body.clone())
+            },
+            |_| "upstream",
+        );
+        let addr = spawn_upstream(upstream).await;
+
+        let provider = |name: &str| {
+            upstream_provider(name, ProviderType::OpenAI, format!("http://{}/{}", addr, name))
+        };
+        let (fallback, audio) = (provider("fallback"), provider("audio"));
+        // A path rule with a model rewrite must still leave the multipart body alone
+        let mut rule = RoutingRule::new(
+            audio.id.clone(),
+            "/api/openai/v1/audio/*".to_string(),
+            1,
+            RuleType::Path,
+            ApiGroup::OpenAI,
+        );
+        rule.model_rewrite = Some("whisper-large".to_string());
+        let TestProxy { app, _config_dir, .. } = spawn_test_proxy(|c| {
+            c.providers = vec![fallback, audio];
+            c.routing_rules = vec![rule];
+        })
+        .await;
+
+        let body = Bytes::from_static(
+            b"--XYZ\r\nContent-Disposition: form-data; name=\"model\"\r\n\r\nwhisper-1\r\n\
+              --XYZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.wav\"\r\n\
+              Content-Type: audio/wav\r\n\r\nRIFF\x00\x01\xff{\"model\":1}\r\n--XYZ--\r\n",
+        );
+        let request = |path: &str| {
+            Request::builder()
+                .method(Method::POST)
+                .uri(path)
+                .header(header::CONTENT_TYPE, "multipart/form-data; boundary=XYZ")
+                .body(Body::from(body.clone()))
+                .unwrap()
+        };
+
+        for path in ["/api/openai/v1/audio/transcriptions", "/api/openai/v1/files"] {
+            let response = app.clone().oneshot(request(path)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen[0].0, "/audio/v1/audio/transcriptions");
+        assert_eq!(seen[1].0, "/fallback/v1/files");
+        assert!(seen.iter().all(|(_, _, forwarded)| *forwarded == body));
+        assert!(seen
+            .iter()
+            .all(|(_, content_types, _)| *content_types == ["multipart/form-data; boundary=XYZ"]));
+    }
+
+    #[tokio::test]
+    async fn test_anthropic_count_tokens_routes_by_model() {
+        use tower::ServiceExt;
+
+        let (upstream, seen) = recording_upstream(
+            |uri, _, body| {
+                let body: serde_json::Value = serde_json::from_slice(body).unwrap_or_default();
+                (uri.path().to_string(), body)
+            },
+            |_| r#"{"input_tokens":12}"#,
+        );
+        let addr = spawn_upstream(upstream).await;
+
+        let provider = |name: &str, provider_type: ProviderType| {
+            upstream_provider(name, provider_type, format!("http://{}/{}", addr, name))
+        };
+        let fallback = provider("fallback", ProviderType::Anthropic);
+        let claude = provider("claude", ProviderType::Anthropic);
+        let gemini = provider("gemini", ProviderType::Google);
+        let mut claude_rule = RoutingRule::new(
+            claude.id.clone(),
+            "claude-*".to_string(),
+            1,
+            RuleType::Model,
+            ApiGroup::Anthropic,
+        );
+        claude_rule.model_rewrite = Some("claude-sonnet-4-5".to_string());
+        let mut gemini_rule = RoutingRule::new(
+            gemini.id.clone(),
+            "gemini-*".to_string(),
+            2,
+            RuleType::Model,
+            ApiGroup::Anthropic,
+        );
+        gemini_rule.translate = true;
+        let rule_ids = vec![Some(gemini_rule.id.clone()), Some(claude_rule.id.clone())];
+        let TestProxy { app, server, _config_dir } = spawn_test_proxy(|c| {
+            c.providers = vec![fallback, claude, gemini];
+            c.routing_rules = vec![claude_rule, gemini_rule];
+        })
+        .await;
+        let request = |model: &str| {
+            let body = serde_json::json!({
+                "model": model,
+                "system": "You are terse.",
+                "messages": [{"role": "user", "content": "Hello"}],
+            });
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/anthropic/v1/messages/count_tokens")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(request("claude-latest")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], br#"{"input_tokens":12}"#);
+
+        // A count routed to a translating rule is refused rather than turned into a generation
+        let response = app.oneshot(request("gemini-2.5-pro")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].0, "/claude/v1/messages/count_tokens");
+        assert_eq!(seen[0].1["model"], "claude-sonnet-4-5");
+        assert_eq!(seen[0].1["messages"][0]["content"], "Hello");
+
+        // The request inspector records which rule routed each request
+        let records = server.recent_requests();
+        let rules: Vec<Option<String>> = records.iter().map(|r| r.rule.clone()).collect();
+        assert_eq!(rules, rule_ids);
+        assert!(records.iter().all(|r| !r.fallback));
+    }
+
+    #[tokio::test]
+    async fn test_realtime_websocket_passthrough() {
+        use std::sync::Mutex;
+
+        // Upstream echoes text frames and records the handshake it received
+        type Handshake = (String, Option<String>);
+        let handshakes: Arc<Mutex<Vec<Handshake>>> = Arc::default();
+        let recorded = handshakes.clone();
+        let upstream = Router::new().route(
+            "/v1/realtime",
+            any(move |upgrade: WebSocketUpgrade, uri: Uri, headers: HeaderMap| {
+                let auth = headers
+                    .get(header::AUTHORIZATION)
+                    .map(|v| v.to_str().unwrap().to_string());
+                recorded.lock().unwrap().push((uri.query().unwrap_or_default().to_string(), auth));
+                async move {
+                    upgrade.on_upgrade(|mut socket| async move {
+                        while let Some(Ok(ws::Message::Text(text))) = socket.recv().await {
+                            let reply = format!("echo: {}", text.as_str());
+                            if socket.send(ws::Message::Text(reply.into())).await.is_err() {
+                                break;
+                            }
+                        }
+                    })
+                }
+            }),
+        );
+        let upstream_addr = spawn_upstream(upstream).await;
+
+        let base_url = format!("http://{}/v1", upstream_addr);
+        let provider = upstream_provider("Realtime", ProviderType::OpenAI, base_url);
+        let mut rule = RoutingRule::new(
+            provider.id.clone(),
+            "gpt-realtime*".to_string(),
+            1,
+            RuleType::Model,
+            ApiGroup::OpenAI,
+        );
+        rule.model_rewrite = Some("gpt-4o-realtime-preview".to_string());
+        let TestProxy { app, _config_dir, .. } = spawn_test_proxy(|c| {
+            c.providers = vec![provider];
+            c.routing_rules = vec![rule];
+        })
+        .await;
+        let proxy_addr = spawn_upstream(app).await;
+
+        let url = format!("ws://{}/api/openai/v1/realtime?model=gpt-realtime&x=1", proxy_addr);
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        socket.send(tungstenite::Message::text("hello")).await.unwrap();
+        let reply = socket.next().await.unwrap().unwrap();
+        assert_eq!(reply.into_text().unwrap().as_str(), "echo: hello");
+        socket.close(None).await.unwrap();
+
+        let handshakes = handshakes.lock().unwrap();
+        assert_eq!(
+            *handshakes,
+            vec![(
+                "model=gpt-4o-realtime-preview&x=1".to_string(),
+                Some("Bearer sk-test".to_string())
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_realtime_http_post_is_proxied() {
+        use tower::ServiceExt;
+
+        // WebRTC clients post an SDP offer to the realtime path without upgrading
+        let (upstream, seen) = recording_upstream(
+            |uri, headers, body| {
+                let content_type = headers[header::CONTENT_TYPE].to_str().unwrap().to_string();
+                (uri.path().to_string(), content_type, body.clone())
+            },
+            |_| "v=0 answer",
+        );
+        let addr = spawn_upstream(upstream).await;
+
+        let provider =
+            upstream_provider("Realtime", ProviderType::OpenAI, format!("http://{}/v1", addr));
+        let TestProxy { app, _config_dir, .. } =
+            spawn_test_proxy(|c| c.providers = vec![provider]).await;
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/openai/v1/realtime?model=gpt-realtime")
+            .header(header::CONTENT_TYPE, "application/sdp")
+            .body(Body::from("v=0 offer"))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"v=0 answer");
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [(
+                "/v1/realtime".to_string(),
+                "application/sdp".to_string(),
+                Bytes::from_static(b"v=0 offer")
+            )]
+        );
+    }
+
+    #[test]
+    fn test_realtime_needs_proxy() {
+        let mut app = VibeMateConfig::default().app;
+        let url = "wss://api.openai.com/v1/realtime";
+        assert!(!realtime_needs_proxy(&app, url));
+        app.proxy_mode = ProxyMode::System;
+        assert!(realtime_needs_proxy(&app, url));
+
+        app.proxy_mode = ProxyMode::Custom;
+        app.proxy_url = Some("http://proxy.internal:3128".to_string());
+        assert!(realtime_needs_proxy(&app, url));
+        assert!(!realtime_needs_proxy(&app, "ws://127.0.0.1:8080/v1/realtime"));
+        app.no_proxy = vec![".openai.com".to_string()];
+        assert!(!realtime_needs_proxy(&app, url));
+        app.no_proxy = vec!["*.openai.com".to_string()];
+        assert!(!realtime_needs_proxy(&app, url));
+        app.no_proxy = vec!["ai.com".to_string()];
+        assert!(realtime_needs_proxy(&app, url));
+    }
 }
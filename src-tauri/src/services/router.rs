@@ -1,9 +1,14 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use chrono::Utc;
 use glob::Pattern;
 
-use crate::models::{ApiGroup, CreateRuleInput, RoutingRule, RuleType, UpdateRuleInput};
+use super::proxy::{matches_pattern, rules_in_match_order, select_rule};
+use crate::models::{
+    ApiGroup, CreateRuleInput, PatternTestResult, Provider, RoutingRule, RuleImportError,
+    RuleImportResult, RuleIssue, RulePreview, RulePreviewSample, RuleType, RoutingTableEntry,
+    UpdateRuleInput, VibeMateConfig,
+};
 use crate::storage::ConfigStore;
 
 #[derive(Debug, thiserror::Error)]
@@ -14,6 +19,8 @@ pub enum RouterError {
     Storage(#[from] crate::storage::StorageError),
     #[error("Invalid pattern: {0}")]
     InvalidPattern(String),
+    #[error("Invalid header: {0}")]
+    InvalidHeader(String),
 }
 
 pub struct RouterService {
@@ -27,9 +34,14 @@ impl RouterService {
 
     pub async fn list_rules(&self) -> Result<Vec<RoutingRule>, RouterError> {
         let config = self.store.get_config().await;
-        let (mut rules, has_duplicates) = deduplicate_rules(config.routing_rules);
+        for issue in find_rule_issues(&config) {
+            tracing::warn!("Routing rule {}: {}", issue.rule_id, issue.message);
+        }
+        let (mut rules, removed) = deduplicate_rules(config.routing_rules);
+        warn_if_many_rules(rules.len(), config.app.rule_count_warning);
 
-        if has_duplicates {
+        if !removed.is_empty() {
+            tracing::info!("Removed {} duplicate routing rules", removed.len());
             // Persist cleaned rules so future fetches stay deduped
             let rules_to_save = rules.clone();
             self.store
@@ -59,10 +71,7 @@ impl RouterService {
     }
 
     pub async fn create_rule(&self, input: CreateRuleInput) -> Result<RoutingRule, RouterError> {
-        // Validate the pattern
-        Pattern::new(&input.match_pattern)
-            .map_err(|_| RouterError::InvalidPattern(input.match_pattern.clone()))?;
-        validate_api_group_pattern(&input.api_group, &input.rule_type, &input.match_pattern)?;
+        validate_rule_input(&input)?;
 
         let config = self.store.get_config().await;
 
@@ -79,24 +88,7 @@ impl RouterService {
             return Ok(existing.clone());
         }
 
-        let priority = config
-            .routing_rules
-            .iter()
-            .filter(|r| r.api_group == input.api_group && r.rule_type == input.rule_type)
-            .map(|r| r.priority)
-            .max()
-            .unwrap_or(0)
-            + 1;
-
-        let mut rule = RoutingRule::new(
-            input.provider_id,
-            input.match_pattern,
-            priority,
-            input.rule_type,
-            input.api_group,
-        );
-        rule.model_rewrite = input.model_rewrite;
-        rule.enabled = input.enabled;
+        let rule = new_rule_from_input(&config.routing_rules, input);
 
         let rule_clone = rule.clone();
         self.store
@@ -108,6 +100,53 @@ impl RouterService {
         Ok(rule)
     }
 
+    /// Create many rules with a single config write. Invalid items are reported per item;
+    /// rules duplicating an existing or earlier one are dropped and counted.
+    pub async fn import_rules(
+        &self,
+        inputs: Vec<CreateRuleInput>,
+    ) -> Result<RuleImportResult, RouterError> {
+        let mut errors = Vec::new();
+        let mut valid = Vec::with_capacity(inputs.len());
+        for (index, input) in inputs.into_iter().enumerate() {
+            match validate_rule_input(&input) {
+                Ok(()) => valid.push(input),
+                Err(e) => errors.push(RuleImportError {
+                    index,
+                    match_pattern: input.match_pattern,
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        let mut created = Vec::with_capacity(valid.len());
+        let mut removed = Vec::new();
+        self.store
+            .update(|config| {
+                for input in valid {
+                    let rule = new_rule_from_input(&config.routing_rules, input);
+                    config.routing_rules.push(rule.clone());
+                    created.push(rule);
+                }
+                let (rules, duplicates) =
+                    deduplicate_rules(std::mem::take(&mut config.routing_rules));
+                config.routing_rules = rules;
+                removed = duplicates;
+                warn_if_many_rules(config.routing_rules.len(), config.app.rule_count_warning);
+            })
+            .await?;
+
+        if !removed.is_empty() {
+            tracing::info!("Dropped {} duplicate routing rules on import", removed.len());
+            created.retain(|rule| !removed.iter().any(|r| r.id == rule.id));
+        }
+        Ok(RuleImportResult {
+            created,
+            errors,
+            duplicates_removed: (!removed.is_empty()).then_some(removed.len()),
+        })
+    }
+
     pub async fn update_rule(
         &self,
         id: &str,
@@ -125,6 +164,9 @@ impl RouterService {
         let next_rule_type = input.rule_type.clone().unwrap_or(existing.rule_type);
         let next_pattern = input.match_pattern.clone().unwrap_or(existing.match_pattern);
         validate_api_group_pattern(&next_api_group, &next_rule_type, &next_pattern)?;
+        if let Some(ref headers) = input.add_headers {
+            validate_add_headers(headers)?;
+        }
 
         let id_owned = id.to_string();
         self.store
@@ -148,6 +190,12 @@ impl RouterService {
                     if let Some(enabled) = input.enabled {
                         rule.enabled = enabled;
                     }
+                    if let Some(translate) = input.translate {
+                        rule.translate = translate;
+                    }
+                    if let Some(add_headers) = input.add_headers.clone() {
+                        rule.add_headers = add_headers;
+                    }
                     rule.updated_at = Utc::now();
                 }
             })
@@ -170,13 +218,66 @@ impl RouterService {
         Ok(())
     }
 
+    /// Enable or disable several rules with one config write. Fails without changing
+    /// anything if any id is unknown. Returns the updated rules.
+    pub async fn set_rules_enabled(
+        &self,
+        ids: Vec<String>,
+        enabled: bool,
+    ) -> Result<Vec<RoutingRule>, RouterError> {
+        let config = self.store.get_config().await;
+        if let Some(missing) = ids
+            .iter()
+            .find(|id| !config.routing_rules.iter().any(|r| r.id == **id))
+        {
+            return Err(RouterError::RuleNotFound(missing.clone()));
+        }
+
+        let mut updated = Vec::with_capacity(ids.len());
+        self.store
+            .update(|config| {
+                let now = Utc::now();
+                for rule in config.routing_rules.iter_mut().filter(|r| ids.contains(&r.id)) {
+                    rule.enabled = enabled;
+                    rule.updated_at = now;
+                    updated.push(rule.clone());
+                }
+            })
+            .await?;
+
+        Ok(updated)
+    }
+
+    /// Rules that cannot work as configured, e.g. ones pointing at a deleted provider
+    /// after the config file was edited by hand
+    pub async fn list_rule_issues(&self) -> Result<Vec<RuleIssue>, RouterError> {
+        let config = self.store.get_config().await;
+        Ok(find_rule_issues(&config))
+    }
+
+    /// Put the listed rules first, in the given order, followed by the rest in their current
+    /// priority order. Every rule is renumbered, so no two rules share a priority afterwards.
     pub async fn reorder_rules(&self, rule_ids: Vec<String>) -> Result<(), RouterError> {
         self.store
             .update(|config| {
-                for (index, rule_id) in rule_ids.iter().enumerate() {
-                    if let Some(rule) = config.routing_rules.iter_mut().find(|r| r.id == *rule_id) {
-                        rule.priority = index as i32 + 1;
-                        rule.updated_at = Utc::now();
+                let mut order: Vec<(Option<usize>, i32, usize)> = config
+                    .routing_rules
+                    .iter()
+                    .enumerate()
+                    .map(|(index, rule)| {
+                        let rank = rule_ids.iter().position(|id| *id == rule.id);
+                        (rank, rule.priority, index)
+                    })
+                    .collect();
+                order.sort_by_key(|&(rank, priority, _)| (rank.is_none(), rank, priority));
+
+                let now = Utc::now();
+                for (position, (_, _, index)) in order.into_iter().enumerate() {
+                    let rule = &mut config.routing_rules[index];
+                    let priority = position as i32 + 1;
+                    if rule.priority != priority {
+                        rule.priority = priority;
+                        rule.updated_at = now;
                     }
                 }
             })
@@ -185,6 +286,62 @@ impl RouterService {
         Ok(())
     }
 
+    /// Render the api group -> rule -> provider mapping as a Graphviz DOT graph
+    pub async fn export_routes_dot(&self) -> Result<String, RouterError> {
+        let config = self.store.get_config().await;
+        Ok(render_routes_dot(&config))
+    }
+
+    /// Every rule in the order the proxy tries it, per api group, after deduplication
+    pub async fn routing_table(&self) -> Result<Vec<RoutingTableEntry>, RouterError> {
+        let config = self.store.get_config().await;
+        Ok(build_routing_table(&config))
+    }
+
+    /// Show which rule would win for a sample request if `input` were created.
+    /// Runs the proxy's own rule selection on a copy of the rules; nothing is saved.
+    pub async fn preview_rule(
+        &self,
+        input: CreateRuleInput,
+        sample: RulePreviewSample,
+    ) -> Result<RulePreview, RouterError> {
+        validate_rule_input(&input)?;
+
+        let config = self.store.get_config().await;
+        Ok(preview_rule_against(
+            config.routing_rules,
+            &config.providers,
+            input,
+            &sample,
+        ))
+    }
+
+    /// Check which samples a `match_pattern` would match, using the proxy's own matcher.
+    /// Model rules are matched against model names, path rules against full request paths.
+    pub fn test_pattern(
+        &self,
+        pattern: &str,
+        rule_type: &RuleType,
+        samples: Vec<String>,
+    ) -> PatternTestResult {
+        let error = match Pattern::new(pattern) {
+            Err(e) => Some(e.to_string()),
+            Ok(_) if *rule_type == RuleType::Path && !pattern.starts_with(['/', '*']) => Some(
+                "Path patterns match the full request path and must start with '/'".to_string(),
+            ),
+            Ok(_) => None,
+        };
+        let matches = if error.is_none() {
+            samples
+                .into_iter()
+                .filter(|sample| matches_pattern(pattern, sample))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        PatternTestResult { matches, error }
+    }
+
     /// Match a model name against routing rules
     #[cfg(test)]
     pub fn matches_pattern(pattern: &str, model_name: &str) -> Result<bool, RouterError> {
@@ -194,6 +351,103 @@ impl RouterService {
     }
 }
 
+/// Reject patterns and headers the proxy could not use
+fn validate_rule_input(input: &CreateRuleInput) -> Result<(), RouterError> {
+    Pattern::new(&input.match_pattern)
+        .map_err(|_| RouterError::InvalidPattern(input.match_pattern.clone()))?;
+    validate_api_group_pattern(&input.api_group, &input.rule_type, &input.match_pattern)?;
+    validate_add_headers(&input.add_headers)
+}
+
+/// A rule count past the configured soft cap usually means rules are being duplicated
+fn warn_if_many_rules(count: usize, cap: usize) {
+    if cap > 0 && count > cap {
+        tracing::warn!(
+            "{} routing rules exceed the expected maximum of {}; check for duplicated rules",
+            count,
+            cap
+        );
+    }
+}
+
+/// Build a new rule from input, placed after existing rules of the same api group and type
+fn new_rule_from_input(existing: &[RoutingRule], input: CreateRuleInput) -> RoutingRule {
+    let priority = existing
+        .iter()
+        .filter(|r| r.api_group == input.api_group && r.rule_type == input.rule_type)
+        .map(|r| r.priority)
+        .max()
+        .unwrap_or(0)
+        + 1;
+
+    let mut rule = RoutingRule::new(
+        input.provider_id,
+        input.match_pattern,
+        priority,
+        input.rule_type,
+        input.api_group,
+    );
+    rule.model_rewrite = input.model_rewrite;
+    rule.enabled = input.enabled;
+    rule.translate = input.translate;
+    rule.add_headers = input.add_headers;
+    rule
+}
+
+fn preview_rule_against(
+    mut rules: Vec<RoutingRule>,
+    providers: &[Provider],
+    input: CreateRuleInput,
+    sample: &RulePreviewSample,
+) -> RulePreview {
+    let candidate = new_rule_from_input(&rules, input);
+    let candidate_id = candidate.id.clone();
+    rules.push(candidate);
+
+    let winner = select_rule(
+        &rules,
+        providers,
+        &sample.api_group,
+        &sample.path,
+        sample.model.as_deref(),
+    );
+    RulePreview {
+        winning_rule_id: winner.map(|r| r.id.clone()),
+        is_candidate: winner.is_some_and(|r| r.id == candidate_id),
+    }
+}
+
+/// Rules grouped by api group and listed in match order. Disabled rules, and rules whose
+/// provider is disabled or missing, keep their place but are marked inactive. OpenAI and
+/// Anthropic requests that match none of their group's rules go on to the Generic rows.
+fn build_routing_table(config: &VibeMateConfig) -> Vec<RoutingTableEntry> {
+    let (rules, _) = deduplicate_rules(config.routing_rules.clone());
+    // The proxy pre-sorts by priority before grouping; keep the same tie order
+    let mut rules: Vec<&RoutingRule> = rules.iter().collect();
+    rules.sort_by_key(|r| r.priority);
+
+    let mut table = Vec::with_capacity(rules.len());
+    for api_group in [ApiGroup::OpenAI, ApiGroup::Anthropic, ApiGroup::Generic] {
+        for (index, rule) in rules_in_match_order(&rules, &api_group).into_iter().enumerate() {
+            let provider = config.providers.iter().find(|p| p.id == rule.provider_id);
+            table.push(RoutingTableEntry {
+                position: index + 1,
+                rule_id: rule.id.clone(),
+                api_group: rule.api_group.clone(),
+                rule_type: rule.rule_type.clone(),
+                match_pattern: rule.match_pattern.clone(),
+                priority: rule.priority,
+                provider_id: rule.provider_id.clone(),
+                provider_name: provider.map(|p| p.name.clone()),
+                model_rewrite: rule.model_rewrite.clone(),
+                enabled: rule.enabled,
+                active: rule.enabled && provider.is_some_and(|p| p.enabled),
+            });
+        }
+    }
+    table
+}
+
 fn validate_api_group_pattern(
     api_group: &ApiGroup,
     rule_type: &RuleType,
@@ -208,6 +462,17 @@ fn validate_api_group_pattern(
     Ok(())
 }
 
+/// Rule headers must be valid HTTP header names and values
+fn validate_add_headers(headers: &HashMap<String, String>) -> Result<(), RouterError> {
+    for (name, value) in headers {
+        reqwest::header::HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| RouterError::InvalidHeader(name.clone()))?;
+        reqwest::header::HeaderValue::from_str(value)
+            .map_err(|_| RouterError::InvalidHeader(format!("{}: {}", name, value)))?;
+    }
+    Ok(())
+}
+
 fn api_group_order(api_group: &ApiGroup) -> u8 {
     match api_group {
         ApiGroup::OpenAI => 0,
@@ -223,10 +488,122 @@ fn rule_type_order(rule_type: &RuleType) -> u8 {
     }
 }
 
-fn deduplicate_rules(rules: Vec<RoutingRule>) -> (Vec<RoutingRule>, bool) {
-    let original_len = rules.len();
+fn api_group_label(api_group: &ApiGroup) -> &'static str {
+    match api_group {
+        ApiGroup::OpenAI => "OpenAI (/api/openai)",
+        ApiGroup::Anthropic => "Anthropic (/api/anthropic)",
+        ApiGroup::Generic => "Generic (/api)",
+    }
+}
+
+fn api_group_node(api_group: &ApiGroup) -> &'static str {
+    match api_group {
+        ApiGroup::OpenAI => "group_openai",
+        ApiGroup::Anthropic => "group_anthropic",
+        ApiGroup::Generic => "group_generic",
+    }
+}
+
+fn escape_dot(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Render enabled routing rules as edges from api group nodes to provider nodes.
+/// The global default provider is highlighted, and each group is linked to its own fallback
+/// (the group default, else the global one) with a dashed edge.
+fn render_routes_dot(config: &VibeMateConfig) -> String {
+    let mut rules: Vec<&RoutingRule> = config.routing_rules.iter().filter(|r| r.enabled).collect();
+    rules.sort_by_key(|r| {
+        (
+            api_group_order(&r.api_group),
+            rule_type_order(&r.rule_type),
+            r.priority,
+        )
+    });
+    let default_provider_id = config.default_provider().map(|p| p.id.as_str());
+
+    let mut dot = String::from("digraph routes {\n  rankdir=LR;\n  node [shape=box];\n");
+
+    for api_group in [ApiGroup::OpenAI, ApiGroup::Anthropic, ApiGroup::Generic] {
+        dot.push_str(&format!(
+            "  \"{}\" [label=\"{}\", shape=ellipse];\n",
+            api_group_node(&api_group),
+            api_group_label(&api_group)
+        ));
+    }
+
+    for provider in &config.providers {
+        let is_default = Some(provider.id.as_str()) == default_provider_id;
+        let label = if is_default {
+            format!("{} (default)", provider.name)
+        } else {
+            provider.name.clone()
+        };
+        let style = if is_default {
+            ", style=\"filled,bold\", fillcolor=gold"
+        } else {
+            ""
+        };
+        dot.push_str(&format!(
+            "  \"provider_{}\" [label=\"{}\"{}];\n",
+            escape_dot(&provider.id),
+            escape_dot(&label),
+            style
+        ));
+    }
+
+    for rule in rules {
+        let kind = match rule.rule_type {
+            RuleType::Path => "path",
+            RuleType::Model => "model",
+        };
+        let mut label = format!("#{} {}: {}", rule.priority, kind, rule.match_pattern);
+        if let Some(rewrite) = &rule.model_rewrite {
+            label.push_str(&format!(" -> {}", rewrite));
+        }
+        dot.push_str(&format!(
+            "  \"{}\" -> \"provider_{}\" [label=\"{}\"];\n",
+            api_group_node(&rule.api_group),
+            escape_dot(&rule.provider_id),
+            escape_dot(&label)
+        ));
+    }
+
+    for api_group in [ApiGroup::OpenAI, ApiGroup::Anthropic, ApiGroup::Generic] {
+        if let Some(fallback) = config.default_provider_for(&api_group) {
+            dot.push_str(&format!(
+                "  \"{}\" -> \"provider_{}\" [label=\"fallback\", style=dashed];\n",
+                api_group_node(&api_group),
+                escape_dot(&fallback.id)
+            ));
+        }
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+fn find_rule_issues(config: &VibeMateConfig) -> Vec<RuleIssue> {
+    config
+        .routing_rules
+        .iter()
+        .filter(|r| !config.providers.iter().any(|p| p.id == r.provider_id))
+        .map(|r| RuleIssue {
+            rule_id: r.id.clone(),
+            message: format!(
+                "Provider {} no longer exists; requests fall through to the default provider",
+                r.provider_id
+            ),
+        })
+        .collect()
+}
+
+/// Drop rules repeating an earlier rule's api group, rule type and pattern.
+/// Returns the kept rules and the removed duplicates.
+fn deduplicate_rules(rules: Vec<RoutingRule>) -> (Vec<RoutingRule>, Vec<RoutingRule>) {
     let mut seen = HashSet::new();
-    let mut deduped = Vec::with_capacity(original_len);
+    let mut deduped = Vec::with_capacity(rules.len());
+    let mut removed = Vec::new();
 
     for rule in rules {
         let key = (
@@ -237,11 +614,12 @@ fn deduplicate_rules(rules: Vec<RoutingRule>) -> (Vec<RoutingRule>, bool) {
 
         if seen.insert(key) {
             deduped.push(rule);
+        } else {
+            removed.push(rule);
         }
     }
 
-    let changed = deduped.len() != original_len;
-    (deduped, changed)
+    (deduped, removed)
 }
 
 #[cfg(test)]
@@ -262,4 +640,359 @@ mod tests {
         // Complex patterns
         assert!(RouterService::matches_pattern("*-turbo", "gpt-4-turbo").unwrap());
     }
+
+    #[test]
+    fn test_preview_rule_reports_winner() {
+        let existing = RoutingRule::new(
+            "openai".to_string(),
+            "gpt-4*".to_string(),
+            1,
+            RuleType::Model,
+            ApiGroup::OpenAI,
+        );
+        let candidate = CreateRuleInput {
+            rule_type: RuleType::Model,
+            api_group: ApiGroup::OpenAI,
+            provider_id: "other".to_string(),
+            match_pattern: "gpt-4o*".to_string(),
+            model_rewrite: None,
+            enabled: true,
+            translate: false,
+            add_headers: HashMap::new(),
+        };
+        let sample = |model: &str| RulePreviewSample {
+            model: Some(model.to_string()),
+            path: "/api/openai/v1/chat/completions".to_string(),
+            api_group: ApiGroup::OpenAI,
+        };
+
+        // The existing rule has a better priority, so it still wins
+        let preview =
+            preview_rule_against(vec![existing.clone()], &[], candidate.clone(), &sample("gpt-4o"));
+        assert_eq!(preview.winning_rule_id.as_deref(), Some(existing.id.as_str()));
+        assert!(!preview.is_candidate);
+
+        let preview = preview_rule_against(vec![], &[], candidate.clone(), &sample("gpt-4o-mini"));
+        assert!(preview.is_candidate);
+
+        let preview = preview_rule_against(vec![existing], &[], candidate, &sample("claude-sonnet-4"));
+        assert_eq!(preview.winning_rule_id, None);
+        assert!(!preview.is_candidate);
+    }
+
+    #[test]
+    fn test_find_rule_issues_flags_dangling_provider() {
+        let provider = Provider::new_model(
+            "OpenAI".to_string(),
+            crate::models::ProviderType::OpenAI,
+            "https://api.openai.com/v1".to_string(),
+            "sk-test".to_string(),
+        );
+        let ok = RoutingRule::new(
+            provider.id.clone(),
+            "gpt-*".to_string(),
+            1,
+            RuleType::Model,
+            ApiGroup::OpenAI,
+        );
+        let dangling = RoutingRule::new(
+            "deleted".to_string(),
+            "claude-*".to_string(),
+            1,
+            RuleType::Model,
+            ApiGroup::Anthropic,
+        );
+        let config = VibeMateConfig {
+            providers: vec![provider],
+            routing_rules: vec![ok, dangling.clone()],
+            ..VibeMateConfig::default()
+        };
+
+        let issues = find_rule_issues(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule_id, dangling.id);
+    }
+
+    #[test]
+    fn test_deduplicate_keeps_rule_headers() {
+        let mut rule = RoutingRule::new(
+            "openrouter".to_string(),
+            "anthropic/*".to_string(),
+            1,
+            RuleType::Model,
+            ApiGroup::OpenAI,
+        );
+        rule.add_headers = HashMap::from([("X-Title".to_string(), "Vibe Mate".to_string())]);
+        let mut duplicate = rule.clone();
+        duplicate.id = "duplicate".to_string();
+        duplicate.add_headers.clear();
+
+        let (rules, removed) = deduplicate_rules(vec![rule, duplicate]);
+        assert_eq!(rules.len(), 1);
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].id, "duplicate");
+        assert_eq!(rules[0].add_headers["X-Title"], "Vibe Mate");
+
+        assert!(validate_add_headers(&rules[0].add_headers).is_ok());
+        let invalid = HashMap::from([("Bad Header".to_string(), "x".to_string())]);
+        assert!(matches!(
+            validate_add_headers(&invalid),
+            Err(RouterError::InvalidHeader(_))
+        ));
+    }
+
+    #[test]
+    fn test_render_routes_dot() {
+        use crate::models::{Provider, ProviderType};
+
+        let openai = Provider::new_model(
+            "OpenAI".to_string(),
+            ProviderType::OpenAI,
+            "https://api.openai.com/v1".to_string(),
+            "sk-test".to_string(),
+        );
+        let anthropic = Provider::new_model(
+            "Anthropic".to_string(),
+            ProviderType::Anthropic,
+            "https://api.anthropic.com".to_string(),
+            "sk-ant".to_string(),
+        );
+        let gpt_rule = RoutingRule::new(
+            openai.id.clone(),
+            "gpt-*".to_string(),
+            1,
+            RuleType::Model,
+            ApiGroup::OpenAI,
+        );
+        let claude_rule = RoutingRule::new(
+            anthropic.id.clone(),
+            "claude-*".to_string(),
+            1,
+            RuleType::Model,
+            ApiGroup::Anthropic,
+        );
+        let mut disabled_rule = RoutingRule::new(
+            anthropic.id.clone(),
+            "o1-*".to_string(),
+            2,
+            RuleType::Model,
+            ApiGroup::OpenAI,
+        );
+        disabled_rule.enabled = false;
+
+        let mut config = VibeMateConfig {
+            providers: vec![openai.clone(), anthropic.clone()],
+            routing_rules: vec![gpt_rule, claude_rule, disabled_rule],
+            ..VibeMateConfig::default()
+        };
+        config
+            .app
+            .default_provider_by_group
+            .insert(ApiGroup::Anthropic, anthropic.id.clone());
+        let dot = render_routes_dot(&config);
+
+        assert!(dot.starts_with("digraph routes {"));
+        assert!(dot.contains(&format!(
+            "\"provider_{}\" [label=\"OpenAI (default)\", style=\"filled,bold\", fillcolor=gold];",
+            openai.id
+        )));
+        assert!(dot.contains(&format!("\"provider_{}\" [label=\"Anthropic\"];", anthropic.id)));
+        assert!(dot.contains(&format!(
+            "\"group_openai\" -> \"provider_{}\" [label=\"#1 model: gpt-*\"];",
+            openai.id
+        )));
+        assert!(dot.contains(&format!(
+            "\"group_anthropic\" -> \"provider_{}\" [label=\"#1 model: claude-*\"];",
+            anthropic.id
+        )));
+        assert!(!dot.contains("o1-*"));
+        // Fallback edges follow the group defaults
+        let fallback = |group: &str, id: &str| {
+            format!("\"{}\" -> \"provider_{}\" [label=\"fallback\", style=dashed];", group, id)
+        };
+        assert!(dot.contains(&fallback("group_anthropic", &anthropic.id)));
+        assert!(dot.contains(&fallback("group_openai", &openai.id)));
+    }
+
+    #[test]
+    fn test_pattern_reports_matches_and_errors() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let service = RouterService::new(Arc::new(ConfigStore::new(temp_dir.path().to_path_buf())));
+        let samples = vec!["gpt-4o".to_string(), "o3".to_string(), "gpt-4.1".to_string()];
+
+        let result = service.test_pattern("gpt-4*", &RuleType::Model, samples.clone());
+        assert_eq!(result.matches, vec!["gpt-4o", "gpt-4.1"]);
+        assert!(result.error.is_none());
+
+        let result = service.test_pattern("gpt-[4", &RuleType::Model, samples);
+        assert!(result.matches.is_empty());
+        assert!(result.error.is_some());
+
+        let result = service.test_pattern("v1/*", &RuleType::Path, vec!["/v1/models".to_string()]);
+        assert!(result.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_set_rules_enabled_in_one_write() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ConfigStore::new(temp_dir.path().to_path_buf()));
+        store.init().await.unwrap();
+        let rules: Vec<RoutingRule> = ["gpt-*", "o1-*", "o3-*"]
+            .iter()
+            .map(|pattern| {
+                RoutingRule::new(
+                    "p1".to_string(),
+                    pattern.to_string(),
+                    1,
+                    RuleType::Model,
+                    ApiGroup::OpenAI,
+                )
+            })
+            .collect();
+        let ids = vec![rules[0].id.clone(), rules[2].id.clone()];
+        store.update(|config| config.routing_rules = rules).await.unwrap();
+        let service = RouterService::new(store);
+
+        let updated = service.set_rules_enabled(ids.clone(), false).await.unwrap();
+        assert_eq!(updated.len(), 2);
+
+        // Reload from disk: both rules were written, the third is untouched
+        let reloaded = ConfigStore::new(temp_dir.path().to_path_buf());
+        reloaded.init().await.unwrap();
+        let enabled: Vec<bool> = reloaded
+            .get_config()
+            .await
+            .routing_rules
+            .iter()
+            .map(|r| r.enabled)
+            .collect();
+        assert_eq!(enabled, vec![false, true, false]);
+
+        assert!(matches!(
+            service.set_rules_enabled(vec!["missing".to_string()], true).await,
+            Err(RouterError::RuleNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_import_rules_counts_duplicates() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ConfigStore::new(temp_dir.path().to_path_buf()));
+        store.init().await.unwrap();
+        let service = RouterService::new(store);
+
+        let input = |pattern: &str| CreateRuleInput {
+            rule_type: RuleType::Model,
+            api_group: ApiGroup::OpenAI,
+            provider_id: "p1".to_string(),
+            match_pattern: pattern.to_string(),
+            model_rewrite: None,
+            enabled: true,
+            translate: false,
+            add_headers: HashMap::new(),
+        };
+        let result = service
+            .import_rules(vec![input("gpt-*"), input("[bad"), input("gpt-*"), input("o1-*")])
+            .await
+            .unwrap();
+
+        let patterns: Vec<&str> = result.created.iter().map(|r| r.match_pattern.as_str()).collect();
+        assert_eq!(patterns, vec!["gpt-*", "o1-*"]);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].index, 1);
+        assert_eq!(result.duplicates_removed, Some(1));
+        assert_eq!(service.list_rules().await.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_routing_table_follows_match_order() {
+        let provider = Provider::new_model(
+            "OpenAI".to_string(),
+            crate::models::ProviderType::OpenAI,
+            "https://api.openai.com/v1".to_string(),
+            "sk-test".to_string(),
+        );
+        let rule = |pattern: &str, priority: i32, rule_type: RuleType, api_group: ApiGroup| {
+            let provider_id = provider.id.clone();
+            RoutingRule::new(provider_id, pattern.to_string(), priority, rule_type, api_group)
+        };
+        let catch_all = rule("/api/*", 1, RuleType::Path, ApiGroup::Generic);
+        let generic_path = rule("/api/v1/*", 2, RuleType::Path, ApiGroup::Generic);
+        let openai_path = rule("/api/openai/*", 1, RuleType::Path, ApiGroup::OpenAI);
+        let openai_model = rule("gpt-*", 5, RuleType::Model, ApiGroup::OpenAI);
+        let mut missing_provider = rule("o1*", 6, RuleType::Model, ApiGroup::OpenAI);
+        missing_provider.provider_id = "deleted".to_string();
+        let config = VibeMateConfig {
+            providers: vec![provider.clone()],
+            routing_rules: vec![
+                catch_all.clone(),
+                generic_path.clone(),
+                openai_path.clone(),
+                missing_provider.clone(),
+                openai_model.clone(),
+                // Duplicates are listed once
+                openai_model.clone(),
+            ],
+            ..VibeMateConfig::default()
+        };
+
+        let table = build_routing_table(&config);
+        let order: Vec<(&str, usize)> =
+            table.iter().map(|e| (e.rule_id.as_str(), e.position)).collect();
+        assert_eq!(
+            order,
+            vec![
+                // Model rules come before path rules, whatever their priority
+                (openai_model.id.as_str(), 1),
+                (missing_provider.id.as_str(), 2),
+                (openai_path.id.as_str(), 3),
+                // Generic's catch-all is tried last
+                (generic_path.id.as_str(), 1),
+                (catch_all.id.as_str(), 2),
+            ]
+        );
+        assert_eq!(table[0].provider_name.as_deref(), Some("OpenAI"));
+        assert!(table[0].active);
+        assert_eq!(table[1].provider_name, None);
+        assert!(!table[1].active);
+    }
+
+    #[tokio::test]
+    async fn test_reorder_subset_leaves_no_priority_collisions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(ConfigStore::new(temp_dir.path().to_path_buf()));
+        store.init().await.unwrap();
+        let rule = |pattern: &str, priority: i32, rule_type: RuleType, api_group: ApiGroup| {
+            RoutingRule::new("p1".to_string(), pattern.to_string(), priority, rule_type, api_group)
+        };
+        let rules = vec![
+            rule("gpt-*", 1, RuleType::Model, ApiGroup::OpenAI),
+            rule("o1-*", 2, RuleType::Model, ApiGroup::OpenAI),
+            rule("o3-*", 3, RuleType::Model, ApiGroup::OpenAI),
+            rule("/v1/files*", 1, RuleType::Path, ApiGroup::OpenAI),
+            rule("claude-*", 1, RuleType::Model, ApiGroup::Anthropic),
+            rule("claude-3-*", 2, RuleType::Model, ApiGroup::Anthropic),
+        ];
+        let (o3, gpt, claude3) = (rules[2].id.clone(), rules[0].id.clone(), rules[5].id.clone());
+        store.update(|config| config.routing_rules = rules).await.unwrap();
+        let service = RouterService::new(store.clone());
+
+        service.reorder_rules(vec![o3.clone(), gpt.clone(), claude3.clone()]).await.unwrap();
+
+        let mut rules = store.get_config().await.routing_rules;
+        let mut seen = HashSet::new();
+        for rule in &rules {
+            assert!(
+                seen.insert((rule.api_group.clone(), rule.rule_type.clone(), rule.priority)),
+                "priority {} repeated in its scope",
+                rule.priority
+            );
+        }
+        rules.sort_by_key(|r| r.priority);
+        let patterns: Vec<&str> = rules.iter().map(|r| r.match_pattern.as_str()).collect();
+        assert_eq!(
+            patterns,
+            vec!["o3-*", "gpt-*", "claude-3-*", "/v1/files*", "claude-*", "o1-*"]
+        );
+    }
 }
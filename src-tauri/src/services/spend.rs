@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::fs;
+
+use crate::models::{ModelPrice, Provider, ProviderSpend, SpendPeriod};
+
+const SPEND_FILE: &str = "spend.json";
+
+/// Spend accumulator path inside the config directory (~/.vibemate/spend.json)
+pub(crate) fn spend_path(config_dir: &Path) -> PathBuf {
+    config_dir.join(SPEND_FILE)
+}
+
+/// Token counts reported by an upstream response
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl TokenUsage {
+    /// Usage in an OpenAI, Anthropic or Gemini response body or stream event
+    pub(crate) fn from_json(value: &Value) -> Option<Self> {
+        if let Some(usage) = value.get("usage").or_else(|| value.pointer("/message/usage")) {
+            let count = |keys: &[&str]| {
+                keys.iter()
+                    .find_map(|key| usage.get(*key).and_then(Value::as_u64))
+                    .unwrap_or(0)
+            };
+            return Some(Self {
+                input_tokens: count(&["input_tokens", "prompt_tokens"]),
+                output_tokens: count(&["output_tokens", "completion_tokens"]),
+            });
+        }
+        let usage = value.get("usageMetadata")?;
+        let count = |key: &str| usage.get(key).and_then(Value::as_u64).unwrap_or(0);
+        Some(Self {
+            input_tokens: count("promptTokenCount"),
+            output_tokens: count("candidatesTokenCount"),
+        })
+    }
+
+    /// Streams repeat or grow their counts from event to event, so keep the largest
+    fn merge(&mut self, other: Self) {
+        self.input_tokens = self.input_tokens.max(other.input_tokens);
+        self.output_tokens = self.output_tokens.max(other.output_tokens);
+    }
+}
+
+/// Period spend is accumulated over: the limit's, or calendar months without a limit
+fn provider_period(provider: &Provider) -> SpendPeriod {
+    provider
+        .spend_limit
+        .as_ref()
+        .map(|limit| limit.period)
+        .unwrap_or_default()
+}
+
+/// Current UTC day or month, e.g. "2026-10-16" or "2026-10"
+fn period_key(period: SpendPeriod) -> String {
+    let now = Utc::now();
+    match period {
+        SpendPeriod::Daily => now.format("%Y-%m-%d").to_string(),
+        SpendPeriod::Monthly => now.format("%Y-%m").to_string(),
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SpendEntry {
+    period: String,
+    spent_usd: f64,
+}
+
+/// Per-provider spend in the current period, persisted to spend.json so restarts keep it.
+/// An entry from an earlier period counts as zero and is replaced on the next charge.
+pub(crate) struct SpendTracker {
+    path: PathBuf,
+    /// Provider id -> spend
+    entries: Mutex<HashMap<String, SpendEntry>>,
+    /// Serializes file writes so an older snapshot never lands after a newer one
+    write_lock: tokio::sync::Mutex<()>,
+}
+
+impl SpendTracker {
+    /// Load the accumulator; a missing or unreadable file starts from zero
+    pub(crate) fn load(path: PathBuf) -> Self {
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                tracing::warn!("Failed to parse {}, ignoring it: {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        Self {
+            path,
+            entries: Mutex::new(entries),
+            write_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    fn current_spend(&self, provider_id: &str, period: SpendPeriod) -> f64 {
+        let key = period_key(period);
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(provider_id)
+            .filter(|entry| entry.period == key)
+            .map_or(0.0, |entry| entry.spent_usd)
+    }
+
+    /// Whether the provider has spent its limit for the current period
+    pub(crate) fn limit_reached(&self, provider: &Provider) -> bool {
+        provider.spend_limit.as_ref().is_some_and(|limit| {
+            self.current_spend(&provider.id, limit.period) >= limit.limit_usd
+        })
+    }
+
+    pub(crate) fn spend_for(&self, provider: &Provider) -> ProviderSpend {
+        let period = provider_period(provider);
+        ProviderSpend {
+            provider_id: provider.id.clone(),
+            period,
+            period_start: period_key(period),
+            spent_usd: self.current_spend(&provider.id, period),
+            limit_usd: provider.spend_limit.as_ref().map(|limit| limit.limit_usd),
+        }
+    }
+
+    /// Add a request's cost to the provider's current period and persist in the background
+    fn charge(self: &Arc<Self>, provider_id: &str, period: SpendPeriod, cost: f64) {
+        {
+            let key = period_key(period);
+            let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+            let entry = entries.entry(provider_id.to_string()).or_default();
+            if entry.period != key {
+                *entry = SpendEntry {
+                    period: key,
+                    spent_usd: 0.0,
+                };
+            }
+            entry.spent_usd += cost;
+        }
+        let tracker = self.clone();
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(async move { tracker.save().await });
+            }
+            Err(_) => tracing::warn!("No runtime to persist spend for provider {}", provider_id),
+        }
+    }
+
+    async fn save(&self) {
+        let _guard = self.write_lock.lock().await;
+        let content = {
+            let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+            serde_json::to_string_pretty(&*entries)
+        };
+        let result = match content {
+            Ok(content) => {
+                // Write to a temp file and rename so a crash never leaves a truncated file
+                let tmp_path = self.path.with_extension("json.tmp");
+                match fs::write(&tmp_path, content).await {
+                    Ok(()) => fs::rename(&tmp_path, &self.path).await,
+                    Err(e) => Err(e),
+                }
+            }
+            Err(e) => Err(std::io::Error::other(e)),
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to save spend to {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Collects the token usage of one response and charges its cost when dropped, i.e. once
+/// the body or stream has been relayed (or the client went away)
+pub(crate) struct SpendMeter {
+    tracker: Arc<SpendTracker>,
+    provider_id: String,
+    period: SpendPeriod,
+    price: ModelPrice,
+    usage: TokenUsage,
+    /// Incomplete SSE line carried over to the next chunk
+    pending: Vec<u8>,
+}
+
+impl SpendMeter {
+    /// None when the provider has no price for `model`, so its spend isn't tracked
+    pub(crate) fn new(
+        tracker: &Arc<SpendTracker>,
+        provider: &Provider,
+        model: &str,
+    ) -> Option<Self> {
+        Some(Self {
+            tracker: tracker.clone(),
+            provider_id: provider.id.clone(),
+            period: provider_period(provider),
+            price: provider.price_for(model)?,
+            usage: TokenUsage::default(),
+            pending: Vec::new(),
+        })
+    }
+
+    /// Usage of a complete JSON response body
+    pub(crate) fn observe_body(&mut self, body: &[u8]) {
+        if let Some(usage) = serde_json::from_slice(body).ok().as_ref().and_then(TokenUsage::from_json)
+        {
+            self.usage.merge(usage);
+        }
+    }
+
+    /// Usage reported in the `data:` events of an SSE chunk
+    pub(crate) fn observe_chunk(&mut self, chunk: &[u8]) {
+        self.pending.extend_from_slice(chunk);
+        while let Some(end) = self.pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            if let Some(data) = line.strip_prefix(b"data:") {
+                self.observe_body(data.trim_ascii());
+            }
+        }
+    }
+}
+
+impl Drop for SpendMeter {
+    fn drop(&mut self) {
+        if self.usage == TokenUsage::default() {
+            return;
+        }
+        let cost = self.price.cost(self.usage.input_tokens, self.usage.output_tokens);
+        self.tracker.charge(&self.provider_id, self.period, cost);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ProviderType, SpendLimit};
+
+    fn priced_provider(limit_usd: f64) -> Provider {
+        let mut provider = Provider::new_model(
+            "Priced".to_string(),
+            ProviderType::OpenAI,
+            "https://api.openai.com/v1".to_string(),
+            "sk-test".to_string(),
+        );
+        provider.input_price_per_1k = Some(1.0);
+        provider.output_price_per_1k = Some(2.0);
+        provider.spend_limit = Some(SpendLimit {
+            limit_usd,
+            period: SpendPeriod::Monthly,
+        });
+        provider
+    }
+
+    #[tokio::test]
+    async fn test_spend_accumulates_and_persists() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = spend_path(temp_dir.path());
+        let tracker = Arc::new(SpendTracker::load(path.clone()));
+        let provider = priced_provider(5.0);
+
+        // OpenAI body: 1k prompt + 1k completion tokens = $3
+        let mut meter = SpendMeter::new(&tracker, &provider, "gpt-4o").unwrap();
+        meter.observe_body(br#"{"usage":{"prompt_tokens":1000,"completion_tokens":1000}}"#);
+        drop(meter);
+        assert!(!tracker.limit_reached(&provider));
+
+        // Anthropic stream split mid-event: 500 input + 1000 output tokens = $2.50
+        let mut meter = SpendMeter::new(&tracker, &provider, "gpt-4o").unwrap();
+        meter.observe_chunk(b"data: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_");
+        meter.observe_chunk(b"tokens\":500,\"output_tokens\":1}}}\n\n");
+        meter.observe_chunk(b"data: {\"type\":\"message_delta\",\"usage\":{\"output_tokens\":1000}}\n");
+        drop(meter);
+
+        let spend = tracker.spend_for(&provider);
+        assert!((spend.spent_usd - 5.5).abs() < 1e-9);
+        assert_eq!(spend.limit_usd, Some(5.0));
+        assert!(tracker.limit_reached(&provider));
+
+        // Restarts keep the running total
+        tracker.save().await;
+        let reloaded = SpendTracker::load(path.clone());
+        assert!((reloaded.spend_for(&provider).spent_usd - 5.5).abs() < 1e-9);
+
+        // A total from an earlier period no longer counts
+        std::fs::write(&path, r#"{"PROVIDER":{"period":"1999-01","spentUsd":100.0}}"#
+            .replace("PROVIDER", &provider.id))
+        .unwrap();
+        let reloaded = SpendTracker::load(path);
+        assert_eq!(reloaded.spend_for(&provider).spent_usd, 0.0);
+        assert!(!reloaded.limit_reached(&provider));
+    }
+}
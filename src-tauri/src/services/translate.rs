@@ -0,0 +1,427 @@
+//! Body translation between the Anthropic Messages API and Google's generateContent API,
+//! used when a rule with `translate` routes `/api/anthropic` traffic to a Google provider.
+//! Only non-streaming requests are supported for now.
+
+use std::collections::HashMap;
+
+use serde_json::{json, Map, Value};
+
+/// JSON Schema keywords Gemini rejects in function declarations
+const UNSUPPORTED_SCHEMA_KEYS: &[&str] = &["$schema", "additionalProperties"];
+
+/// Build the generateContent URL for a Gemini model, adding the API version when the
+/// provider base URL does not already include one
+pub fn gemini_generate_url(base_url: &str, model: &str) -> String {
+    let base = base_url.trim_end_matches('/');
+    let versioned = if base.ends_with("/v1beta") || base.ends_with("/v1") {
+        base.to_string()
+    } else {
+        format!("{}/v1beta", base)
+    };
+    format!("{}/models/{}:generateContent", versioned, model)
+}
+
+/// Whether an Anthropic request body asks for a streamed response
+pub fn is_streaming_request(body: &[u8]) -> bool {
+    serde_json::from_slice::<Value>(body)
+        .ok()
+        .and_then(|v| v.get("stream").and_then(Value::as_bool))
+        .unwrap_or(false)
+}
+
+/// Translate an Anthropic Messages request body into a Gemini generateContent body
+pub fn anthropic_to_gemini_request(body: &[u8]) -> Result<Vec<u8>, String> {
+    let request: Value =
+        serde_json::from_slice(body).map_err(|e| format!("Invalid JSON body: {}", e))?;
+    let messages = request
+        .get("messages")
+        .and_then(Value::as_array)
+        .ok_or_else(|| "Request is missing a messages array".to_string())?;
+
+    // tool_result blocks only carry the tool_use id; Gemini wants the function name
+    let mut tool_names: HashMap<String, String> = HashMap::new();
+    let mut contents = Vec::with_capacity(messages.len());
+    for message in messages {
+        let role = match message.get("role").and_then(Value::as_str) {
+            Some("assistant") => "model",
+            _ => "user",
+        };
+        let parts = content_to_parts(message.get("content"), &mut tool_names);
+        if !parts.is_empty() {
+            contents.push(json!({ "role": role, "parts": parts }));
+        }
+    }
+
+    let mut gemini = Map::new();
+    gemini.insert("contents".to_string(), Value::Array(contents));
+
+    if let Some(system) = system_text(request.get("system")) {
+        gemini.insert(
+            "systemInstruction".to_string(),
+            json!({ "parts": [{ "text": system }] }),
+        );
+    }
+
+    if let Some(tools) = request.get("tools").and_then(Value::as_array) {
+        let declarations: Vec<Value> = tools
+            .iter()
+            .filter_map(|tool| {
+                let name = tool.get("name")?.as_str()?;
+                let mut declaration = json!({ "name": name });
+                if let Some(description) = tool.get("description") {
+                    declaration["description"] = description.clone();
+                }
+                if let Some(schema) = tool.get("input_schema") {
+                    declaration["parameters"] = strip_unsupported_schema_keys(schema.clone());
+                }
+                Some(declaration)
+            })
+            .collect();
+        if !declarations.is_empty() {
+            gemini.insert(
+                "tools".to_string(),
+                json!([{ "functionDeclarations": declarations }]),
+            );
+        }
+    }
+
+    if let Some(tool_config) = tool_choice_to_config(request.get("tool_choice")) {
+        gemini.insert("toolConfig".to_string(), tool_config);
+    }
+
+    let mut generation_config = Map::new();
+    for (from, to) in [
+        ("max_tokens", "maxOutputTokens"),
+        ("temperature", "temperature"),
+        ("top_p", "topP"),
+        ("top_k", "topK"),
+        ("stop_sequences", "stopSequences"),
+    ] {
+        if let Some(value) = request.get(from) {
+            generation_config.insert(to.to_string(), value.clone());
+        }
+    }
+    if !generation_config.is_empty() {
+        gemini.insert(
+            "generationConfig".to_string(),
+            Value::Object(generation_config),
+        );
+    }
+
+    serde_json::to_vec(&Value::Object(gemini)).map_err(|e| e.to_string())
+}
+
+/// Translate a Gemini generateContent response body into an Anthropic Messages response
+pub fn gemini_to_anthropic_response(body: &[u8], model: &str) -> Result<Vec<u8>, String> {
+    let response: Value =
+        serde_json::from_slice(body).map_err(|e| format!("Invalid upstream JSON: {}", e))?;
+    let candidate = response
+        .get("candidates")
+        .and_then(Value::as_array)
+        .and_then(|c| c.first());
+
+    let mut content = Vec::new();
+    let mut has_tool_use = false;
+    let parts = candidate
+        .and_then(|c| c.pointer("/content/parts"))
+        .and_then(Value::as_array);
+    for part in parts.into_iter().flatten() {
+        if let Some(text) = part.get("text").and_then(Value::as_str) {
+            content.push(json!({ "type": "text", "text": text }));
+        } else if let Some(call) = part.get("functionCall") {
+            has_tool_use = true;
+            content.push(json!({
+                "type": "tool_use",
+                "id": format!("toolu_{}", uuid::Uuid::new_v4().simple()),
+                "name": call.get("name").cloned().unwrap_or(Value::Null),
+                "input": call.get("args").cloned().unwrap_or_else(|| json!({})),
+            }));
+        }
+    }
+
+    let stop_reason = match candidate
+        .and_then(|c| c.get("finishReason"))
+        .and_then(Value::as_str)
+    {
+        _ if has_tool_use => "tool_use",
+        Some("MAX_TOKENS") => "max_tokens",
+        _ => "end_turn",
+    };
+
+    let usage = response.get("usageMetadata");
+    let token_count = |key: &str| {
+        usage
+            .and_then(|u| u.get(key))
+            .and_then(Value::as_u64)
+            .unwrap_or(0)
+    };
+
+    let message = json!({
+        "id": format!("msg_{}", uuid::Uuid::new_v4().simple()),
+        "type": "message",
+        "role": "assistant",
+        "model": model,
+        "content": content,
+        "stop_reason": stop_reason,
+        "stop_sequence": null,
+        "usage": {
+            "input_tokens": token_count("promptTokenCount"),
+            "output_tokens": token_count("candidatesTokenCount"),
+        },
+    });
+    serde_json::to_vec(&message).map_err(|e| e.to_string())
+}
+
+/// Wrap an upstream (Gemini) error body in the Anthropic error envelope
+pub fn gemini_error_to_anthropic(body: &[u8]) -> Vec<u8> {
+    let message = serde_json::from_slice::<Value>(body)
+        .ok()
+        .and_then(|v| {
+            v.pointer("/error/message")
+                .and_then(Value::as_str)
+                .map(String::from)
+        })
+        .unwrap_or_else(|| String::from_utf8_lossy(body).into_owned());
+    let error = json!({
+        "type": "error",
+        "error": { "type": "api_error", "message": message },
+    });
+    serde_json::to_vec(&error).unwrap_or_default()
+}
+
+fn system_text(system: Option<&Value>) -> Option<String> {
+    let text = match system? {
+        Value::String(text) => text.clone(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|b| b.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => return None,
+    };
+    (!text.is_empty()).then_some(text)
+}
+
+fn content_to_parts(
+    content: Option<&Value>,
+    tool_names: &mut HashMap<String, String>,
+) -> Vec<Value> {
+    let blocks = match content {
+        Some(Value::String(text)) => return vec![json!({ "text": text })],
+        Some(Value::Array(blocks)) => blocks,
+        _ => return Vec::new(),
+    };
+
+    blocks
+        .iter()
+        .filter_map(|block| match block.get("type").and_then(Value::as_str)? {
+            "text" => Some(json!({ "text": block.get("text")?.as_str()? })),
+            "image" => {
+                let source = block.get("source")?;
+                Some(json!({
+                    "inlineData": {
+                        "mimeType": source.get("media_type")?,
+                        "data": source.get("data")?,
+                    }
+                }))
+            }
+            "tool_use" => {
+                let name = block.get("name")?.as_str()?.to_string();
+                if let Some(id) = block.get("id").and_then(Value::as_str) {
+                    tool_names.insert(id.to_string(), name.clone());
+                }
+                Some(json!({
+                    "functionCall": {
+                        "name": name,
+                        "args": block.get("input").cloned().unwrap_or_else(|| json!({})),
+                    }
+                }))
+            }
+            "tool_result" => {
+                let id = block.get("tool_use_id")?.as_str()?;
+                let name = tool_names
+                    .get(id)
+                    .cloned()
+                    .unwrap_or_else(|| id.to_string());
+                Some(json!({
+                    "functionResponse": {
+                        "name": name,
+                        "response": { "content": tool_result_text(block.get("content")) },
+                    }
+                }))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn tool_result_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .filter_map(|b| b.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn tool_choice_to_config(tool_choice: Option<&Value>) -> Option<Value> {
+    let tool_choice = tool_choice?;
+    let config = match tool_choice.get("type").and_then(Value::as_str)? {
+        "auto" => json!({ "mode": "AUTO" }),
+        "any" => json!({ "mode": "ANY" }),
+        "none" => json!({ "mode": "NONE" }),
+        "tool" => json!({
+            "mode": "ANY",
+            "allowedFunctionNames": [tool_choice.get("name")?],
+        }),
+        _ => return None,
+    };
+    Some(json!({ "functionCallingConfig": config }))
+}
+
+fn strip_unsupported_schema_keys(mut schema: Value) -> Value {
+    match &mut schema {
+        Value::Object(map) => {
+            for key in UNSUPPORTED_SCHEMA_KEYS {
+                map.remove(*key);
+            }
+            for value in map.values_mut() {
+                *value = strip_unsupported_schema_keys(value.take());
+            }
+        }
+        Value::Array(items) => {
+            for item in items.iter_mut() {
+                *item = strip_unsupported_schema_keys(item.take());
+            }
+        }
+        _ => {}
+    }
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gemini_generate_url() {
+        assert_eq!(
+            gemini_generate_url("https://generativelanguage.googleapis.com", "gemini-2.5-pro"),
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-pro:generateContent"
+        );
+        assert_eq!(
+            gemini_generate_url("https://generativelanguage.googleapis.com/v1beta/", "gemini-2.5-pro"),
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-pro:generateContent"
+        );
+    }
+
+    #[test]
+    fn test_anthropic_to_gemini_request() {
+        let body = json!({
+            "model": "gemini-2.5-pro",
+            "max_tokens": 1024,
+            "temperature": 0.2,
+            "system": [{ "type": "text", "text": "Be brief." }],
+            "tools": [{
+                "name": "read_file",
+                "description": "Read a file",
+                "input_schema": {
+                    "$schema": "http://json-schema.org/draft-07/schema#",
+                    "type": "object",
+                    "properties": { "path": { "type": "string" } },
+                    "additionalProperties": false
+                }
+            }],
+            "messages": [
+                { "role": "user", "content": "Open main.rs" },
+                { "role": "assistant", "content": [
+                    { "type": "text", "text": "Reading it." },
+                    { "type": "tool_use", "id": "toolu_1", "name": "read_file", "input": { "path": "main.rs" } }
+                ]},
+                { "role": "user", "content": [
+                    { "type": "tool_result", "tool_use_id": "toolu_1", "content": "fn main() {}" }
+                ]}
+            ]
+        });
+
+        let translated: Value = serde_json::from_slice(
+            &anthropic_to_gemini_request(&serde_json::to_vec(&body).unwrap()).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            translated["systemInstruction"]["parts"][0]["text"],
+            "Be brief."
+        );
+        assert_eq!(translated["generationConfig"]["maxOutputTokens"], 1024);
+        assert_eq!(translated["generationConfig"]["temperature"], 0.2);
+
+        let declaration = &translated["tools"][0]["functionDeclarations"][0];
+        assert_eq!(declaration["name"], "read_file");
+        assert!(declaration["parameters"].get("$schema").is_none());
+        assert!(declaration["parameters"]
+            .get("additionalProperties")
+            .is_none());
+
+        let contents = translated["contents"].as_array().unwrap();
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents[0]["role"], "user");
+        assert_eq!(contents[0]["parts"][0]["text"], "Open main.rs");
+        assert_eq!(contents[1]["role"], "model");
+        assert_eq!(contents[1]["parts"][1]["functionCall"]["name"], "read_file");
+        assert_eq!(
+            contents[1]["parts"][1]["functionCall"]["args"]["path"],
+            "main.rs"
+        );
+        assert_eq!(
+            contents[2]["parts"][0]["functionResponse"]["name"],
+            "read_file"
+        );
+        assert_eq!(
+            contents[2]["parts"][0]["functionResponse"]["response"]["content"],
+            "fn main() {}"
+        );
+    }
+
+    #[test]
+    fn test_gemini_to_anthropic_response() {
+        let body = json!({
+            "candidates": [{
+                "content": { "role": "model", "parts": [
+                    { "text": "Let me check." },
+                    { "functionCall": { "name": "read_file", "args": { "path": "lib.rs" } } }
+                ]},
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": { "promptTokenCount": 12, "candidatesTokenCount": 7 }
+        });
+
+        let translated: Value = serde_json::from_slice(
+            &gemini_to_anthropic_response(&serde_json::to_vec(&body).unwrap(), "gemini-2.5-pro")
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(translated["type"], "message");
+        assert_eq!(translated["model"], "gemini-2.5-pro");
+        assert_eq!(translated["stop_reason"], "tool_use");
+        assert_eq!(translated["content"][0]["text"], "Let me check.");
+        assert_eq!(translated["content"][1]["type"], "tool_use");
+        assert_eq!(translated["content"][1]["input"]["path"], "lib.rs");
+        assert_eq!(translated["usage"]["input_tokens"], 12);
+        assert_eq!(translated["usage"]["output_tokens"], 7);
+    }
+
+    #[test]
+    fn test_gemini_error_to_anthropic() {
+        let translated: Value = serde_json::from_slice(&gemini_error_to_anthropic(
+            br#"{"error":{"code":400,"message":"API key not valid"}}"#,
+        ))
+        .unwrap();
+        assert_eq!(translated["type"], "error");
+        assert_eq!(translated["error"]["message"], "API key not valid");
+    }
+}
@@ -1,11 +1,61 @@
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use serde_json::Value;
 use tokio::fs;
+use tokio::io::AsyncWriteExt;
 use tokio::sync::RwLock;
 
-use crate::models::{CodingAgent, VibeMateConfig};
+use super::{detect_schema_version, migrate};
+use crate::models::{
+    normalize_route_prefix, CodingAgent, Provider, VibeMateConfig, CURRENT_SCHEMA_VERSION,
+    SECRET_KEY_PLACEHOLDER,
+};
 
+/// Overrides the config directory, e.g. for portable installs, tests, multiple profiles, or
+/// where no home directory exists (containers, CI)
+pub const CONFIG_DIR_ENV: &str = "VIBEMATE_CONFIG_DIR";
+/// Alias of `VIBEMATE_CONFIG_DIR`, which wins when both are set
+pub const HOME_DIR_ENV: &str = "VIBEMATE_HOME";
+const CONFIG_DIR_NAME: &str = ".vibemate";
 const CONFIG_FILE: &str = "settings.json";
+/// Provider id -> API key, kept out of settings.json so that file can be shared
+const SECRETS_FILE: &str = "secrets.json";
+
+/// Vibe Mate's data directory: `$VIBEMATE_CONFIG_DIR` (or `$VIBEMATE_HOME`) when set, else
+/// ~/.vibemate/, else .vibemate/ in the current directory (with a warning). Resolved once
+/// per process so settings, auth files and quota history always agree.
+pub fn config_dir() -> &'static Path {
+    static CONFIG_DIR: OnceLock<PathBuf> = OnceLock::new();
+    CONFIG_DIR.get_or_init(|| config_dir_from(|key| std::env::var_os(key)))
+}
+
+/// `config_dir` with environment variables read through `lookup`
+fn config_dir_from(lookup: impl Fn(&str) -> Option<OsString>) -> PathBuf {
+    let override_dir = [CONFIG_DIR_ENV, HOME_DIR_ENV]
+        .iter()
+        .filter_map(|key| lookup(key))
+        .find(|v| !v.is_empty());
+    resolve_config_dir(override_dir.map(PathBuf::from), dirs::home_dir())
+}
+
+fn resolve_config_dir(override_dir: Option<PathBuf>, home: Option<PathBuf>) -> PathBuf {
+    if let Some(dir) = override_dir {
+        return dir;
+    }
+    if let Some(home) = home {
+        return home.join(CONFIG_DIR_NAME);
+    }
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let dir = cwd.join(CONFIG_DIR_NAME);
+    tracing::warn!(
+        "Could not determine home directory and {} is not set; using {}",
+        CONFIG_DIR_ENV,
+        dir.display()
+    );
+    dir
+}
 
 #[derive(Debug, thiserror::Error)]
 pub enum StorageError {
@@ -13,11 +63,16 @@ pub enum StorageError {
     Io(#[from] std::io::Error),
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
+    #[error("Unsupported config schema version: {0}")]
+    UnsupportedSchemaVersion(u32),
+    #[error("Invalid config: {0}")]
+    InvalidConfig(&'static str),
 }
 
 pub struct ConfigStore {
     config_dir: PathBuf,
     config: Arc<RwLock<VibeMateConfig>>,
+    secrets: Arc<RwLock<HashMap<String, String>>>,
 }
 
 impl ConfigStore {
@@ -25,9 +80,15 @@ impl ConfigStore {
         Self {
             config_dir,
             config: Arc::new(RwLock::new(VibeMateConfig::default())),
+            secrets: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
+    /// Directory holding settings.json and other app data (~/.vibemate/)
+    pub fn config_dir(&self) -> &Path {
+        &self.config_dir
+    }
+
     /// Get configuration file path
     fn config_path(&self) -> PathBuf {
         self.config_dir.join(CONFIG_FILE)
@@ -37,29 +98,100 @@ impl ConfigStore {
     pub async fn init(&self) -> Result<(), StorageError> {
         fs::create_dir_all(&self.config_dir).await?;
         self.load().await?;
+        self.load_secrets().await?;
         Ok(())
     }
 
-    /// Load configuration from file
+    /// Load configuration from file, migrating older schema versions
     pub async fn load(&self) -> Result<(), StorageError> {
         let path = self.config_path();
+        let mut migrated = false;
         let config = if path.exists() {
             let content = fs::read_to_string(&path).await?;
-            serde_json::from_str::<VibeMateConfig>(&content)
-                .unwrap_or_default()
+            match serde_json::from_str::<Value>(&content) {
+                Ok(raw) => {
+                    let version = detect_schema_version(&raw);
+                    let raw = if version < CURRENT_SCHEMA_VERSION {
+                        // Keep the pre-migration file in case the upgrade goes wrong
+                        let backup = self
+                            .config_dir
+                            .join(format!("{}.v{}.bak", CONFIG_FILE, version));
+                        fs::write(&backup, &content).await?;
+                        migrated = true;
+                        migrate(raw, version)
+                    } else {
+                        if version > CURRENT_SCHEMA_VERSION {
+                            tracing::warn!(
+                                "Config schema v{} is newer than supported v{}, loading as-is",
+                                version,
+                                CURRENT_SCHEMA_VERSION
+                            );
+                        }
+                        Ok(raw)
+                    };
+                    raw.and_then(|raw| Ok(serde_json::from_value::<VibeMateConfig>(raw)?))
+                        .unwrap_or_else(|e| {
+                            tracing::warn!("Failed to load config, using defaults: {}", e);
+                            VibeMateConfig::default()
+                        })
+                }
+                Err(e) => {
+                    tracing::warn!("Config file is not valid JSON, using defaults: {}", e);
+                    VibeMateConfig::default()
+                }
+            }
         } else {
             VibeMateConfig::default()
         };
+        let mut config = VibeMateConfig {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            ..config
+        };
+        sanitize_route_prefix(&mut config);
         *self.config.write().await = config;
+        if migrated {
+            self.save().await?;
+        }
         Ok(())
     }
 
+    /// Read settings.json (e.g. after it was edited by hand) without applying it. Unlike
+    /// `load`, a file that doesn't parse is an error rather than a fallback to defaults.
+    pub async fn read_config_file(&self) -> Result<VibeMateConfig, StorageError> {
+        let path = self.config_path();
+        if !path.exists() {
+            return Ok(VibeMateConfig::default());
+        }
+        let raw: Value = serde_json::from_str(&fs::read_to_string(&path).await?)?;
+        let version = detect_schema_version(&raw);
+        let raw = if version < CURRENT_SCHEMA_VERSION {
+            migrate(raw, version)?
+        } else {
+            raw
+        };
+        Ok(VibeMateConfig {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            ..serde_json::from_value(raw)?
+        })
+    }
+
+    /// Swap in a config from `read_config_file`, re-reading secrets.json along with it
+    pub async fn replace(&self, config: VibeMateConfig) -> Result<(), StorageError> {
+        *self.config.write().await = config;
+        self.load_secrets().await
+    }
+
     /// Save configuration to file
     pub async fn save(&self) -> Result<(), StorageError> {
-        let path = self.config_path();
         let config = self.config.read().await;
-        let content = serde_json::to_string_pretty(&*config)?;
-        fs::write(&path, content).await?;
+        self.write_config(&config).await
+    }
+
+    /// Write `config` to settings.json. Callers hold a lock on `self.config` until the
+    /// write finishes, so an older snapshot can never land on disk after a newer one.
+    async fn write_config(&self, config: &VibeMateConfig) -> Result<(), StorageError> {
+        let content = serde_json::to_string_pretty(config)?;
+        fs::write(self.config_path(), content).await?;
         Ok(())
     }
 
@@ -68,16 +200,77 @@ impl ConfigStore {
         self.config.read().await.clone()
     }
 
-    /// Update configuration and save
+    /// Update configuration and save. The write lock is held through the save, so
+    /// concurrent updates are applied and persisted one at a time.
     pub async fn update<F>(&self, f: F) -> Result<(), StorageError>
     where
         F: FnOnce(&mut VibeMateConfig),
     {
-        {
-            let mut config = self.config.write().await;
-            f(&mut config);
+        let mut config = self.config.write().await;
+        f(&mut config);
+        self.write_config(&config).await
+    }
+
+    fn secrets_path(&self) -> PathBuf {
+        self.config_dir.join(SECRETS_FILE)
+    }
+
+    /// Load provider secrets; a missing or unreadable file means no secrets
+    async fn load_secrets(&self) -> Result<(), StorageError> {
+        let path = self.secrets_path();
+        let secrets = if path.exists() {
+            let content = fs::read_to_string(&path).await?;
+            serde_json::from_str(&content).unwrap_or_else(|e| {
+                tracing::warn!("Failed to parse {}, ignoring it: {}", path.display(), e);
+                HashMap::new()
+            })
+        } else {
+            HashMap::new()
+        };
+        *self.secrets.write().await = secrets;
+        Ok(())
+    }
+
+    /// API key stored for a provider in secrets.json
+    pub async fn get_secret(&self, provider_id: &str) -> Option<String> {
+        self.secrets.read().await.get(provider_id).cloned()
+    }
+
+    /// Replace a `SECRET_KEY_PLACEHOLDER` api_key with the key from secrets.json
+    pub async fn resolve_provider_secret(&self, provider: &mut Provider) {
+        if provider.api_key.as_deref() == Some(SECRET_KEY_PLACEHOLDER) {
+            provider.api_key = self.get_secret(&provider.id).await;
         }
-        self.save().await
+    }
+
+    /// Store (`Some`) or remove (`None`) a provider's API key in secrets.json
+    pub async fn set_secret(
+        &self,
+        provider_id: &str,
+        secret: Option<String>,
+    ) -> Result<(), StorageError> {
+        // Held until the file is written, like `update`
+        let mut secrets = self.secrets.write().await;
+        match secret {
+            Some(secret) => secrets.insert(provider_id.to_string(), secret),
+            None => secrets.remove(provider_id),
+        };
+        let content = serde_json::to_string_pretty(&*secrets)?;
+        let path = self.secrets_path();
+        // Keys are credentials: write a file that is private to the user from the start,
+        // then rename it into place. The mode only applies when the file is created.
+        let tmp_path = path.with_extension("json.tmp");
+        let _ = fs::remove_file(&tmp_path).await;
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let mut file = options.open(&tmp_path).await?;
+        file.write_all(content.as_bytes()).await?;
+        file.sync_all().await?;
+        drop(file);
+        fs::rename(&tmp_path, &path).await?;
+        Ok(())
     }
 }
 
@@ -99,9 +292,22 @@ pub fn merge_coding_agents(
         .collect()
 }
 
+/// The route prefix is spliced into route paths, where a hand-edited value without a
+/// leading '/' would panic; normalize it, or drop it when it can't be made valid
+fn sanitize_route_prefix(config: &mut VibeMateConfig) {
+    match normalize_route_prefix(&config.app.route_prefix) {
+        Ok(prefix) => config.app.route_prefix = prefix,
+        Err(e) => {
+            tracing::warn!("{}; serving without a route prefix", e);
+            config.app.route_prefix = String::new();
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::AgentProviderType;
     use tempfile::tempdir;
 
     #[tokio::test]
@@ -133,4 +339,202 @@ mod tests {
         let config = store.get_config().await;
         assert!(config.app.enable_proxy);
     }
+
+    #[tokio::test]
+    async fn test_config_store_round_trips_coding_agents() {
+        use crate::models::{AgentType, Provider, ProviderType};
+
+        let temp_dir = tempdir().unwrap();
+        let store = ConfigStore::new(temp_dir.path().to_path_buf());
+        store.init().await.unwrap();
+
+        let mut codex = CodingAgent::new(AgentType::Codex);
+        codex.featured = false;
+        codex.proxy_enabled = true;
+        store
+            .update(|config| {
+                config.providers.push(Provider::new_model(
+                    "OpenAI".to_string(),
+                    ProviderType::OpenAI,
+                    "https://api.openai.com/v1".to_string(),
+                    "sk-test".to_string(),
+                ));
+                config.coding_agents = vec![CodingAgent::new(AgentType::ClaudeCode), codex];
+            })
+            .await
+            .unwrap();
+
+        let content = std::fs::read_to_string(store.config_path()).unwrap();
+        assert!(content.contains("\"codingAgents\""));
+
+        let reloaded = ConfigStore::new(temp_dir.path().to_path_buf());
+        reloaded.init().await.unwrap();
+        let config = reloaded.get_config().await;
+        assert_eq!(config.providers.len(), 1);
+        assert_eq!(config.coding_agents.len(), 2);
+        let codex = &config.coding_agents[1];
+        assert_eq!(codex.agent_type, AgentType::Codex);
+        assert!(!codex.featured);
+        assert!(codex.proxy_enabled);
+    }
+
+    #[test]
+    fn test_merge_coding_agents_preserves_user_flags() {
+        use crate::models::AgentType;
+
+        let mut stored_codex = CodingAgent::new(AgentType::Codex);
+        stored_codex.featured = false;
+        stored_codex.proxy_enabled = true;
+        stored_codex.version = Some("0.1.0".to_string());
+        let stored_gemini = CodingAgent::new(AgentType::GeminiCLI);
+
+        let mut discovered_codex = CodingAgent::new(AgentType::Codex);
+        discovered_codex.version = Some("0.2.0".to_string());
+        let discovered = vec![CodingAgent::new(AgentType::ClaudeCode), discovered_codex];
+
+        let merged = merge_coding_agents(&[stored_codex, stored_gemini], discovered);
+
+        // Uninstalled agents are dropped; new ones keep their defaults
+        let types: Vec<&AgentType> = merged.iter().map(|a| &a.agent_type).collect();
+        assert_eq!(types, vec![&AgentType::ClaudeCode, &AgentType::Codex]);
+        assert!(merged[0].featured);
+        assert!(!merged[0].proxy_enabled);
+
+        // User-managed flags survive, discovered fields are refreshed
+        assert!(!merged[1].featured);
+        assert!(merged[1].proxy_enabled);
+        assert_eq!(merged[1].version.as_deref(), Some("0.2.0"));
+    }
+
+    #[tokio::test]
+    async fn test_secrets_persist_outside_settings() {
+        let temp_dir = tempdir().unwrap();
+        let store = ConfigStore::new(temp_dir.path().to_path_buf());
+        store.init().await.unwrap();
+
+        store.set_secret("p1", Some("sk-secret".to_string())).await.unwrap();
+        store.update(|config| config.app.enable_proxy = true).await.unwrap();
+        let settings = std::fs::read_to_string(store.config_path()).unwrap();
+        assert!(!settings.contains("sk-secret"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(store.secrets_path()).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let reloaded = ConfigStore::new(temp_dir.path().to_path_buf());
+        reloaded.init().await.unwrap();
+        assert_eq!(reloaded.get_secret("p1").await.as_deref(), Some("sk-secret"));
+
+        reloaded.set_secret("p1", None).await.unwrap();
+        assert!(reloaded.get_secret("p1").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_config_store_migrates_and_backs_up() {
+        let temp_dir = tempdir().unwrap();
+        let legacy = r#"{"app":{"enableProxy":true},"routingRules":[]}"#;
+        std::fs::write(temp_dir.path().join(CONFIG_FILE), legacy).unwrap();
+
+        let store = ConfigStore::new(temp_dir.path().to_path_buf());
+        store.init().await.unwrap();
+
+        let config = store.get_config().await;
+        assert!(config.app.enable_proxy);
+        assert_eq!(config.schema_version, CURRENT_SCHEMA_VERSION);
+
+        let backup = std::fs::read_to_string(temp_dir.path().join("settings.json.v0.bak")).unwrap();
+        assert_eq!(backup, legacy);
+        let saved: Value =
+            serde_json::from_str(&std::fs::read_to_string(store.config_path()).unwrap()).unwrap();
+        assert_eq!(detect_schema_version(&saved), CURRENT_SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_resolve_config_dir_fallbacks() {
+        let home = PathBuf::from("/home/user");
+        let custom = PathBuf::from("/data/vibemate");
+        assert_eq!(resolve_config_dir(Some(custom.clone()), Some(home.clone())), custom);
+        assert_eq!(resolve_config_dir(None, Some(home)), PathBuf::from("/home/user/.vibemate"));
+        let fallback = resolve_config_dir(None, None);
+        assert_eq!(fallback, std::env::current_dir().unwrap().join(".vibemate"));
+    }
+
+    #[tokio::test]
+    async fn test_config_dir_env_relocates_settings_and_auth() {
+        let (config_dir, home_dir) = (tempdir().unwrap(), tempdir().unwrap());
+        let env = |vars: Vec<(&'static str, &Path)>| {
+            let vars: HashMap<&str, OsString> =
+                vars.into_iter().map(|(k, v)| (k, v.as_os_str().to_owned())).collect();
+            move |key: &str| vars.get(key).cloned()
+        };
+
+        for (var, expected) in [(CONFIG_DIR_ENV, &config_dir), (HOME_DIR_ENV, &home_dir)] {
+            let dir = config_dir_from(env(vec![(var, expected.path())]));
+            assert_eq!(dir, expected.path());
+
+            let store = ConfigStore::new(dir.clone());
+            store.update(|config| config.app.access_log = true).await.unwrap();
+            assert!(expected.path().join(CONFIG_FILE).exists());
+            let auth_path = crate::agents::auth::auth_path_in(&dir, &AgentProviderType::Codex);
+            assert!(auth_path.starts_with(expected.path()));
+        }
+
+        let both = env(vec![(CONFIG_DIR_ENV, config_dir.path()), (HOME_DIR_ENV, home_dir.path())]);
+        assert_eq!(config_dir_from(both), config_dir.path());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_updates_are_not_lost() {
+        let temp_dir = tempdir().unwrap();
+        let store = Arc::new(ConfigStore::new(temp_dir.path().to_path_buf()));
+        store.init().await.unwrap();
+
+        let tasks: Vec<_> = (0..50)
+            .map(|i| {
+                let store = store.clone();
+                tokio::spawn(async move {
+                    store
+                        .update(|c| {
+                            c.app.model_aliases.insert(format!("alias-{}", i), "m".to_string());
+                        })
+                        .await
+                        .unwrap();
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(store.get_config().await.app.model_aliases.len(), 50);
+        let reloaded = ConfigStore::new(temp_dir.path().to_path_buf());
+        reloaded.init().await.unwrap();
+        assert_eq!(reloaded.get_config().await.app.model_aliases.len(), 50);
+    }
+
+    #[tokio::test]
+    async fn test_load_normalizes_route_prefix() {
+        let temp_dir = tempdir().unwrap();
+        let store = ConfigStore::new(temp_dir.path().to_path_buf());
+        store.init().await.unwrap();
+        store.save().await.unwrap();
+
+        let write_prefix = |prefix: &str| {
+            let path = store.config_path();
+            let mut raw: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap())
+                .unwrap();
+            raw["app"]["routePrefix"] = Value::from(prefix);
+            std::fs::write(&path, raw.to_string()).unwrap();
+        };
+
+        write_prefix("vibemate/");
+        store.load().await.unwrap();
+        assert_eq!(store.get_config().await.app.route_prefix, "/vibemate");
+
+        write_prefix("{*path}");
+        store.load().await.unwrap();
+        assert_eq!(store.get_config().await.app.route_prefix, "");
+    }
 }
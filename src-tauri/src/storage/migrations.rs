@@ -0,0 +1,175 @@
+use serde_json::{Map, Value};
+
+use super::StorageError;
+use crate::models::CURRENT_SCHEMA_VERSION;
+
+/// A single migration step, upgrading a raw config from version `n` to `n + 1`
+type Migration = fn(&mut Map<String, Value>);
+
+/// Ordered migration steps; `MIGRATIONS[n]` upgrades version `n` to `n + 1`
+const MIGRATIONS: &[Migration] = &[migrate_v0_to_v1, migrate_v1_to_v2, migrate_v2_to_v3];
+
+/// Schema version recorded in a raw config. Files written before versioning are version 0.
+pub fn detect_schema_version(raw: &Value) -> u32 {
+    raw.get("schemaVersion")
+        .and_then(Value::as_u64)
+        .map(|v| v as u32)
+        .unwrap_or(0)
+}
+
+/// Apply migrations in order to bring a raw config from `from_version` to the current version
+pub fn migrate(mut raw: Value, from_version: u32) -> Result<Value, StorageError> {
+    if from_version > CURRENT_SCHEMA_VERSION {
+        return Err(StorageError::UnsupportedSchemaVersion(from_version));
+    }
+
+    let root = raw
+        .as_object_mut()
+        .ok_or(StorageError::InvalidConfig("root is not an object"))?;
+    for (version, step) in MIGRATIONS
+        .iter()
+        .enumerate()
+        .skip(from_version as usize)
+    {
+        tracing::info!("Migrating config schema v{} -> v{}", version, version + 1);
+        step(root);
+    }
+    root.insert(
+        "schemaVersion".to_string(),
+        Value::from(CURRENT_SCHEMA_VERSION),
+    );
+
+    Ok(raw)
+}
+
+/// v0 -> v1: write out fields that older builds left implicit. Providers without a `status`
+/// previously failed to parse and reset the whole config.
+fn migrate_v0_to_v1(root: &mut Map<String, Value>) {
+    for rule in objects_in(root, "routingRules") {
+        rule.entry("ruleType").or_insert_with(|| Value::from("model"));
+        rule.entry("apiGroup").or_insert_with(|| Value::from("generic"));
+        rule.entry("enabled").or_insert(Value::Bool(true));
+    }
+    for provider in objects_in(root, "providers") {
+        provider
+            .entry("status")
+            .or_insert_with(|| Value::from("Disconnected"));
+    }
+}
+
+/// v1 -> v2: derive `app.proxyMode` from the old `app.enableProxy` toggle
+fn migrate_v1_to_v2(root: &mut Map<String, Value>) {
+    if let Some(app) = root.get_mut("app").and_then(Value::as_object_mut) {
+        let enabled = app
+            .get("enableProxy")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        app.entry("proxyMode")
+            .or_insert_with(|| Value::from(if enabled { "custom" } else { "none" }));
+    }
+}
+
+/// v2 -> v3: `app.port` is the only listen port key; adopt the value from the
+/// `appPort`/`proxyServerPort` spellings some configs carry, which were otherwise ignored
+fn migrate_v2_to_v3(root: &mut Map<String, Value>) {
+    if let Some(app) = root.get_mut("app").and_then(Value::as_object_mut) {
+        for legacy in ["appPort", "proxyServerPort"] {
+            if let Some(port) = app.remove(legacy) {
+                app.entry("port").or_insert(port);
+            }
+        }
+    }
+}
+
+fn objects_in<'a>(
+    root: &'a mut Map<String, Value>,
+    key: &str,
+) -> impl Iterator<Item = &'a mut Map<String, Value>> {
+    root.get_mut(key)
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ApiGroup, ProxyMode, RuleType, VibeMateConfig};
+    use serde_json::json;
+
+    #[test]
+    fn test_migrate_v0_config() {
+        let raw = json!({
+            "providers": [{
+                "id": "p1",
+                "name": "OpenAI",
+                "type": "OpenAI",
+                "apiBaseUrl": "https://api.openai.com/v1",
+                "apiKey": "sk-test",
+                "createdAt": "2025-01-01T00:00:00Z",
+                "updatedAt": "2025-01-01T00:00:00Z"
+            }],
+            "routingRules": [{
+                "id": "r1",
+                "providerId": "p1",
+                "matchPattern": "gpt-*",
+                "modelRewrite": null,
+                "priority": 1,
+                "createdAt": "2025-01-01T00:00:00Z",
+                "updatedAt": "2025-01-01T00:00:00Z"
+            }]
+        });
+        assert_eq!(detect_schema_version(&raw), 0);
+        // A v0 file with implicit fields cannot be parsed directly
+        assert!(serde_json::from_value::<VibeMateConfig>(raw.clone()).is_err());
+
+        let migrated = migrate(raw, 0).unwrap();
+        assert_eq!(detect_schema_version(&migrated), CURRENT_SCHEMA_VERSION);
+
+        let config: VibeMateConfig = serde_json::from_value(migrated).unwrap();
+        assert_eq!(config.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(config.providers.len(), 1);
+        let rule = &config.routing_rules[0];
+        assert!(rule.enabled);
+        assert_eq!(rule.rule_type, RuleType::Model);
+        assert_eq!(rule.api_group, ApiGroup::Generic);
+    }
+
+    #[test]
+    fn test_migrate_v1_proxy_mode() {
+        let raw = json!({
+            "schemaVersion": 1,
+            "app": { "enableProxy": true, "proxyUrl": "http://127.0.0.1:7890" }
+        });
+        let config: VibeMateConfig = serde_json::from_value(migrate(raw, 1).unwrap()).unwrap();
+        assert_eq!(config.app.proxy_mode, ProxyMode::Custom);
+
+        let raw = json!({ "schemaVersion": 1, "app": { "enableProxy": false } });
+        let config: VibeMateConfig = serde_json::from_value(migrate(raw, 1).unwrap()).unwrap();
+        assert_eq!(config.app.proxy_mode, ProxyMode::None);
+    }
+
+    #[test]
+    fn test_migrate_v2_legacy_port_keys() {
+        let raw = json!({ "schemaVersion": 2, "app": { "appPort": 23456 } });
+        let config: VibeMateConfig = serde_json::from_value(migrate(raw, 2).unwrap()).unwrap();
+        assert_eq!(config.app.port, 23456);
+
+        // The canonical key wins over a stale legacy one
+        let raw = json!({ "schemaVersion": 2, "app": { "port": 12000, "proxyServerPort": 9 } });
+        let migrated = migrate(raw, 2).unwrap();
+        assert!(migrated["app"].get("proxyServerPort").is_none());
+        let config: VibeMateConfig = serde_json::from_value(migrated).unwrap();
+        assert_eq!(config.app.port, 12000);
+    }
+
+    #[test]
+    fn test_migrate_rejects_newer_version() {
+        let raw = json!({ "schemaVersion": CURRENT_SCHEMA_VERSION + 1 });
+        assert!(matches!(
+            migrate(raw, CURRENT_SCHEMA_VERSION + 1),
+            Err(StorageError::UnsupportedSchemaVersion(_))
+        ));
+    }
+}
@@ -1,4 +1,6 @@
 mod config_store;
+mod migrations;
 
 pub use config_store::*;
+pub use migrations::*;
 
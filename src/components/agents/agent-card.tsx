@@ -3,7 +3,7 @@ import { motion } from "motion/react";
 import { useMemo, useState } from "react";
 import { LogIn, Loader2, RefreshCw, Settings2 } from "lucide-react";
 import type { AgentAccountInfo, AgentQuota, AgentQuotaEntry, AgentProviderType } from "@/types";
-import { cn } from "@/lib/utils";
+import { cn, errorMessage } from "@/lib/utils";
 import { Card, CardContent, CardHeader } from "@/components/ui/card";
 import { Button } from "@/components/ui/button";
 import { Switch } from "@/components/ui/switch";
@@ -52,6 +52,7 @@ export function AgentCard({
   const isQuotaSupported = agentType !== "GeminiCli";
   const startAuth = useAgentAuthStore((state) => state.startAuth);
   const completeAuth = useAgentAuthStore((state) => state.completeAuth);
+  const completeAuthManual = useAgentAuthStore((state) => state.completeAuthManual);
   const { toast } = useToast();
   const [isAuthLoading, setIsAuthLoading] = useState(false);
   const [isExpanded, setIsExpanded] = useState(false);
@@ -124,7 +125,15 @@ export function AgentCard({
     setIsAuthLoading(true);
     try {
       const start = await startAuth(agentType);
-      await completeAuth(start.flowId);
+      if (start.manual) {
+        const code = window.prompt(
+          "The login callback could not start. After signing in, paste the code or the full URL from the browser's address bar:",
+        );
+        if (!code) return;
+        await completeAuthManual(start.flowId, code);
+      } else {
+        await completeAuth(start.flowId);
+      }
       toast({
         title: "Authentication complete",
         description: `${label} is now connected.`,
@@ -133,7 +142,7 @@ export function AgentCard({
     } catch (error) {
       toast({
         title: "Authentication failed",
-        description: String(error),
+        description: errorMessage(error),
         variant: "destructive",
       });
     } finally {
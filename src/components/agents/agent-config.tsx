@@ -6,6 +6,7 @@ import type { AgentType } from "@/types";
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { useToast } from "@/hooks/use-toast";
+import { errorMessage } from "@/lib/utils";
 
 interface AgentConfigProps {
   agentType: AgentType;
@@ -31,7 +32,7 @@ export function AgentConfig({ agentType, configPath }: AgentConfigProps) {
     } catch (error) {
       toast({
         title: "Failed to load config",
-        description: String(error),
+        description: errorMessage(error),
         variant: "destructive",
       });
       // Set empty config if file doesn't exist
@@ -55,7 +56,7 @@ export function AgentConfig({ agentType, configPath }: AgentConfigProps) {
     } catch (error) {
       toast({
         title: "Failed to save config",
-        description: String(error),
+        description: errorMessage(error),
         variant: "destructive",
       });
     } finally {
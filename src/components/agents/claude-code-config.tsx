@@ -5,6 +5,7 @@ import { Textarea } from "@/components/ui/textarea";
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { useToast } from "@/hooks/use-toast";
+import { errorMessage } from "@/lib/utils";
 
 interface ClaudeCodeConfigProps {
   configPath: string | null;
@@ -37,7 +38,7 @@ export function ClaudeCodeConfig({
     } catch (error) {
       toast({
         title: "Failed to load config",
-        description: String(error),
+        description: errorMessage(error),
         variant: "destructive",
       });
       // Set default config if file doesn't exist
@@ -74,7 +75,7 @@ export function ClaudeCodeConfig({
     } catch (error) {
       toast({
         title: "Failed to save config",
-        description: String(error),
+        description: errorMessage(error),
         variant: "destructive",
       });
     } finally {
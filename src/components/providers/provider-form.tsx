@@ -98,6 +98,7 @@ export function ProviderForm({
       Anthropic: "https://api.anthropic.com",
       Google: "https://generativelanguage.googleapis.com",
       OpenRouter: "https://openrouter.ai/api",
+      Azure: "",
       Custom: "",
     };
 
@@ -15,6 +15,7 @@ export function ProviderLogo({ type, className }: ProviderLogoProps) {
     Anthropic: { bg: "bg-orange-500/10", text: "text-orange-400", label: "A" },
     Google: { bg: "bg-blue-500/10", text: "text-blue-400", label: "G" },
     OpenRouter: { bg: "bg-violet-500/10", text: "text-violet-400", label: "OR" },
+    Azure: { bg: "bg-sky-500/10", text: "text-sky-400", label: "AZ" },
     Custom: { bg: "bg-purple-500/10", text: "text-purple-400", label: "C" },
     // Agent Providers
     ClaudeCode: { bg: "bg-orange-500/10", text: "text-orange-400", label: "CC" },
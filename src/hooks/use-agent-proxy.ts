@@ -3,7 +3,13 @@ import { invoke } from "@tauri-apps/api/core";
 import { useToast } from "@/hooks/use-toast";
 import { getAgentName } from "@/lib/agents";
 import { isProxyCompatibleAgentType } from "@/lib/constants";
-import type { AgentType, CodingAgent } from "@/types";
+import type {
+  AgentProxyChangePreview,
+  AgentProxyConfigStatus,
+  AgentType,
+  CodingAgent,
+} from "@/types";
+import { errorMessage } from "@/lib/utils";
 
 export function useAgentProxy(agents: CodingAgent[], isLoading: boolean) {
   const { toast } = useToast();
@@ -56,7 +62,7 @@ export function useAgentProxy(agents: CodingAgent[], isLoading: boolean) {
       } catch (error) {
         toast({
           title: "Failed to update proxy",
-          description: String(error),
+          description: errorMessage(error),
           variant: "destructive",
         });
       }
@@ -64,6 +70,20 @@ export function useAgentProxy(agents: CodingAgent[], isLoading: boolean) {
     [toast],
   );
 
+  /** Check the agent's config file itself, which may have drifted from the toggle */
+  const verifyProxyConfig = useCallback(
+    (agentType: AgentType) =>
+      invoke<AgentProxyConfigStatus>("verify_agent_proxy_config", { agentType }),
+    [],
+  );
+
+  /** Diff of what toggling the proxy would write to the agent's config file */
+  const previewProxyChange = useCallback(
+    (agentType: AgentType, enabled: boolean) =>
+      invoke<AgentProxyChangePreview>("preview_agent_proxy_change", { agentType, enabled }),
+    [],
+  );
+
   const getProxyToggleProps = useCallback(
     (agentType: AgentType) => {
       if (!isProxyCompatibleAgentType(agentType)) {
@@ -82,6 +102,8 @@ export function useAgentProxy(agents: CodingAgent[], isLoading: boolean) {
   return {
     getProxyToggleProps,
     isProxyCompatibleAgentType,
+    previewProxyChange,
     proxyStatusByType,
+    verifyProxyConfig,
   };
 }
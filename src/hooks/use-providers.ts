@@ -1,4 +1,6 @@
 import { useEffect } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { CONFIG_RELOADED_EVENT } from "@/hooks/use-tauri";
 import { useProviderStore } from "@/stores/provider-store";
 
 export function useProviders() {
@@ -17,6 +19,15 @@ export function useProviders() {
     fetchProviders();
   }, [fetchProviders]);
 
+  useEffect(() => {
+    const unlisten = listen(CONFIG_RELOADED_EVENT, () => {
+      fetchProviders();
+    });
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, [fetchProviders]);
+
   return {
     providers,
     isLoading,
@@ -1,4 +1,6 @@
 import { useEffect } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { CONFIG_RELOADED_EVENT } from "@/hooks/use-tauri";
 import { useRouterStore } from "@/stores/router-store";
 
 export function useRoutingRules() {
@@ -17,6 +19,15 @@ export function useRoutingRules() {
     fetchRules();
   }, [fetchRules]);
 
+  useEffect(() => {
+    const unlisten = listen(CONFIG_RELOADED_EVENT, () => {
+      fetchRules();
+    });
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, [fetchRules]);
+
   // Sort rules by priority
   const sortedRules = [...rules].sort((a, b) => a.priority - b.priority);
 
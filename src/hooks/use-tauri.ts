@@ -1,16 +1,22 @@
 import { useEffect, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { useAppStore } from "@/stores/app-store";
 import type {
   ProxyStatus,
   AppConfig,
   UpdateAppConfigInput,
   LatencyResult,
+  PortAvailability,
 } from "@/types";
+import { errorMessage } from "@/lib/utils";
+
+/** Emitted by the backend after `reload_config` re-read settings.json */
+export const CONFIG_RELOADED_EVENT = "config-reloaded";
 
 // Hook for proxy status
 export function useProxyStatus() {
-  const { proxyStatus, setProxyStatus } = useAppStore();
+  const { proxyStatus, setProxyStatus, appConfig } = useAppStore();
 
   const fetchStatus = useCallback(async () => {
     try {
@@ -24,6 +30,19 @@ export function useProxyStatus() {
 
   const startProxy = useCallback(async () => {
     try {
+      if (appConfig) {
+        const availability = await invoke<PortAvailability>("check_port_available", {
+          port: appConfig.port,
+        });
+        if (!availability.available) {
+          const holder = availability.conflictingProcess
+            ? ` by ${availability.conflictingProcess}`
+            : " by another process";
+          throw new Error(
+            `Port ${availability.port} is already in use${holder}. Stop it or choose a different port in Settings.`
+          );
+        }
+      }
       await invoke("start_proxy");
       // Wait a bit for the server to start, then check status
       await new Promise(resolve => setTimeout(resolve, 1000));
@@ -32,7 +51,7 @@ export function useProxyStatus() {
       console.error("Failed to start proxy:", error);
       throw error;
     }
-  }, [fetchStatus]);
+  }, [appConfig, fetchStatus]);
 
   const stopProxy = useCallback(async () => {
     try {
@@ -44,6 +63,16 @@ export function useProxyStatus() {
     }
   }, [fetchStatus]);
 
+  const pauseProxy = useCallback(async () => {
+    await invoke("pause_proxy");
+    await fetchStatus();
+  }, [fetchStatus]);
+
+  const resumeProxy = useCallback(async () => {
+    await invoke("resume_proxy");
+    await fetchStatus();
+  }, [fetchStatus]);
+
   useEffect(() => {
     fetchStatus();
     // Poll for status updates every 5 seconds
@@ -51,7 +80,14 @@ export function useProxyStatus() {
     return () => clearInterval(interval);
   }, [fetchStatus]);
 
-  return { proxyStatus, startProxy, stopProxy, refetch: fetchStatus };
+  return {
+    proxyStatus,
+    startProxy,
+    stopProxy,
+    pauseProxy,
+    resumeProxy,
+    refetch: fetchStatus,
+  };
 }
 
 // Hook for app config
@@ -83,13 +119,19 @@ export function useAppConfig() {
     [setAppConfig]
   );
 
+  const reloadConfig = useCallback(async () => {
+    const config = await invoke<AppConfig>("reload_config");
+    setAppConfig(config);
+    return config;
+  }, [setAppConfig]);
+
   const testLatency = useCallback(async (): Promise<LatencyResult> => {
     try {
       const result = await invoke<LatencyResult>("test_latency");
       return result;
     } catch (error) {
       console.error("Failed to test latency:", error);
-      return { success: false, latencyMs: null, error: String(error) };
+      return { success: false, latencyMs: null, error: errorMessage(error) };
     }
   }, []);
 
@@ -97,7 +139,16 @@ export function useAppConfig() {
     fetchConfig();
   }, [fetchConfig]);
 
-  return { appConfig, updateConfig, testLatency, refetch: fetchConfig };
+  useEffect(() => {
+    const unlisten = listen<AppConfig>(CONFIG_RELOADED_EVENT, (event) => {
+      setAppConfig(event.payload);
+    });
+    return () => {
+      unlisten.then((stop) => stop());
+    };
+  }, [setAppConfig]);
+
+  return { appConfig, updateConfig, reloadConfig, testLatency, refetch: fetchConfig };
 }
 
 // Hook for system info
@@ -5,6 +5,7 @@ export const PROVIDER_TYPES = [
   { value: "Anthropic", label: "Anthropic", logo: "anthropic" },
   { value: "Google", label: "Google", logo: "google" },
   { value: "OpenRouter", label: "OpenRouter", logo: "custom" },
+  { value: "Azure", label: "Azure OpenAI", logo: "custom" },
   { value: "Custom", label: "Custom", logo: "custom" },
 ] as const;
 
@@ -15,7 +16,7 @@ export const AGENT_TYPES = [
   { value: "Antigravity", label: "Antigravity", logo: "custom" },
 ] as const;
 
-export const PROXY_COMPATIBLE_AGENT_TYPES: AgentType[] = ["ClaudeCode", "Codex"];
+export const PROXY_COMPATIBLE_AGENT_TYPES: AgentType[] = ["ClaudeCode", "Codex", "GeminiCLI"];
 
 /** Map from agent detection type (AgentType) to auth/quota type (AgentProviderType) */
 const AGENT_TYPE_TO_PROVIDER: Record<AgentType, AgentProviderType> = {
@@ -10,6 +10,7 @@ import { agentTypeToProviderType } from "@/lib/constants";
 import { getAgentName } from "@/lib/agents";
 import type { AgentProviderType, AgentQuota } from "@/types";
 import { containerVariants, itemVariants } from "@/lib/animations";
+import { errorMessage } from "@/lib/utils";
 
 export function AgentsPage() {
   const { accounts, isLoading: isAuthLoading, getQuota } = useAgentAuth();
@@ -35,7 +36,7 @@ export function AgentsPage() {
         const data = await getQuota(agentType);
         setQuotaByAgentType((prev) => ({ ...prev, [agentType]: data }));
       } catch (error) {
-        setQuotaErrorByAgentType((prev) => ({ ...prev, [agentType]: String(error) }));
+        setQuotaErrorByAgentType((prev) => ({ ...prev, [agentType]: errorMessage(error) }));
       }
     },
     [getQuota],
@@ -135,6 +136,8 @@ export function AgentsPage() {
                 agentType: providerType,
                 isAuthenticated: false,
                 email: null,
+                isActive: false,
+                authPath: "",
               });
             return (
               <motion.div key={agentType} variants={itemVariants} layout initial={false}>
@@ -22,6 +22,7 @@ import {
 } from "@/components/ui/dialog";
 import { Button } from "@/components/ui/button";
 import { Card, CardContent } from "@/components/ui/card";
+import { errorMessage } from "@/lib/utils";
 
 const API_ENDPOINTS = [
   { label: "OpenAI Compatible API", path: "/api/openai" },
@@ -81,7 +82,7 @@ export function DashboardPage() {
         const data = await getQuota(agentType);
         setQuotaByAgentType((prev) => ({ ...prev, [agentType]: data }));
       } catch (error) {
-        setQuotaErrorByAgentType((prev) => ({ ...prev, [agentType]: String(error) }));
+        setQuotaErrorByAgentType((prev) => ({ ...prev, [agentType]: errorMessage(error) }));
       }
     },
     [getQuota]
@@ -180,7 +181,7 @@ export function DashboardPage() {
     } catch (e) {
       toast({
         title: "Failed to save",
-        description: String(e),
+        description: errorMessage(e),
         variant: "destructive",
       });
     }
@@ -265,6 +266,8 @@ export function DashboardPage() {
                       agentType: providerType,
                       isAuthenticated: false,
                       email: null,
+                      isActive: false,
+                      authPath: "",
                     });
                   return (
                     <motion.div
@@ -11,6 +11,7 @@ import type {
   UpdateProviderInput,
 } from "@/types";
 import { containerVariants, itemVariants } from "@/lib/animations";
+import { errorMessage } from "@/lib/utils";
 
 export function ProvidersPage() {
   const {
@@ -36,7 +37,7 @@ export function ProvidersPage() {
     } catch (error) {
       toast({
         title: "Error",
-        description: String(error),
+        description: errorMessage(error),
         variant: "destructive",
       });
       throw error;
@@ -66,7 +67,7 @@ export function ProvidersPage() {
     } catch (error) {
       toast({
         title: "Error",
-        description: String(error),
+        description: errorMessage(error),
         variant: "destructive",
       });
       throw error;
@@ -84,7 +85,7 @@ export function ProvidersPage() {
     } catch (error) {
       toast({
         title: "Error",
-        description: String(error),
+        description: errorMessage(error),
         variant: "destructive",
       });
       throw error;
@@ -111,7 +112,7 @@ export function ProvidersPage() {
     } catch (error) {
       toast({
         title: "Connection Error",
-        description: String(error),
+        description: errorMessage(error),
         variant: "destructive",
       });
     }
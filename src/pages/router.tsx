@@ -17,6 +17,7 @@ import type {
   RuleType,
   ApiGroup,
 } from "@/types";
+import { errorMessage } from "@/lib/utils";
 
 export function RouterPage() {
   const { rules, isLoading, createRule, updateRule, deleteRule, reorderRules } =
@@ -136,7 +137,7 @@ export function RouterPage() {
       } catch (error) {
         toast({
           title: "Error",
-          description: String(error),
+          description: errorMessage(error),
           variant: "destructive",
         });
       }
@@ -190,7 +191,7 @@ export function RouterPage() {
     } catch (error) {
       toast({
         title: "Error",
-        description: String(error),
+        description: errorMessage(error),
         variant: "destructive",
       });
     }
@@ -219,7 +220,7 @@ export function RouterPage() {
     } catch (error) {
       toast({
         title: "Error",
-        description: String(error),
+        description: errorMessage(error),
         variant: "destructive",
       });
     }
@@ -240,7 +241,7 @@ export function RouterPage() {
     } catch (error) {
       toast({
         title: "Error",
-        description: String(error),
+        description: errorMessage(error),
         variant: "destructive",
       });
     }
@@ -256,7 +257,7 @@ export function RouterPage() {
     } catch (error) {
       toast({
         title: "Error",
-        description: String(error),
+        description: errorMessage(error),
         variant: "destructive",
       });
     }
@@ -268,7 +269,7 @@ export function RouterPage() {
     } catch (error) {
       toast({
         title: "Error",
-        description: String(error),
+        description: errorMessage(error),
         variant: "destructive",
       });
     }
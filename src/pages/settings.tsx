@@ -9,6 +9,7 @@ import { Switch } from "@/components/ui/switch";
 import { useAppConfig } from "@/hooks/use-tauri";
 import { useToast } from "@/hooks/use-toast";
 import type { UpdateAppConfigInput } from "@/types";
+import { errorMessage } from "@/lib/utils";
 
 export function SettingsPage() {
   const { appConfig, updateConfig } = useAppConfig();
@@ -74,7 +75,7 @@ export function SettingsPage() {
     } catch (error) {
       toast({
         title: "Error",
-        description: String(error),
+        description: errorMessage(error),
         variant: "destructive",
       });
     } finally {
@@ -4,9 +4,13 @@ import { openUrl } from "@tauri-apps/plugin-opener";
 import type {
   AgentAccountInfo,
   AgentAuthStart,
+  AgentOrganization,
   AgentProviderType,
   AgentQuota,
+  AgentTokenStatus,
+  AuthFileReport,
 } from "@/types";
+import { errorMessage } from "@/lib/utils";
 
 interface AgentAuthState {
   accounts: AgentAccountInfo[];
@@ -16,7 +20,16 @@ interface AgentAuthState {
   listAccounts: () => Promise<void>;
   startAuth: (agentType: AgentProviderType) => Promise<AgentAuthStart>;
   completeAuth: (flowId: string) => Promise<AgentAccountInfo>;
-  getQuota: (agentType: AgentProviderType) => Promise<AgentQuota>;
+  /** For `manual` flows: the pasted code, `code#state` or redirect URL */
+  completeAuthManual: (flowId: string, code: string) => Promise<AgentAccountInfo>;
+  upsertAccount: (account: AgentAccountInfo) => void;
+  /** `accountId` views another organization's quota (Codex) */
+  getQuota: (agentType: AgentProviderType, accountId?: string) => Promise<AgentQuota>;
+  listOrganizations: (agentType: AgentProviderType) => Promise<AgentOrganization[]>;
+  /** Probe every logged-in account, refreshing tokens that are due */
+  validateTokens: () => Promise<AgentTokenStatus[]>;
+  /** Email, expiry and token presence of the stored login, for diagnostics */
+  inspectAuthFile: (agentType: AgentProviderType) => Promise<AuthFileReport>;
   removeAuth: (agentType: AgentProviderType) => Promise<void>;
 }
 
@@ -31,7 +44,7 @@ export const useAgentAuthStore = create<AgentAuthState>((set, get) => ({
       const accounts = await invoke<AgentAccountInfo[]>("list_agent_accounts");
       set({ accounts, isLoading: false });
     } catch (error) {
-      set({ error: String(error), isLoading: false });
+      set({ error: errorMessage(error), isLoading: false });
     }
   },
 
@@ -47,6 +60,20 @@ export const useAgentAuthStore = create<AgentAuthState>((set, get) => ({
 
   completeAuth: async (flowId: string) => {
     const account = await invoke<AgentAccountInfo>("complete_agent_auth", { flowId });
+    get().upsertAccount(account);
+    return account;
+  },
+
+  completeAuthManual: async (flowId: string, code: string) => {
+    const account = await invoke<AgentAccountInfo>("complete_agent_auth_manual", {
+      flowId,
+      code,
+    });
+    get().upsertAccount(account);
+    return account;
+  },
+
+  upsertAccount: (account: AgentAccountInfo) => {
     set((state) => {
       const has = state.accounts.some((a) => a.agentType === account.agentType);
       const accounts = has
@@ -54,11 +81,22 @@ export const useAgentAuthStore = create<AgentAuthState>((set, get) => ({
         : [...state.accounts, account];
       return { accounts };
     });
-    return account;
   },
 
-  getQuota: async (agentType: AgentProviderType) => {
-    return invoke<AgentQuota>("get_agent_quota", { agentType });
+  getQuota: async (agentType: AgentProviderType, accountId?: string) => {
+    return invoke<AgentQuota>("get_agent_quota", { agentType, accountId });
+  },
+
+  listOrganizations: async (agentType: AgentProviderType) => {
+    return invoke<AgentOrganization[]>("list_agent_organizations", { agentType });
+  },
+
+  validateTokens: async () => {
+    return invoke<AgentTokenStatus[]>("validate_agent_tokens");
+  },
+
+  inspectAuthFile: async (agentType: AgentProviderType) => {
+    return invoke<AuthFileReport>("inspect_auth_file", { agentType });
   },
 
   removeAuth: async (agentType: AgentProviderType) => {
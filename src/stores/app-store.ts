@@ -21,6 +21,7 @@ export const useAppStore = create<AppState>((set) => ({
     isRunning: false,
     port: 12345,
     requestCount: 0,
+    paused: false,
   },
   setProxyStatus: (status) => set({ proxyStatus: status }),
 
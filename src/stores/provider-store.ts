@@ -4,8 +4,12 @@ import type {
   Provider,
   CreateProviderInput,
   UpdateProviderInput,
+  ProviderFilter,
+  ProviderPing,
+  ProviderSpend,
 } from "@/types";
 import { useRouterStore } from "./router-store";
+import { errorMessage } from "@/lib/utils";
 
 interface ProviderState {
   providers: Provider[];
@@ -13,10 +17,25 @@ interface ProviderState {
   error: string | null;
 
   fetchProviders: () => Promise<void>;
+  /** Providers matching a tag and/or group, without touching the cached list */
+  filterProviders: (filter: ProviderFilter) => Promise<Provider[]>;
+  fetchProviderGroups: () => Promise<string[]>;
   createProvider: (input: CreateProviderInput) => Promise<Provider>;
   updateProvider: (id: string, input: UpdateProviderInput) => Promise<Provider>;
-  deleteProvider: (id: string) => Promise<void>;
+  /** `removeAuthFile` also deletes an agent provider's unshared OAuth file */
+  deleteProvider: (id: string, removeAuthFile?: boolean) => Promise<void>;
   testConnection: (id: string) => Promise<{ isConnected: boolean; latencyMs?: number; error?: string }>;
+  /** curl command reproducing the proxy's request to the provider (API key masked) */
+  curlExample: (id: string, sampleModel: string) => Promise<string>;
+  pingProvider: (id: string) => Promise<ProviderPing>;
+  fetchSpend: () => Promise<ProviderSpend[]>;
+  /** Dollar estimate; rejects when the model has no pricing */
+  estimateCost: (
+    providerId: string,
+    model: string,
+    promptTokens: number,
+    maxTokens: number
+  ) => Promise<number>;
 }
 
 export const useProviderStore = create<ProviderState>((set) => ({
@@ -30,10 +49,18 @@ export const useProviderStore = create<ProviderState>((set) => ({
       const providers = await invoke<Provider[]>("list_providers");
       set({ providers, isLoading: false });
     } catch (error) {
-      set({ error: String(error), isLoading: false });
+      set({ error: errorMessage(error), isLoading: false });
     }
   },
 
+  filterProviders: async (filter: ProviderFilter) => {
+    return invoke<Provider[]>("list_providers", { filter });
+  },
+
+  fetchProviderGroups: async () => {
+    return invoke<string[]>("list_provider_groups");
+  },
+
   createProvider: async (input: CreateProviderInput) => {
     try {
       const provider = await invoke<Provider>("create_provider", { input });
@@ -42,7 +69,7 @@ export const useProviderStore = create<ProviderState>((set) => ({
       }));
       return provider;
     } catch (error) {
-      set({ error: String(error) });
+      set({ error: errorMessage(error) });
       throw error;
     }
   },
@@ -55,21 +82,21 @@ export const useProviderStore = create<ProviderState>((set) => ({
       }));
       return provider;
     } catch (error) {
-      set({ error: String(error) });
+      set({ error: errorMessage(error) });
       throw error;
     }
   },
 
-  deleteProvider: async (id: string) => {
+  deleteProvider: async (id: string, removeAuthFile?: boolean) => {
     try {
-      await invoke("delete_provider", { id });
+      await invoke("delete_provider", { id, removeAuthFile });
       set((state) => ({
         providers: state.providers.filter((p) => p.id !== id),
       }));
       // Refresh routing rules since backend removes rules referencing deleted provider
       useRouterStore.getState().fetchRules();
     } catch (error) {
-      set({ error: String(error) });
+      set({ error: errorMessage(error) });
       throw error;
     }
   },
@@ -88,8 +115,29 @@ export const useProviderStore = create<ProviderState>((set) => ({
       }));
       return result;
     } catch (error) {
-      set({ error: String(error) });
+      set({ error: errorMessage(error) });
       throw error;
     }
   },
+
+  curlExample: async (id: string, sampleModel: string) => {
+    return invoke<string>("provider_curl_example", { providerId: id, sampleModel });
+  },
+
+  pingProvider: async (id: string) => {
+    return invoke<ProviderPing>("ping_provider", { id });
+  },
+
+  fetchSpend: async () => {
+    return invoke<ProviderSpend[]>("get_provider_spend");
+  },
+
+  estimateCost: async (providerId, model, promptTokens, maxTokens) => {
+    return invoke<number>("estimate_request_cost", {
+      providerId,
+      model,
+      promptTokens,
+      maxTokens,
+    });
+  },
 }));
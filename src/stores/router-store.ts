@@ -1,6 +1,12 @@
 import { create } from "zustand";
 import { invoke } from "@tauri-apps/api/core";
-import type { RoutingRule, CreateRuleInput, UpdateRuleInput } from "@/types";
+import type {
+  RoutingRule,
+  CreateRuleInput,
+  UpdateRuleInput,
+  RoutingTableEntry,
+} from "@/types";
+import { errorMessage } from "@/lib/utils";
 
 interface RouterState {
   rules: RoutingRule[];
@@ -13,6 +19,8 @@ interface RouterState {
   updateRule: (id: string, input: UpdateRuleInput) => Promise<RoutingRule>;
   deleteRule: (id: string) => Promise<void>;
   reorderRules: (ruleIds: string[]) => Promise<void>;
+  /** Rules in the order the proxy tries them */
+  fetchRoutingTable: () => Promise<RoutingTableEntry[]>;
 }
 
 export const useRouterStore = create<RouterState>((set) => ({
@@ -26,7 +34,7 @@ export const useRouterStore = create<RouterState>((set) => ({
       const rules = await invoke<RoutingRule[]>("list_rules");
       set({ rules, isLoading: false });
     } catch (error) {
-      set({ error: String(error), isLoading: false });
+      set({ error: errorMessage(error), isLoading: false });
     }
   },
 
@@ -38,7 +46,7 @@ export const useRouterStore = create<RouterState>((set) => ({
       }));
       return rule;
     } catch (error) {
-      set({ error: String(error) });
+      set({ error: errorMessage(error) });
       throw error;
     }
   },
@@ -51,7 +59,7 @@ export const useRouterStore = create<RouterState>((set) => ({
       }));
       return rule;
     } catch (error) {
-      set({ error: String(error) });
+      set({ error: errorMessage(error) });
       throw error;
     }
   },
@@ -63,7 +71,7 @@ export const useRouterStore = create<RouterState>((set) => ({
         rules: state.rules.filter((r) => r.id !== id),
       }));
     } catch (error) {
-      set({ error: String(error) });
+      set({ error: errorMessage(error) });
       throw error;
     }
   },
@@ -83,8 +91,12 @@ export const useRouterStore = create<RouterState>((set) => ({
         return { rules: updatedRules };
       });
     } catch (error) {
-      set({ error: String(error) });
+      set({ error: errorMessage(error) });
       throw error;
     }
   },
+
+  fetchRoutingTable: async () => {
+    return invoke<RoutingTableEntry[]>("get_routing_table");
+  },
 }));
@@ -10,6 +10,10 @@ export interface CodingAgent {
   agentType: AgentType;
   name: string;
   version?: string | null;
+  /** When `version` was last read from the binary. */
+  versionCheckedAt?: string | null;
+  /** Binary modification time (unix seconds) when `version` was read. */
+  binaryMtime?: number | null;
   status: AgentStatus;
   executablePath?: string | null;
   configPath: string | null;
@@ -1,17 +1,81 @@
+import type { ApiGroup } from "./router";
+import type { AgentType } from "./agent";
+import type { AgentProviderType } from "./provider";
+
+export type ProxyMode = "none" | "system" | "custom";
+
 export interface AppConfig {
   /** Proxy server listen port (config key: app.port) */
   port: number;
+  proxyMode: ProxyMode;
+  /** Mirrors `proxyMode !== "none"`. */
   enableProxy: boolean;
   proxyUrl: string | null;
+  proxyUsername: string | null;
+  proxyPassword: string | null;
   noProxy: string[];
+  bypassProxyForLocalhost: boolean;
+  exposeDebugHeaders: boolean;
+  modelAliases: Record<string, string>;
+  resetStatsOnStart: boolean;
+  latencyProbeUrl: string | null;
+  /** Sent upstream instead of the client's User-Agent */
+  userAgent: string | null;
+  /** Send requests sharing a stickySessionHeader value to the same provider */
+  stickySessions: boolean;
+  stickySessionHeader: string;
+  /** Idle seconds before a `: ping` comment is sent on SSE streams (0 disables it) */
+  sseKeepaliveSecs: number;
+  /** Path prepended to every proxy route ("" for none). Applied on proxy restart. */
+  routePrefix: string;
+  /** Append a JSON line per proxied request to ~/.vibemate/access.jsonl */
+  accessLog: boolean;
+  /** Requests kept for the request inspector (0 disables it) */
+  recentRequestsCapacity: number;
+  /** Upstream connect timeout (seconds) */
+  connectTimeoutSecs: number;
+  /** Upstream idle timeout (seconds); resets on every received chunk */
+  idleTimeoutSecs: number;
+  /** Attempts per upstream request on 429/503 (1 disables retries) */
+  retryMaxAttempts: number;
+  retryBaseDelayMs: number;
+  /** Larger request bodies are rejected with 413 */
+  maxRequestBodyBytes: number;
+  /** Browser origins allowed to call the proxy; empty allows any. Applied on proxy restart. */
+  corsAllowedOrigins: string[];
+  /** Warn past this many routing rules (0 disables) */
+  ruleCountWarning: number;
+  /** Provider id per API group for requests no rule matches, before the global default */
+  defaultProviderByGroup: Partial<Record<ApiGroup, string>>;
   updatedAt: string;
 }
 
 export interface UpdateAppConfigInput {
   port?: number;
+  proxyMode?: ProxyMode;
   enableProxy?: boolean;
   proxyUrl?: string | null;
+  proxyUsername?: string;
+  proxyPassword?: string;
   noProxy?: string[];
+  bypassProxyForLocalhost?: boolean;
+  exposeDebugHeaders?: boolean;
+  resetStatsOnStart?: boolean;
+  latencyProbeUrl?: string;
+  userAgent?: string;
+  stickySessions?: boolean;
+  stickySessionHeader?: string;
+  sseKeepaliveSecs?: number;
+  routePrefix?: string;
+  accessLog?: boolean;
+  recentRequestsCapacity?: number;
+  connectTimeoutSecs?: number;
+  idleTimeoutSecs?: number;
+  retryMaxAttempts?: number;
+  retryBaseDelayMs?: number;
+  maxRequestBodyBytes?: number;
+  corsAllowedOrigins?: string[];
+  ruleCountWarning?: number;
 }
 
 export interface LatencyResult {
@@ -24,4 +88,136 @@ export interface ProxyStatus {
   isRunning: boolean;
   port: number;
   requestCount: number;
+  /** Forwarding paused; the server stays up and answers 503 */
+  paused: boolean;
+}
+
+export interface PortAvailability {
+  port: number;
+  available: boolean;
+  conflictingProcess: string | null;
+}
+
+/** Environment readiness report from the `diagnostics` command */
+export interface DiagnosticsReport {
+  configDir: string;
+  configDirWritable: boolean;
+  proxyRunning: boolean;
+  /** null while the proxy itself holds the port */
+  port: PortAvailability | null;
+  agents: AgentDiagnostic[];
+  authFiles: AuthFileDiagnostic[];
+}
+
+export interface AgentDiagnostic {
+  agentType: AgentType;
+  executablePath: string | null;
+}
+
+export interface AuthFileDiagnostic {
+  providerId: string;
+  providerName: string;
+  agentType: AgentProviderType;
+  path: string | null;
+  exists: boolean;
+  valid: boolean;
+  error: string | null;
+}
+
+export interface RequestTimings {
+  bodyReadMs: number;
+  routingMs: number;
+  bodyTransformMs: number;
+  upstreamMs: number;
+  responseMs: number;
+  totalMs: number;
+}
+
+export interface AccessLogEntry {
+  timestamp: string;
+  method: string;
+  path: string;
+  apiGroup: ApiGroup;
+  provider: string | null;
+  model: string | null;
+  status: number;
+  durationMs: number;
+  requestBytes: number;
+  /** null for streaming responses */
+  responseBytes: number | null;
+  timings: RequestTimings | null;
+}
+
+export interface RequestRecord {
+  id: string;
+  timestamp: string;
+  method: string;
+  path: string;
+  apiGroup: ApiGroup;
+  provider: string | null;
+  model: string | null;
+  /** Matched routing rule id, "default" for the default provider, null when rejected before routing */
+  rule: string | null;
+  /** A rule matched but its provider is gone, so the default provider took the request */
+  fallback: boolean;
+  status: number;
+  durationMs: number;
+  /** Only set for a request captured via capture_next_request */
+  requestBody: string | null;
+  responseBody: string | null;
+}
+
+export interface ReplayResult {
+  status: number;
+  body: string;
+  /** null when the request was rejected before routing */
+  provider: string | null;
+}
+
+/** Rejection value of every Tauri command; `code` is stable (e.g. "not_found", "auth_expired") */
+export interface CommandError {
+  code: string;
+  message: string;
+}
+
+/** Local proxy URLs from `get_proxy_endpoints` */
+export interface ProxyEndpoints {
+  port: number;
+  openaiUrl: string;
+  /** For OpenAI-compatible clients that expect `/v1` in the base URL (e.g. Codex) */
+  openaiV1Url: string;
+  anthropicUrl: string;
+  genericUrl: string;
+  agents: AgentEndpoint[];
+}
+
+export interface AgentEndpoint {
+  agentType: AgentType;
+  envVar: string;
+  baseUrl: string;
+}
+
+/** Whether an agent's config file points at the proxy on the current port */
+export type AgentProxyConfigState = "Enabled" | "StalePort" | "Disabled";
+
+/** Would-be result of toggling an agent's proxy; nothing is written */
+export interface AgentProxyChangePreview {
+  agentType: AgentType;
+  configPath: string;
+  /** Current file content (empty if the file does not exist yet) */
+  current: string;
+  /** Content the file would have after the change */
+  proposed: string;
+  /** Line diff, each line prefixed with "-", "+" or a space */
+  diff: string;
+  changed: boolean;
+}
+
+export interface AgentProxyConfigStatus {
+  agentType: AgentType;
+  state: AgentProxyConfigState;
+  /** Base URL found in the agent's config file */
+  configuredUrl: string | null;
+  /** Base URL for the current proxy port */
+  expectedUrl: string;
 }
@@ -1,15 +1,53 @@
-export type ProviderType = "OpenAI" | "Anthropic" | "Google" | "OpenRouter" | "Custom";
+export type ProviderType = "OpenAI" | "Anthropic" | "Google" | "OpenRouter" | "Azure" | "Custom";
 
 export type AgentProviderType = "Codex" | "ClaudeCode" | "GeminiCli" | "Antigravity";
 
 export type ProviderStatus = "Connected" | "Disconnected" | "Error";
 
+export interface ResponseTransform {
+  dropKeys?: string[];
+  /** Old key -> new key */
+  renameKeys?: Record<string, string>;
+}
+
 export interface Provider {
   id: string;
   name: string;
   type: ProviderType;
   apiBaseUrl?: string;
   apiKey?: string;
+  agentAccount?: AgentProviderType | null;
+  maxConcurrentRequests?: number | null;
+  enabled: boolean;
+  /** OpenRouter attribution headers; unset uses Vibe Mate's identity */
+  httpReferer?: string | null;
+  appTitle?: string | null;
+  /** Custom providers: header name and `{key}` template for the API key */
+  authHeaderName?: string | null;
+  authHeaderTemplate?: string | null;
+  /** Without an API key, forward the client's own Authorization header */
+  passthroughAuth?: boolean;
+  /** JSON keys dropped or renamed in non-streaming responses */
+  responseTransform?: ResponseTransform | null;
+  /** Overrides the global upstream User-Agent */
+  userAgent?: string | null;
+  /** Azure OpenAI: deployment name (defaults to the routed model) and api-version */
+  deployment?: string | null;
+  apiVersion?: string | null;
+  /** Sent as the model for every request routed to this provider */
+  forceModel?: string | null;
+  /** Send an Idempotency-Key header, reused across retries of one request */
+  idempotencyKeys?: boolean;
+  /** Dollars per 1k prompt / completion tokens, for spend tracking */
+  inputPricePer1k?: number | null;
+  outputPricePer1k?: number | null;
+  /** Per-model prices, overriding the provider-wide ones */
+  modelPrices?: Record<string, ModelPrice>;
+  /** Once spend reaches the limit, routing skips the provider like a disabled one */
+  spendLimit?: SpendLimit | null;
+  /** Free-form labels for organizing the provider list */
+  tags?: string[];
+  group?: string | null;
   status: ProviderStatus;
   createdAt: string;
   updatedAt: string;
@@ -20,12 +58,94 @@ export interface CreateProviderInput {
   type: ProviderType;
   apiBaseUrl?: string;
   apiKey?: string;
+  agentAccount?: AgentProviderType | null;
+  maxConcurrentRequests?: number | null;
+  httpReferer?: string | null;
+  appTitle?: string | null;
+  authHeaderName?: string | null;
+  authHeaderTemplate?: string | null;
+  passthroughAuth?: boolean;
+  responseTransform?: ResponseTransform | null;
+  userAgent?: string | null;
+  deployment?: string | null;
+  apiVersion?: string | null;
+  forceModel?: string | null;
+  idempotencyKeys?: boolean;
+  inputPricePer1k?: number | null;
+  outputPricePer1k?: number | null;
+  modelPrices?: Record<string, ModelPrice>;
+  spendLimit?: SpendLimit | null;
+  tags?: string[];
+  group?: string | null;
 }
 
 export interface UpdateProviderInput {
   name?: string;
   apiBaseUrl?: string;
   apiKey?: string;
+  agentAccount?: AgentProviderType;
+  maxConcurrentRequests?: number;
+  httpReferer?: string;
+  appTitle?: string;
+  authHeaderName?: string;
+  authHeaderTemplate?: string;
+  passthroughAuth?: boolean;
+  responseTransform?: ResponseTransform | null;
+  userAgent?: string | null;
+  deployment?: string | null;
+  apiVersion?: string | null;
+  forceModel?: string | null;
+  idempotencyKeys?: boolean;
+  inputPricePer1k?: number | null;
+  outputPricePer1k?: number | null;
+  modelPrices?: Record<string, ModelPrice>;
+  spendLimit?: SpendLimit | null;
+  /** Replaces all tags */
+  tags?: string[];
+  /** An empty string removes the provider from its group */
+  group?: string;
+}
+
+/** Narrows list_providers; every set field must match */
+export interface ProviderFilter {
+  tag?: string | null;
+  group?: string | null;
+}
+
+/** Dollars per 1k tokens */
+export interface ModelPrice {
+  inputPer1k: number;
+  outputPer1k: number;
+}
+
+/** Calendar period (UTC) a spend limit applies to */
+export type SpendPeriod = "Daily" | "Monthly";
+
+export interface SpendLimit {
+  limitUsd: number;
+  period: SpendPeriod;
+}
+
+/** A provider's spend in the current period */
+export interface ProviderSpend {
+  providerId: string;
+  period: SpendPeriod;
+  /** e.g. "2026-10" or "2026-10-16" */
+  periodStart: string;
+  spentUsd: number;
+  limitUsd: number | null;
+}
+
+export interface ProviderImportError {
+  /** Position of the skipped item in the imported list */
+  index: number;
+  name: string;
+  error: string;
+}
+
+export interface ProviderImportResult {
+  created: Provider[];
+  errors: ProviderImportError[];
 }
 
 export interface ConnectionStatus {
@@ -34,15 +154,66 @@ export interface ConnectionStatus {
   error?: string;
 }
 
+/** Time to first byte of an authenticated request to the provider */
+export interface ProviderPing {
+  /** A 2xx response was received */
+  success: boolean;
+  status: number | null;
+  latencyMs: number | null;
+  error: string | null;
+}
+
 export interface AgentAccountInfo {
   agentType: AgentProviderType;
   isAuthenticated: boolean;
   email?: string | null;
+  /** The account used for this agent's requests and quota */
+  isActive: boolean;
+  /** Where the login is stored */
+  authPath: string;
+}
+
+export interface AgentOrganization {
+  /** Pass as `accountId` to get_agent_quota */
+  id: string;
+  title?: string | null;
+  isDefault: boolean;
+}
+
+/** Valid: works as is; Refreshed: fixed by a token refresh; Expired: log in again */
+export type TokenValidity = "Valid" | "Refreshed" | "Expired";
+
+export interface AgentTokenStatus {
+  agentType: AgentProviderType;
+  /** null when the check itself failed (e.g. offline), see `error` */
+  validity: TokenValidity | null;
+  error: string | null;
+}
+
+/** Present: readable; Unreadable: exists but can't be parsed (see `error`) */
+export type AuthFileStatus = "Present" | "Missing" | "Unreadable";
+
+/** Diagnostic view of a stored agent login; never contains token values */
+export interface AuthFileReport {
+  agentType: AgentProviderType;
+  authPath: string;
+  status: AuthFileStatus;
+  error: string | null;
+  email: string | null;
+  /** Access token expiry as stored (RFC 3339) */
+  expiresAt: string | null;
+  expired: boolean;
+  /** Inside the refresh window; the next use refreshes the token */
+  needsRefresh: boolean;
+  hasAccessToken: boolean;
+  hasRefreshToken: boolean;
 }
 
 export interface AgentAuthStart {
   flowId: string;
   authUrl: string;
+  /** No callback server: the code (or redirect URL) must be pasted back by hand */
+  manual: boolean;
 }
 
 export interface AgentQuota {
@@ -56,8 +227,20 @@ export interface AgentQuota {
   note?: string | null;
 }
 
+export interface QuotaHistoryPoint {
+  timestamp: number;
+  sessionUsedPercent: number;
+  weekUsedPercent: number;
+}
+
 export interface AgentQuotaEntry {
   label: string;
   usedPercent: number;
   resetAt?: number | null;
 }
+
+/** Auth files no provider's agent account uses */
+export interface OrphanAuthFiles {
+  files: string[];
+  removed: boolean;
+}
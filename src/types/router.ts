@@ -7,6 +7,9 @@ export interface RoutingRule {
   modelRewrite: string | null;
   priority: number;
   enabled: boolean;
+  translate: boolean;
+  /** Headers set on forwarded requests, overriding client values */
+  addHeaders: Record<string, string>;
   createdAt: string;
   updatedAt: string;
 }
@@ -22,6 +25,8 @@ export interface CreateRuleInput {
   matchPattern: string;
   modelRewrite?: string | null;
   enabled?: boolean;
+  translate?: boolean;
+  addHeaders?: Record<string, string>;
 }
 
 export interface UpdateRuleInput {
@@ -31,6 +36,8 @@ export interface UpdateRuleInput {
   matchPattern?: string;
   modelRewrite?: string | null;
   enabled?: boolean;
+  translate?: boolean;
+  addHeaders?: Record<string, string>;
 }
 
 export interface ResolvedProvider {
@@ -39,3 +46,57 @@ export interface ResolvedProvider {
   apiUrl: string;
   modelName: string;
 }
+
+export interface RulePreviewSample {
+  model?: string | null;
+  path: string;
+  apiGroup: ApiGroup;
+}
+
+export interface RulePreview {
+  winningRuleId: string | null;
+  isCandidate: boolean;
+}
+
+/** A rule as the proxy evaluates it, listed in match order per api group */
+export interface RoutingTableEntry {
+  /** 1-based order in which the rule is tried within its api group */
+  position: number;
+  ruleId: string;
+  apiGroup: ApiGroup;
+  ruleType: RuleType;
+  matchPattern: string;
+  priority: number;
+  providerId: string;
+  /** null when the provider no longer exists */
+  providerName: string | null;
+  modelRewrite: string | null;
+  enabled: boolean;
+  /** The rule and its provider are both enabled */
+  active: boolean;
+}
+
+export interface PatternTestResult {
+  /** Samples the pattern matches */
+  matches: string[];
+  error?: string | null;
+}
+
+export interface RuleImportError {
+  /** Position of the skipped item in the imported list */
+  index: number;
+  matchPattern: string;
+  error: string;
+}
+
+export interface RuleImportResult {
+  created: RoutingRule[];
+  errors: RuleImportError[];
+  /** Number of rules dropped as duplicates, present only when non-zero */
+  duplicatesRemoved?: number;
+}
+
+export interface RuleIssue {
+  ruleId: string;
+  message: string;
+}